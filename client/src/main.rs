@@ -28,10 +28,10 @@ use components::on_screen_log::{LogEntry, LogLevel};
 use dotenv::dotenv;
 use networking::{networking_events::NetworkingEvent, networking_ressource::NetworkingRessource};
 use protocol::{
+    api,
     gods::get_gods,
-    protocol::{Credentials, Protocol, Turn},
+    protocol::{Credentials, ErrorCode, Protocol, Turn},
 };
-use reqwest::Method;
 use std::env;
 
 mod components;
@@ -149,14 +149,10 @@ pub fn setup(
                 "Logging in as {} from env. Except during development you prob. shouldn't do this!",
                 user,
             );
-            networking.request_data(
-                Method::POST,
-                "users",
-                &Credentials {
-                    username: user,
-                    password: pass,
-                },
-            );
+            networking.send(api::login(&Credentials {
+                username: user,
+                password: pass,
+            }));
         }
     }
 }
@@ -172,6 +168,7 @@ fn wasm_get_base_url() -> Option<String> {
 
 fn networking_handler(
     mut commands: Commands,
+    networking: Res<NetworkingRessource>,
     mut ev_net: EventReader<NetworkingEvent>,
     mut ev_state_change: EventWriter<StateChangeEvent>,
     mut ev_log: EventWriter<LogEntry>,
@@ -185,9 +182,23 @@ fn networking_handler(
         });
 
         match &ev.0 {
-            Protocol::LobbyLeaveResponse => {
+            Protocol::LobbyLeaveResponse | Protocol::LobbyKickResponse => {
                 ev_state_change.send(StateChangeEvent(AppState::MenuMain))
             }
+            Protocol::GameLeaveResponse => {
+                commands.remove_resource::<GameUserRes>();
+                ev_state_change.send(StateChangeEvent(AppState::MenuMain))
+            }
+            Protocol::GameAutoplayResponse => ev_log.send(LogEntry {
+                text: "You were idle, a bot plays for you until you act again".to_string(),
+                lvl: LogLevel::Warning,
+                ..Default::default()
+            }),
+            Protocol::ServerMessageResponse(message) => ev_log.send(LogEntry {
+                text: format!("[SERVER] {}", message),
+                lvl: LogLevel::Warning,
+                ..Default::default()
+            }),
             Protocol::LobbyStatusResponse(lobby) => {
                 if let Some(timer) = lobby.start_at {
                     commands.insert_resource(TimerUi(Some(Timer::from_seconds(
@@ -219,6 +230,33 @@ fn networking_handler(
                     Turn::Shop(_, _) => ev_state_change.send(StateChangeEvent(AppState::GameShop)),
                 }
             }
+            Protocol::GameStateResponse(state) => {
+                let timer: DateTime<Utc> = state.turn.into();
+
+                commands.insert_resource(TimerUi(Some(Timer::from_seconds(
+                    timer.signed_duration_since(Utc::now()).num_seconds() as f32,
+                    TimerMode::Once,
+                ))));
+                commands.insert_resource(GameUserRes(state.user.clone()));
+
+                match (state.turn, &state.last_battle) {
+                    (Turn::Combat(0, _), _) => {
+                        commands.insert_resource(GameCommanderSelection(
+                            state
+                                .god_choices
+                                .iter()
+                                .map(|g| get_gods()[*g as usize].clone())
+                                .collect::<Vec<_>>(),
+                        ));
+                        ev_state_change.send(StateChangeEvent(AppState::GameCommanderSelection));
+                    }
+                    (Turn::Combat(_, _), Some(battle)) => {
+                        commands.insert_resource(BattleRes(battle.clone()));
+                        ev_state_change.send(StateChangeEvent(AppState::GameBattle));
+                    }
+                    _ => ev_state_change.send(StateChangeEvent(AppState::GameShop)),
+                }
+            }
             Protocol::GameUserInfoResponse(user_info) => {
                 commands.insert_resource(GameUserRes(user_info.clone()));
             }
@@ -232,9 +270,20 @@ fn networking_handler(
                 ev_state_change.send(StateChangeEvent(AppState::GameResult))
             }
             Protocol::NetworkingError(e) => {
-                if e.status == 401 {
+                // Expired sessions are refreshed by the networking plugin
+                let refreshable =
+                    e.code == ErrorCode::InvalidSession && networking.refresh_key.is_some();
+                if (e.status == 401 && !refreshable) || e.code == ErrorCode::AccountBanned {
                     ev_state_change.send(StateChangeEvent(AppState::MenuLogin))
                 }
+
+                if e.code != ErrorCode::Unknown {
+                    ev_log.send(LogEntry {
+                        text: e.message.clone(),
+                        lvl: LogLevel::Warning,
+                        ..Default::default()
+                    });
+                }
             }
             _ => {}
         };
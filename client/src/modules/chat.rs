@@ -0,0 +1,115 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use protocol::{
+    api,
+    protocol::{ChatChannel, ChatMessage, Protocol, MAX_CHAT_MESSAGE_LENGTH},
+};
+
+use crate::{
+    networking::{networking_events::NetworkingEvent, networking_ressource::NetworkingRessource},
+    states::menu_login::User,
+    AppState,
+};
+
+/// Messages shown in the chat window
+const CHAT_LOG_SIZE: usize = 50;
+
+pub(crate) struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChatLog>()
+            .init_resource::<ChatInput>()
+            .add_system(on_network)
+            .add_system(load_lobby_chat.in_schedule(OnEnter(AppState::Lobby)))
+            .add_system(load_game_chat.in_schedule(OnEnter(AppState::GameShop)))
+            .add_system(
+                ui_chat.run_if(in_state(AppState::Lobby).or_else(in_state(AppState::GameShop))),
+            );
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct ChatLog(pub Vec<ChatMessage>);
+
+#[derive(Resource, Default)]
+struct ChatInput(String);
+
+fn load_lobby_chat(mut network: ResMut<NetworkingRessource>) {
+    network.send(api::get_lobby_chat());
+}
+
+fn load_game_chat(mut network: ResMut<NetworkingRessource>) {
+    network.send(api::get_game_chat());
+}
+
+fn ui_chat(
+    mut contexts: EguiContexts,
+    mut network: ResMut<NetworkingRessource>,
+    mut input: ResMut<ChatInput>,
+    log: Res<ChatLog>,
+    user: Option<Res<User>>,
+    state: Res<State<AppState>>,
+) {
+    let channel = if state.0 == AppState::Lobby {
+        ChatChannel::Lobby
+    } else {
+        ChatChannel::Game
+    };
+    let own_id = user.map(|user| user.0.id);
+
+    egui::Window::new("Chat")
+        .anchor(egui::Align2::LEFT_BOTTOM, [8.0, -8.0])
+        .default_width(300.0)
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(150.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for message in log.0.iter().filter(|m| m.channel == channel) {
+                        ui.horizontal_wrapped(|ui| {
+                            ui.strong(&message.from);
+                            ui.label(&message.text);
+                            if Some(message.from_id) != own_id && ui.small_button("Mute").clicked()
+                            {
+                                network.send(api::mute_user(message.from_id));
+                            }
+                        });
+                    }
+                });
+            ui.separator();
+            let response = ui.text_edit_singleline(&mut input.0);
+            if let Some((end, _)) = input.0.char_indices().nth(MAX_CHAT_MESSAGE_LENGTH) {
+                input.0.truncate(end);
+            }
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (submitted || ui.button("Send").clicked()) && !input.0.trim().is_empty() {
+                match channel {
+                    ChatChannel::Lobby => network.send(api::post_lobby_message(&input.0)),
+                    ChatChannel::Game => network.send(api::post_game_message(&input.0)),
+                }
+                input.0.clear();
+            }
+        });
+}
+
+fn on_network(mut ev_networking: EventReader<NetworkingEvent>, mut log: ResMut<ChatLog>) {
+    for ev in ev_networking.iter() {
+        match &ev.0 {
+            Protocol::ChatHistoryResponse(history) => {
+                log.0 = history.clone();
+            }
+            Protocol::ChatMessageResponse(message) => {
+                log.0.push(message.clone());
+                let overflow = log.0.len().saturating_sub(CHAT_LOG_SIZE);
+                log.0.drain(..overflow);
+            }
+            Protocol::MutesResponse(mutes) => {
+                log.0
+                    .retain(|message| !mutes.iter().any(|muted| muted.id == message.from_id));
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use protocol::{
+    api,
+    protocol::{Friend, FriendStatus, Presence, Protocol},
+};
+
+use crate::{
+    networking::{networking_events::NetworkingEvent, networking_ressource::NetworkingRessource},
+    AppState,
+};
+
+pub(crate) struct FriendsPlugin;
+
+impl Plugin for FriendsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Friends>()
+            .init_resource::<AddFriendForm>()
+            .add_system(on_network)
+            .add_system(load_friends.in_schedule(OnEnter(AppState::MenuMain)))
+            .add_system(
+                ui_friends.run_if(in_state(AppState::MenuMain).or_else(in_state(AppState::Lobby))),
+            );
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct Friends(pub Vec<Friend>);
+
+#[derive(Resource, Default)]
+struct AddFriendForm(String);
+
+fn load_friends(mut network: ResMut<NetworkingRessource>) {
+    network.send(api::get_friends());
+}
+
+fn presence_label(presence: Presence) -> &'static str {
+    match presence {
+        Presence::Offline => "offline",
+        Presence::Menu => "online",
+        Presence::Lobby => "in lobby",
+        Presence::Game => "in game",
+    }
+}
+
+fn ui_friends(
+    mut contexts: EguiContexts,
+    mut network: ResMut<NetworkingRessource>,
+    mut form: ResMut<AddFriendForm>,
+    friends: Res<Friends>,
+    state: Res<State<AppState>>,
+) {
+    egui::Window::new("Friends")
+        .anchor(egui::Align2::LEFT_TOP, [8.0, 8.0])
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for friend in friends.0.iter() {
+                ui.horizontal(|ui| match friend.status {
+                    FriendStatus::Friend => {
+                        ui.label(format!(
+                            "{} ({})",
+                            friend.name,
+                            presence_label(friend.presence)
+                        ));
+                        if state.0 == AppState::Lobby
+                            && matches!(friend.presence, Presence::Menu | Presence::Lobby)
+                            && ui.button("Invite").clicked()
+                        {
+                            network.send(api::invite_user(&friend.name));
+                        }
+                        if ui.button("Remove").clicked() {
+                            network.send(api::remove_friend(friend.id));
+                        }
+                    }
+                    FriendStatus::Incoming => {
+                        ui.label(format!("{} wants to be friends", friend.name));
+                        if ui.button("Accept").clicked() {
+                            network.send(api::accept_friend(friend.id));
+                        }
+                        if ui.button("Decline").clicked() {
+                            network.send(api::remove_friend(friend.id));
+                        }
+                    }
+                    FriendStatus::Outgoing => {
+                        ui.label(format!("{} (pending)", friend.name));
+                        if ui.button("Cancel").clicked() {
+                            network.send(api::remove_friend(friend.id));
+                        }
+                    }
+                });
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut form.0);
+                if ui.button("Add").clicked() && !form.0.is_empty() {
+                    network.send(api::add_friend(&form.0));
+                    form.0.clear();
+                }
+            });
+        });
+}
+
+fn on_network(mut ev_networking: EventReader<NetworkingEvent>, mut friends: ResMut<Friends>) {
+    for ev in ev_networking.iter() {
+        match &ev.0 {
+            Protocol::FriendsResponse(list) => {
+                friends.0 = list.clone();
+            }
+            Protocol::FriendPresenceResponse(update) => {
+                if let Some(friend) = friends.0.iter_mut().find(|f| f.id == update.id) {
+                    friend.presence = update.presence;
+                }
+            }
+            _ => {}
+        }
+    }
+}
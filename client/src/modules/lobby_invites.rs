@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use protocol::{
+    api,
+    protocol::{LobbyInvite, Protocol},
+};
+
+use crate::{
+    networking::{networking_events::NetworkingEvent, networking_ressource::NetworkingRessource},
+    AppState,
+};
+
+pub(crate) struct LobbyInvitesPlugin;
+
+impl Plugin for LobbyInvitesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LobbyInvites>()
+            .add_system(on_network)
+            .add_system(load_invites.in_schedule(OnEnter(AppState::MenuMain)))
+            .add_system(
+                ui_invites.run_if(
+                    in_state(AppState::MenuMain).or_else(in_state(AppState::DialogLobbyJoin)),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct LobbyInvites(pub Vec<LobbyInvite>);
+
+fn load_invites(mut network: ResMut<NetworkingRessource>) {
+    network.send(api::get_invites());
+}
+
+fn ui_invites(
+    mut contexts: EguiContexts,
+    mut network: ResMut<NetworkingRessource>,
+    mut invites: ResMut<LobbyInvites>,
+) {
+    if invites.0.is_empty() {
+        return;
+    }
+
+    let mut answered = vec![];
+    egui::Window::new("Invites")
+        .anchor(egui::Align2::RIGHT_TOP, [-8.0, 8.0])
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            for invite in invites.0.iter() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} invited you to {}", invite.from, invite.lobby));
+                    if ui.button("Accept").clicked() {
+                        network.send(api::accept_invite(invite.id));
+                        answered.push(invite.id);
+                    }
+                    if ui.button("Decline").clicked() {
+                        network.send(api::decline_invite(invite.id));
+                        answered.push(invite.id);
+                    }
+                });
+            }
+        });
+
+    invites.0.retain(|invite| !answered.contains(&invite.id));
+}
+
+fn on_network(mut ev_networking: EventReader<NetworkingEvent>, mut invites: ResMut<LobbyInvites>) {
+    for ev in ev_networking.iter() {
+        match &ev.0 {
+            Protocol::LobbyInviteResponse(invite) => {
+                invites.0.retain(|i| i.id != invite.id);
+                invites.0.push(invite.clone());
+            }
+            Protocol::LobbyInvitesResponse(list) => {
+                invites.0 = list.clone();
+            }
+            _ => {}
+        }
+    }
+}
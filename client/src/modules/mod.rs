@@ -1,15 +1,21 @@
 use bevy::prelude::*;
 
 pub(crate) mod character;
+pub(crate) mod chat;
+pub(crate) mod friends;
 pub(crate) mod game_user_info;
 pub(crate) mod god;
+pub(crate) mod lobby_invites;
 
 pub(crate) struct ModulesPlugin;
 
 impl Plugin for ModulesPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(character::CharacterPlugin)
+            .add_plugin(chat::ChatPlugin)
+            .add_plugin(friends::FriendsPlugin)
             .add_plugin(game_user_info::GameUserInfoPlugin)
-            .add_plugin(god::GodPlugin);
+            .add_plugin(god::GodPlugin)
+            .add_plugin(lobby_invites::LobbyInvitesPlugin);
     }
 }
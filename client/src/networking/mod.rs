@@ -3,6 +3,7 @@ pub mod networking_ressource;
 pub mod networking_systems;
 pub mod polling;
 pub mod util;
+pub mod websocket;
 
 pub mod networking_plugin {
     use bevy::{
@@ -17,7 +18,10 @@ pub mod networking_plugin {
         networking_events::NetworkingEvent,
         networking_ressource::{NetworkingRessource, ServerUrl},
         networking_systems::*,
-        polling::{on_polling_status_change, polling_poller, PollingStatus},
+        polling::{
+            on_polling_status_change, polling_poller, EventSequence, PollingStatus, Transport,
+        },
+        websocket::websocket_poller,
     };
 
     pub struct NetworkingPlugin(pub(crate) String);
@@ -42,6 +46,9 @@ pub mod networking_plugin {
                 .build()
                 .unwrap();
 
+            let transport = Transport::from_env();
+            debug!("Using {:?} transport", transport);
+
             let mut rate_limit_timer = Timer::from_seconds(1.0, bevy::time::TimerMode::Once);
             rate_limit_timer.pause();
 
@@ -54,11 +61,15 @@ pub mod networking_plugin {
                 ))
                 .insert_resource(Runtime(runtime))
                 .insert_resource(RateLimitTimer(rate_limit_timer))
+                .insert_resource(transport)
+                .init_resource::<EventSequence>()
                 .init_resource::<NetworkingRessource>()
                 .add_event::<PollingStatus>()
                 .add_system(request_dispatcher)
                 .add_system(request_poller)
+                .add_system(session_refresher)
                 .add_system(polling_poller)
+                .add_system(websocket_poller)
                 .add_system(on_polling_status_change);
         }
     }
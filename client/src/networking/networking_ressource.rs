@@ -1,11 +1,20 @@
-use std::{fmt::Debug, time::Duration};
+use std::time::Duration;
 
+use super::util::get_option;
+use async_channel::Sender;
 use bevy::{
-    log::debug,
+    log::{debug, warn},
     prelude::{FromWorld, Resource, World},
 };
-use reqwest::{header::HeaderMap, Client, ClientBuilder, Method, Request, RequestBuilder, Url};
-use serde::Serialize;
+use protocol::{
+    api::{Access, ApiRequest},
+    codec::Encoding,
+    protocol::Protocol,
+};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, ACCEPT},
+    Client, ClientBuilder, Method, Request, RequestBuilder, Url,
+};
 
 #[derive(Resource)]
 pub struct ServerUrl(pub Url);
@@ -23,6 +32,14 @@ pub struct NetworkingRessource {
     pub requests: Vec<Request>,
     pub base_url: Url,
     pub headers: HeaderMap,
+    /// Preferred response encoding. Set `ENCODING=json` for readable traffic.
+    pub encoding: Encoding,
+    /// Exchanged for a new session once the current one expired
+    pub refresh_key: Option<String>,
+    pub refreshing_session: bool,
+    /// Authenticated requests are sent over the websocket while it is open
+    pub websocket: Option<Sender<Protocol>>,
+    next_call_id: u64,
 }
 
 impl FromWorld for NetworkingRessource {
@@ -61,33 +78,75 @@ impl NetworkingRessource {
             requests: vec![],
             base_url,
             headers: HeaderMap::new(),
+            encoding: get_option("encoding")
+                .and_then(|encoding| encoding.parse().ok())
+                .unwrap_or_default(),
+            refresh_key: None,
+            refreshing_session: false,
+            websocket: None,
+            next_call_id: 0,
         }
     }
 
-    pub fn request(&mut self, method: Method, url: &str) {
-        self.requests.push(
-            self.get_request(method, self.base_url.join(url).unwrap().as_str())
-                .build()
-                .unwrap(),
-        )
+    pub fn set_session(&mut self, key: &str, refresh_key: &str) {
+        match HeaderValue::from_str(key) {
+            Ok(key) => {
+                self.headers.insert("x-api-key", key);
+            }
+            Err(err) => warn!("Received invalid session key: {:?}", err),
+        }
+        self.refresh_key = Some(refresh_key.to_string());
+        self.refreshing_session = false;
+    }
+
+    pub fn clear_session(&mut self) {
+        self.headers.remove("x-api-key");
+        self.refresh_key = None;
+        self.refreshing_session = false;
     }
 
-    pub fn request_data<T: Serialize + Debug>(&mut self, method: Method, url: &str, data: &T) {
+    /// Queues a request built by one of the `protocol::api` functions
+    pub fn send(&mut self, request: ApiRequest) {
         debug!(
             "[NET] {} Request to \"{}\" with data {:?}",
-            method, url, data
+            request.endpoint.method.as_str(),
+            request.path,
+            request.body
         );
+        if let Some(websocket) = self
+            .websocket
+            .as_ref()
+            .filter(|_| request.endpoint.access != Access::Public)
+        {
+            self.next_call_id += 1;
+            let call = Protocol::ApiCall(request.clone().into_call(self.next_call_id));
+            if websocket.try_send(call).is_ok() {
+                return;
+            }
+        }
+
         self.requests.push(
-            self.get_request(method, self.base_url.join(url).unwrap().as_str())
-                .json(data)
+            self.api_request(&request)
                 .build()
-                .expect("Failed to parse json request body"),
+                .expect("Failed to build api request"),
         )
     }
 
+    pub fn api_request(&self, request: &ApiRequest) -> RequestBuilder {
+        let method = Method::from_bytes(request.endpoint.method.as_str().as_bytes())
+            .expect("Api methods are valid http methods");
+        let builder = self.get_request(method, &request.path);
+
+        match &request.body {
+            Some(body) => builder.json(body),
+            None => builder,
+        }
+    }
+
     pub fn get_request(&self, method: Method, url: &str) -> RequestBuilder {
         self.client
             .request(method, self.base_url.join(url).unwrap().as_str())
             .headers(self.headers.clone())
+            .header(ACCEPT, self.encoding.content_type())
     }
 }
@@ -1,6 +1,10 @@
 use crate::networking::util::get_task;
 use async_channel::Receiver;
 use bevy::prelude::*;
+use protocol::{
+    api,
+    protocol::{ErrorCode, Protocol},
+};
 use std::sync::Arc;
 
 use super::{
@@ -42,3 +46,35 @@ pub(crate) fn request_poller(
         }
     }
 }
+
+/// Exchanges the refresh key for a new session once the session expired
+pub(crate) fn session_refresher(
+    mut res: ResMut<NetworkingRessource>,
+    mut ev: EventReader<NetworkingEvent>,
+) {
+    for ev in ev.iter() {
+        match &ev.0 {
+            Protocol::SessionResponse(session) => {
+                debug!("Session refreshed");
+                res.set_session(&session.key, &session.refresh_key);
+            }
+            Protocol::NetworkingError(err) if err.code == ErrorCode::InvalidSession => {
+                if res.refreshing_session {
+                    continue;
+                }
+                if let Some(refresh_key) = res.refresh_key.clone() {
+                    debug!("Session expired, refreshing");
+                    res.refreshing_session = true;
+                    res.send(api::refresh_session(&refresh_key));
+                }
+            }
+            Protocol::NetworkingError(err) if res.refreshing_session => {
+                if err.code == ErrorCode::Unauthorized {
+                    res.clear_session();
+                }
+                res.refreshing_session = false;
+            }
+            _ => {}
+        }
+    }
+}
@@ -1,13 +1,15 @@
-use crate::networking::util::get_task;
+use crate::networking::util::{get_option, get_task};
 use async_channel::Receiver;
 use bevy::prelude::*;
-use protocol::protocol::Protocol;
-use reqwest::Method;
+use protocol::{api, protocol::Protocol};
+use reqwest::Request;
 use serde::__private::de;
 
 use super::{
-    networking_events::NetworkingEvent, networking_plugin::Runtime,
+    networking_events::NetworkingEvent,
+    networking_plugin::Runtime,
     networking_ressource::NetworkingRessource,
+    websocket::{self, get_websocket_protocols, get_websocket_url, WebSocketReceiver},
 };
 
 #[derive(Component, Debug)]
@@ -16,28 +18,73 @@ pub struct PollingReceiver(Receiver<NetworkingEvent>);
 #[derive(Resource, Debug)]
 pub struct RateLimitTimer(pub Timer);
 
+/// Sequence id of the last received server event
+#[derive(Resource, Debug, Default)]
+pub struct EventSequence(pub Option<u64>);
+
 pub(crate) enum PollingStatus {
     Start,
     Stop,
 }
 
+/// How server events are received. Websockets fall back to long polling if the
+/// connection can not be established or is lost.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Transport {
+    #[default]
+    WebSocket,
+    Polling,
+}
+
+impl Transport {
+    /// Reads the transport from the `TRANSPORT` env variable or the
+    /// `transport` query parameter
+    pub fn from_env() -> Self {
+        Self::parse(get_option("transport").as_deref())
+    }
+
+    fn parse(transport: Option<&str>) -> Self {
+        match transport {
+            Some("polling") => Transport::Polling,
+            _ => Transport::default(),
+        }
+    }
+}
+
 pub(crate) fn on_polling_status_change(
     mut commands: Commands,
     mut ev_polling_status: EventReader<PollingStatus>,
-    res: Res<NetworkingRessource>,
-    query_poller: Query<Entity, With<PollingReceiver>>,
+    mut res: ResMut<NetworkingRessource>,
+    query_poller: Query<Entity, Or<(With<PollingReceiver>, With<WebSocketReceiver>)>>,
     runtime: Res<Runtime>,
+    mut transport: ResMut<Transport>,
+    mut sequence: ResMut<EventSequence>,
 ) {
     for ev in ev_polling_status.iter() {
         match ev {
             PollingStatus::Start => {
+                if *transport == Transport::WebSocket {
+                    let url = get_websocket_url(&res, &sequence);
+                    if let (Some(url), Some(protocols)) = (url, get_websocket_protocols(&res)) {
+                        let receiver = websocket::connect(&runtime, url, protocols, res.encoding);
+                        res.websocket = Some(receiver.sender());
+                        commands.spawn(receiver);
+                        continue;
+                    }
+
+                    warn!("Could not build websocket url. Falling back to long polling.");
+                    *transport = Transport::Polling;
+                }
+
                 commands.spawn_empty().insert(PollingReceiver(get_task(
                     &runtime,
                     &res.polling_client,
-                    res.get_request(Method::GET, "poll").build().unwrap(),
+                    get_poll_request(&res, &sequence),
                 )));
             }
             PollingStatus::Stop => {
+                sequence.0 = None;
+                res.websocket = None;
                 query_poller
                     .iter()
                     .for_each(|p| commands.entity(p).despawn_recursive());
@@ -53,6 +100,7 @@ pub(crate) fn polling_poller(
     res: Res<NetworkingRessource>,
     runtime: Res<Runtime>,
     mut res_rate_limit_timer: ResMut<RateLimitTimer>,
+    mut sequence: ResMut<EventSequence>,
     time: Res<Time>,
 ) {
     res_rate_limit_timer.0.tick(time.delta());
@@ -74,15 +122,49 @@ pub(crate) fn polling_poller(
                 res_rate_limit_timer.0.reset();
             }
 
+            send_events(event, &mut sequence, &mut ev);
             commands.entity(entity).insert(PollingReceiver(get_task(
                 &runtime,
                 &res.polling_client,
-                res.get_request(Method::GET, "poll").build().unwrap(),
+                get_poll_request(&res, &sequence),
             )));
-            ev.send(event);
         } else if receiver.0.is_closed() {
             warn!("Removing entity {:?} with closed receiver. This could indicate networking requests failing.", entity);
             commands.entity(entity).despawn_recursive();
         }
     }
 }
+
+fn get_poll_request(res: &NetworkingRessource, sequence: &EventSequence) -> Request {
+    res.api_request(&api::poll(sequence.0)).build().unwrap()
+}
+
+/// Unpacks event batches into single networking events. Events that were
+/// already received are skipped. Returns the new sequence id for batches.
+pub(crate) fn send_events(
+    event: NetworkingEvent,
+    sequence: &mut EventSequence,
+    ev: &mut EventWriter<NetworkingEvent>,
+) -> Option<u64> {
+    let Protocol::EventBatchResponse(batch) = event.0 else {
+        ev.send(event);
+        return None;
+    };
+
+    if let Some(snapshot) = batch.snapshot {
+        debug!("Rebuilding state from snapshot {:?}", snapshot);
+        ev.send_batch(snapshot.into_iter().map(NetworkingEvent));
+    }
+
+    let since = sequence.0.unwrap_or_default();
+    ev.send_batch(
+        batch
+            .events
+            .into_iter()
+            .filter(|e| e.seq > since)
+            .map(|e| NetworkingEvent(e.event)),
+    );
+
+    sequence.0 = Some(batch.last_seq);
+    Some(batch.last_seq)
+}
@@ -1,13 +1,34 @@
 use super::{networking_events::NetworkingEvent, networking_plugin::Runtime};
 use async_channel::Receiver;
 use bevy::prelude::*;
-use protocol::protocol::{Error, Protocol};
-use reqwest::{Client, Request, Response, StatusCode};
+use protocol::{
+    codec::Encoding,
+    protocol::{Error, Protocol},
+};
+use reqwest::{header::CONTENT_TYPE, Client, Request, Response, StatusCode};
 use std::sync::Arc;
 
 #[cfg(target_arch = "wasm32")]
 pub(crate) use wasm_bindgen_futures::spawn_local as spawn;
 
+/// Reads a client option from the upper case env variable
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn get_option(name: &str) -> Option<String> {
+    std::env::var(name.to_uppercase()).ok()
+}
+
+/// Reads a client option from the query parameters of the page
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn get_option(name: &str) -> Option<String> {
+    let search = web_sys::window().and_then(|w| w.location().search().ok())?;
+    let prefix = format!("{}=", name);
+    search
+        .trim_start_matches('?')
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(prefix.as_str()))
+        .map(|value| value.to_string())
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) fn get_task(
     runtime: &Runtime,
@@ -69,15 +90,29 @@ async fn decode_response(res: Response, path: &str) -> Protocol {
 
     match res.status() {
         StatusCode::NO_CONTENT => Protocol::EMPTY(path.to_string()),
-        StatusCode::UNAUTHORIZED => {
-            Error::new_protocol(res.status().into(), "Unauthorized".to_string())
+        status => {
+            let encoding = res
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .and_then(Encoding::from_content_type)
+                .unwrap_or(Encoding::Json);
+            let body = match res.bytes().await {
+                Ok(body) => body,
+                Err(err) => {
+                    return Error::new_protocol(
+                        err.status().unwrap_or(StatusCode::IM_A_TEAPOT).into(),
+                        err.to_string(),
+                    )
+                }
+            };
+            match encoding.decode(&body) {
+                Ok(protocol) => protocol,
+                Err(_) if status == StatusCode::UNAUTHORIZED => {
+                    Error::new_protocol(status.into(), "Unauthorized".to_string())
+                }
+                Err(err) => Error::new_protocol(status.into(), err.to_string()),
+            }
         }
-        _ => match res.json().await {
-            Ok(protocol) => protocol,
-            Err(err) => Error::new_protocol(
-                err.status().unwrap_or(StatusCode::IM_A_TEAPOT).into(),
-                err.to_string(),
-            ),
-        },
     }
 }
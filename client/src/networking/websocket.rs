@@ -0,0 +1,251 @@
+use async_channel::{Receiver, Sender};
+use bevy::prelude::*;
+use protocol::{api, codec::Encoding, protocol::Protocol};
+use reqwest::Url;
+
+use super::{
+    networking_events::NetworkingEvent,
+    networking_plugin::Runtime,
+    networking_ressource::NetworkingRessource,
+    polling::{send_events, EventSequence, PollingStatus, Transport},
+};
+
+/// Subprotocol the server selects for every connection
+const PROTOCOL: &str = "rog.v1";
+
+/// Open websocket connection. Events received from the server are read from
+/// `events`, messages for the server are queued in `messages`.
+#[derive(Component, Debug)]
+pub struct WebSocketReceiver {
+    events: Receiver<NetworkingEvent>,
+    messages: Sender<Protocol>,
+}
+
+impl WebSocketReceiver {
+    pub fn send(&self, message: Protocol) {
+        if let Err(err) = self.messages.try_send(message) {
+            warn!("Failed to queue websocket message {:?}", err);
+        }
+    }
+
+    pub fn sender(&self) -> Sender<Protocol> {
+        self.messages.clone()
+    }
+}
+
+pub(crate) fn get_websocket_url(
+    res: &NetworkingRessource,
+    sequence: &EventSequence,
+) -> Option<Url> {
+    let encoding = match res.encoding {
+        Encoding::Json => "json",
+        Encoding::MessagePack => "msgpack",
+    };
+    let mut url = res
+        .base_url
+        .join(&api::connect(sequence.0, Some(encoding)).path)
+        .ok()?;
+    let scheme = match url.scheme() {
+        "https" => "wss",
+        _ => "ws",
+    };
+    url.set_scheme(scheme).ok()?;
+
+    Some(url)
+}
+
+/// Browsers can not set headers for websockets, so the session key is offered
+/// as a subprotocol. Keeps it out of the url and the server logs.
+pub(crate) fn get_websocket_protocols(res: &NetworkingRessource) -> Option<[String; 2]> {
+    let key = res.headers.get("x-api-key")?.to_str().ok()?;
+
+    Some([PROTOCOL.to_string(), format!("key.{}", key)])
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn connect(
+    runtime: &Runtime,
+    url: Url,
+    protocols: [String; 2],
+    encoding: Encoding,
+) -> WebSocketReceiver {
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::{
+        connect_async,
+        tungstenite::{client::IntoClientRequest, http::HeaderValue, Message},
+    };
+
+    let (event_sender, events) = async_channel::unbounded();
+    let (messages, message_receiver) = async_channel::unbounded::<Protocol>();
+
+    runtime.0.spawn(async move {
+        let request = url.as_str().into_client_request().and_then(|mut request| {
+            let protocols = HeaderValue::from_str(&protocols.join(", "))?;
+            request
+                .headers_mut()
+                .insert("Sec-WebSocket-Protocol", protocols);
+            Ok(request)
+        });
+        let socket = match request {
+            Ok(request) => connect_async(request).await,
+            Err(err) => Err(err),
+        };
+        let socket = match socket {
+            Ok((socket, _)) => socket,
+            Err(err) => {
+                warn!("Failed to open websocket: {:?}", err);
+                return;
+            }
+        };
+        debug!("Websocket connected");
+
+        let (mut sink, mut stream) = socket.split();
+        loop {
+            tokio::select! {
+                message = stream.next() => match message {
+                    Some(Ok(message @ (Message::Text(_) | Message::Binary(_)))) => {
+                        let protocol = match message {
+                            Message::Text(text) => Encoding::Json.decode::<Protocol>(text.as_bytes()),
+                            message => Encoding::MessagePack.decode::<Protocol>(&message.into_data()),
+                        };
+                        match protocol {
+                            Ok(protocol) => {
+                                if event_sender.send(NetworkingEvent(protocol)).await.is_err() {
+                                    break;
+                                }
+                            }
+                            Err(err) => warn!("Failed to decode websocket message {:?}", err),
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+                message = message_receiver.recv() => {
+                    let Ok(message) = message else {
+                        // Receiver was despawned
+                        break;
+                    };
+                    let Ok(data) = encoding.encode(&message) else {
+                        continue;
+                    };
+                    let message = match encoding {
+                        Encoding::Json => Message::Text(String::from_utf8_lossy(&data).into_owned()),
+                        Encoding::MessagePack => Message::Binary(data),
+                    };
+                    if sink.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let _ = sink.close().await;
+        debug!("Websocket closed");
+    });
+
+    WebSocketReceiver { events, messages }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn connect(
+    _runtime: &Runtime,
+    url: Url,
+    protocols: [String; 2],
+    encoding: Encoding,
+) -> WebSocketReceiver {
+    use super::util::spawn;
+    use js_sys::{Array, ArrayBuffer, Uint8Array};
+    use wasm_bindgen::{prelude::Closure, JsCast, JsValue};
+    use web_sys::{BinaryType, CloseEvent, MessageEvent, WebSocket};
+
+    let (event_sender, events) = async_channel::unbounded();
+    let (messages, message_receiver) = async_channel::unbounded::<Protocol>();
+
+    let protocols = protocols.iter().map(JsValue::from).collect::<Array>();
+    let socket = match WebSocket::new_with_str_sequence(url.as_str(), &protocols) {
+        Ok(socket) => socket,
+        Err(err) => {
+            warn!("Failed to open websocket: {:?}", err);
+            event_sender.close();
+            return WebSocketReceiver { events, messages };
+        }
+    };
+    socket.set_binary_type(BinaryType::Arraybuffer);
+
+    let sender = event_sender.clone();
+    let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |e: MessageEvent| {
+        let data = e.data();
+        let protocol = if let Some(text) = data.as_string() {
+            Encoding::Json.decode::<Protocol>(text.as_bytes())
+        } else if let Some(buffer) = data.dyn_ref::<ArrayBuffer>() {
+            Encoding::MessagePack.decode::<Protocol>(&Uint8Array::new(buffer).to_vec())
+        } else {
+            return;
+        };
+        match protocol {
+            Ok(protocol) => {
+                let _ = sender.try_send(NetworkingEvent(protocol));
+            }
+            Err(err) => warn!("Failed to decode websocket message {:?}", err),
+        }
+    });
+    socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    on_message.forget();
+
+    let sender = event_sender;
+    let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |_: CloseEvent| {
+        debug!("Websocket closed");
+        sender.close();
+    });
+    socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+    on_close.forget();
+
+    spawn(async move {
+        while let Ok(message) = message_receiver.recv().await {
+            let Ok(data) = encoding.encode(&message) else {
+                continue;
+            };
+            let result = match encoding {
+                Encoding::Json => socket.send_with_str(&String::from_utf8_lossy(&data)),
+                Encoding::MessagePack => socket.send_with_u8_array(&data),
+            };
+            if let Err(err) = result {
+                warn!("Failed to send websocket message {:?}", err);
+            }
+        }
+        let _ = socket.close();
+    });
+
+    WebSocketReceiver { events, messages }
+}
+
+pub(crate) fn websocket_poller(
+    mut commands: Commands,
+    mut res: ResMut<NetworkingRessource>,
+    mut ev: EventWriter<NetworkingEvent>,
+    mut ev_polling_status: EventWriter<PollingStatus>,
+    mut transport: ResMut<Transport>,
+    mut sequence: ResMut<EventSequence>,
+    receivers: Query<(Entity, &WebSocketReceiver)>,
+) {
+    for (entity, receiver) in receivers.iter() {
+        while let Ok(event) = receiver.events.try_recv() {
+            debug!("Sending networking event {:?}", event);
+            let event = match event.0 {
+                Protocol::ApiReply(_, reply) => NetworkingEvent(*reply),
+                event => NetworkingEvent(event),
+            };
+            if let Some(seq) = send_events(event, &mut sequence, &mut ev) {
+                receiver.send(Protocol::EventAck(seq));
+            }
+        }
+
+        if receiver.events.is_closed() {
+            warn!("Websocket connection lost. Falling back to long polling.");
+            commands.entity(entity).despawn_recursive();
+            res.websocket = None;
+            *transport = Transport::Polling;
+            ev_polling_status.send(PollingStatus::Start);
+        }
+    }
+}
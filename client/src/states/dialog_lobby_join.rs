@@ -6,8 +6,10 @@ use crate::{
 };
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
-use protocol::protocol::{LobbyJoinRequest, Protocol};
-use reqwest::Method;
+use protocol::{
+    api,
+    protocol::{LobbyJoinRequest, LobbyListEntry, Protocol},
+};
 
 const STATE: AppState = AppState::DialogLobbyJoin;
 pub(crate) struct DialogLobbyJoinPlugin;
@@ -15,6 +17,8 @@ pub(crate) struct DialogLobbyJoinPlugin;
 impl Plugin for DialogLobbyJoinPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<LobbyJoin>()
+            .init_resource::<LobbyList>()
+            .add_system(setup.in_schedule(OnEnter(STATE)))
             .add_systems((ui_lobby_join_dialog, on_join).in_set(OnUpdate(STATE)))
             .add_system(cleanup_system::<Cleanup>.in_schedule(OnExit(STATE)));
     }
@@ -23,14 +27,45 @@ impl Plugin for DialogLobbyJoinPlugin {
 #[derive(Resource, Default)]
 struct LobbyJoin(LobbyJoinRequest);
 
+#[derive(Resource, Default)]
+struct LobbyList(Vec<LobbyListEntry>);
+
+fn setup(mut network: ResMut<NetworkingRessource>) {
+    network.send(api::list_lobbies());
+}
+
 fn ui_lobby_join_dialog(
     mut contexts: EguiContexts,
     mut network: ResMut<NetworkingRessource>,
     mut lobby: ResMut<LobbyJoin>,
+    lobby_list: Res<LobbyList>,
     mut ev_state_change: EventWriter<StateChangeEvent>,
 ) {
     let ctx = contexts.ctx_mut();
     egui::CentralPanel::default().show(ctx, |ui| {
+        ui.heading("Lobbies");
+        ui.separator();
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .show(ui, |ui| {
+                egui::Grid::new("lobby_list").striped(true).show(ui, |ui| {
+                    for entry in lobby_list.0.iter() {
+                        ui.label(&entry.name);
+                        ui.label(format!("{}/{}", entry.players, entry.settings.max_players));
+                        ui.label(format!("{:?}", entry.settings.rules));
+                        ui.label(if entry.has_passphrase { "🔒" } else { "" });
+                        if ui.button("Select").clicked() {
+                            lobby.0.name = entry.name.clone();
+                            lobby.0.passphrase.clear();
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        if ui.button("Refresh").clicked() {
+            network.send(api::list_lobbies());
+        }
+
         ui.heading("Join Lobby");
         ui.separator();
         ui.horizontal(|ui| {
@@ -44,7 +79,7 @@ fn ui_lobby_join_dialog(
         ui.separator();
         ui.horizontal(|ui| {
             if ui.button("Join").clicked() {
-                network.request_data(Method::PUT, "lobbies", &lobby.0);
+                network.send(api::join_lobby(&lobby.0));
             }
             if ui.button("Cancel").clicked() {
                 ev_state_change.send(StateChangeEvent(AppState::MenuMain));
@@ -57,14 +92,21 @@ fn on_join(
     mut commands: Commands,
     mut ev_networking: EventReader<NetworkingEvent>,
     mut ev_state_change: EventWriter<StateChangeEvent>,
+    mut lobby_list: ResMut<LobbyList>,
 ) {
     for ev in ev_networking.iter() {
-        if let Protocol::LobbyStatusResponse(lobby) = &ev.0 {
-            debug!("Got lobby response {:?}", lobby);
+        match &ev.0 {
+            Protocol::LobbyStatusResponse(lobby) => {
+                debug!("Got lobby response {:?}", lobby);
 
-            commands.insert_resource(CurrentLobby(lobby.clone()));
+                commands.insert_resource(CurrentLobby(lobby.clone()));
 
-            ev_state_change.send(StateChangeEvent(AppState::Lobby));
+                ev_state_change.send(StateChangeEvent(AppState::Lobby));
+            }
+            Protocol::LobbyListResponse(lobbies) => {
+                lobby_list.0 = lobbies.clone();
+            }
+            _ => {}
         }
     }
 }
@@ -11,10 +11,10 @@ use crate::{
 };
 use bevy::prelude::*;
 use protocol::{
+    api,
     protocol::Protocol,
     protocol_types::heros::{self, God},
 };
-use reqwest::Method;
 
 const STATE: AppState = AppState::GameCommanderSelection;
 
@@ -201,7 +201,7 @@ fn god_click(
 ) {
     for ev in ev_clicked.iter() {
         if let Ok((god, _)) = q_god.get(ev.0) {
-            network.request(Method::PUT, format!("games/avatar/{}", god.0.id).as_str());
+            network.send(api::select_avatar(god.0.id));
         }
     }
 }
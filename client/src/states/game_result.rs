@@ -57,8 +57,37 @@ fn setup(
                 ..Default::default()
             });
 
+            // Rating text
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(
+                    format!(
+                        "Rating {} ({:+})",
+                        game_result.0.ranking, game_result.0.ranking_change
+                    )
+                    .as_str(),
+                    TextStyle {
+                        font: asset_server.load("fonts/monogram-extended.ttf"),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                transform: Transform::from_translation(Vec3::new(0.0, -64.0, 0.0)),
+                ..Default::default()
+            });
+
             // Rewards
-            // TODO:Add rewards
+            parent.spawn(Text2dBundle {
+                text: Text::from_section(
+                    format!("Reward {}", game_result.0.reward).as_str(),
+                    TextStyle {
+                        font: asset_server.load("fonts/monogram-extended.ttf"),
+                        font_size: 30.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                transform: Transform::from_translation(Vec3::new(0.0, -96.0, 0.0)),
+                ..Default::default()
+            });
 
             // God image
             // TODO: implement
@@ -94,7 +123,7 @@ fn setup(
                 .spawn((
                     SpriteSheetBundle {
                         texture_atlas: button_atlas_handle,
-                        transform: Transform::from_translation(Vec3::new(0.0, -128.0, 0.0))
+                        transform: Transform::from_translation(Vec3::new(0.0, -160.0, 0.0))
                             .with_scale(Vec3::splat(3.0)),
                         ..Default::default()
                     },
@@ -1,8 +1,15 @@
-use bevy::{core::Stopwatch, prelude::*};
-use bevy_forms::button::{self, ButtonClickEvent};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use chrono::Utc;
+use protocol::{
+    api,
+    protocol::{Protocol, QueueStatus},
+};
 
 use crate::{
-    cleanup_system, components::timer::StopwatchComponent, AppState, Cleanup, StateChangeEvent,
+    cleanup_system,
+    networking::{networking_events::NetworkingEvent, networking_ressource::NetworkingRessource},
+    AppState, Cleanup, StateChangeEvent,
 };
 
 const STATE: AppState = AppState::GameSearch;
@@ -11,99 +18,75 @@ pub(crate) struct GameSearchPlugin;
 
 impl Plugin for GameSearchPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(STATE).with_system(setup))
-            .add_system_set(
-                SystemSet::on_update(STATE)
-                    .with_system(button_click)
-                    .with_system(timer),
-            )
-            .add_system_set(SystemSet::on_exit(STATE).with_system(cleanup_system::<Cleanup>));
+        app.init_resource::<QueueStatusRes>()
+            .add_system(setup.in_schedule(OnEnter(STATE)))
+            .add_systems((ui_game_search, on_network).in_set(OnUpdate(STATE)))
+            .add_system(leave_queue.in_schedule(OnExit(STATE)))
+            .add_system(cleanup_system::<Cleanup>.in_schedule(OnExit(STATE)));
     }
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // root node
-    commands
-        .spawn_bundle(NodeBundle {
-            style: Style {
-                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                justify_content: JustifyContent::SpaceBetween,
-                ..Default::default()
-            },
-            color: Color::NONE.into(),
-            ..Default::default()
-        })
-        .with_children(|parent| {
-            // bevy logo (flex center)
-            parent
-                .spawn_bundle(NodeBundle {
-                    style: Style {
-                        size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                        position_type: PositionType::Absolute,
-                        justify_content: JustifyContent::Center,
-                        align_items: AlignItems::FlexEnd,
-                        ..Default::default()
-                    },
-                    color: Color::NONE.into(),
-                    ..Default::default()
-                })
-                .with_children(|parent| {
-                    parent.spawn_bundle(TextBundle {
-                        text: Text::with_section(
-                            "Searching...",
-                            TextStyle {
-                                font: asset_server.load("fonts/monogram-extended.ttf"),
-                                font_size: 40.0,
-                                color: Color::rgb(0.9, 0.9, 0.9),
-                            },
-                            Default::default(),
-                        ),
-                        ..Default::default()
-                    });
+#[derive(Resource, Default)]
+struct QueueStatusRes(Option<QueueStatus>);
+
+fn setup(mut network: ResMut<NetworkingRessource>, mut queue_status: ResMut<QueueStatusRes>) {
+    queue_status.0 = None;
+    network.send(api::join_queue());
+}
 
-                    parent
-                        .spawn_bundle(TextBundle {
-                            text: Text::with_section(
-                                "",
-                                TextStyle {
-                                    font: asset_server.load("fonts/monogram-extended.ttf"),
-                                    font_size: 40.0,
-                                    color: Color::rgb(0.9, 0.9, 0.9),
-                                },
-                                Default::default(),
-                            ),
-                            ..Default::default()
-                        })
-                        .insert(StopwatchComponent {
-                            time: Stopwatch::new(),
-                        });
-                });
-            button::generate_button("Cancel", "btn_cancel", &asset_server, None, parent);
-        })
-        .insert(Cleanup);
+/// Leaving the state for any reason ends the search. The server ignores it if
+/// the user was already matched.
+fn leave_queue(mut network: ResMut<NetworkingRessource>) {
+    network.send(api::leave_queue());
 }
 
-fn button_click(
-    mut ev_button_click: EventReader<ButtonClickEvent>,
+fn ui_game_search(
+    mut contexts: EguiContexts,
     mut ev_state_change: EventWriter<StateChangeEvent>,
+    queue_status: Res<QueueStatusRes>,
 ) {
-    for ev in ev_button_click.iter() {
-        match ev.0.as_str() {
-            "btn_cancel" => ev_state_change.send(StateChangeEvent(AppState::MenuMain)),
-            _ => (),
+    let ctx = contexts.ctx_mut();
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.heading("Searching...");
+        ui.separator();
+
+        if let Some(status) = &queue_status.0 {
+            ui.label(format!(
+                "Waiting for {}s",
+                Utc::now()
+                    .signed_duration_since(status.queued_since)
+                    .num_seconds()
+                    .max(0)
+            ));
+            ui.label(format!("Players in queue: {}", status.players));
+            ui.label(format!("Rating range: ±{}", status.rating_window));
         }
-    }
+
+        ui.separator();
+        if ui.button("Cancel").clicked() {
+            ev_state_change.send(StateChangeEvent(AppState::MenuMain));
+        }
+    });
 }
 
-fn timer(
+fn on_network(
+    mut ev_networking: EventReader<NetworkingEvent>,
     mut ev_state_change: EventWriter<StateChangeEvent>,
-    mut timer: Query<(&StopwatchComponent, &mut Text), With<Text>>,
+    mut queue_status: ResMut<QueueStatusRes>,
 ) {
-    for (watch, mut text) in timer.iter_mut() {
-        text.sections[0].value = (watch.time.elapsed_secs() as u32).to_string();
-        if watch.time.elapsed_secs() >= 5.0 {
-            ev_state_change.send(StateChangeEvent(AppState::GameCommanderSelection));
+    for ev in ev_networking.iter() {
+        match &ev.0 {
+            Protocol::QueueStatusResponse(status) => {
+                queue_status.0 = Some(status.clone());
+            }
+            Protocol::QueueLeaveResponse => {
+                ev_state_change.send(StateChangeEvent(AppState::MenuMain));
+            }
+            Protocol::NetworkingError(e) if e.status == 409 => {
+                warn!("Failed to join queue: {}", e.message);
+                ev_state_change.send(StateChangeEvent(AppState::MenuMain));
+            }
+            _ => {}
         }
     }
-    // let mut text = text_query.get_mut(watch.).unwrap();
 }
@@ -15,8 +15,10 @@ use crate::{
     AppState, Cleanup,
 };
 use bevy::prelude::*;
-use protocol::protocol::{BuyRequest, CharacterInstance, GameOpponentInfo, Protocol};
-use reqwest::Method;
+use protocol::{
+    api,
+    protocol::{BuyRequest, CharacterInstance, GameOpponentInfo, Protocol},
+};
 
 use super::startup::{CharacterAssets, UiAssets};
 
@@ -102,10 +104,7 @@ fn setup(
     res_anchor: Res<Anchors>,
 ) {
     // root node
-    networking.request(Method::GET, "games/shops");
-    networking.request(Method::GET, "games/characters");
-    networking.request(Method::GET, "games/users/me");
-    networking.request(Method::GET, "games/users");
+    networking.send(api::get_game_state());
     commands.spawn((
         SpatialBundle {
             transform: Transform::from_translation(Vec3::new(-64.0 * 4.0, 200.0, 0.0)),
@@ -270,7 +269,7 @@ fn on_network(
     mut ev_shop_change: EventWriter<ShopChangedEvent>,
     mut ev_board_change: EventWriter<BoardChangedEvent>,
     mut ev_game_users_change: EventWriter<GameUsersChangedEvent>,
-    mut q_lock: Query<(Entity, &Animation), With<Lock>>,
+    q_lock: Query<(Entity, &Animation), With<Lock>>,
 ) {
     for ev in ev_networking.iter() {
         match &ev.0 {
@@ -278,15 +277,14 @@ fn on_network(
                 debug!("GameShopResponse: {:?}", shop);
                 commands.insert_resource(GameUserRes(user_info.clone()));
                 ev_shop_change.send(ShopChangedEvent(shop.clone()));
-                for (entity, animation) in q_lock.iter_mut() {
-                    if *locked {
-                        if let Some(lock_transition) = animation.get_transition("lock") {
-                            commands.entity(entity).insert(lock_transition);
-                        }
-                    } else if let Some(unlock_transition) = animation.get_transition("unlock") {
-                        commands.entity(entity).insert(unlock_transition);
-                    }
-                }
+                set_lock_state(&mut commands, &q_lock, *locked);
+            }
+            Protocol::GameStateResponse(state) => {
+                debug!("GameStateResponse: {:?}", state);
+                ev_shop_change.send(ShopChangedEvent(state.shop.clone()));
+                ev_board_change.send(BoardChangedEvent(state.board.clone()));
+                ev_game_users_change.send(GameUsersChangedEvent(state.opponents.clone()));
+                set_lock_state(&mut commands, &q_lock, state.shop_locked);
             }
             Protocol::BuyResponse(user_info, shop, board) => {
                 debug!("BuyResponse: {:?}", ev);
@@ -312,7 +310,11 @@ fn on_network(
                     match *reference {
                         Protocol::BuyRequest(_) => {
                             debug!("BuyRequest failed: {:?}", err);
-                            networking.request(Method::GET, "games/shops");
+                            networking.send(api::get_shop());
+                        }
+                        Protocol::CharacterMoveRequest => {
+                            debug!("CharacterMoveRequest failed: {:?}", err);
+                            networking.send(api::get_board());
                         }
                         _ => {}
                     }
@@ -323,6 +325,22 @@ fn on_network(
     }
 }
 
+fn set_lock_state(
+    commands: &mut Commands,
+    q_lock: &Query<(Entity, &Animation), With<Lock>>,
+    locked: bool,
+) {
+    for (entity, animation) in q_lock.iter() {
+        if locked {
+            if let Some(lock_transition) = animation.get_transition("lock") {
+                commands.entity(entity).insert(lock_transition);
+            }
+        } else if let Some(unlock_transition) = animation.get_transition("unlock") {
+            commands.entity(entity).insert(unlock_transition);
+        }
+    }
+}
+
 fn on_buy(
     mut commands: Commands,
     mut ev_droped: EventReader<DropEvent>,
@@ -334,14 +352,10 @@ fn on_buy(
         if let Ok(pedestal) = q_pedestal.get(ev.target) {
             if let Ok(god) = q_god.get(ev.entity) {
                 debug!("on_buy: {:?} {:?}", pedestal, god);
-                networking.request_data(
-                    Method::POST,
-                    "games/shops/buy",
-                    &BuyRequest {
-                        character_idx: god.idx,
-                        target_idx: pedestal.0,
-                    },
-                );
+                networking.send(api::buy_character(&BuyRequest {
+                    character_idx: god.idx,
+                    target_idx: pedestal.0,
+                }));
                 commands.entity(ev.entity).despawn_recursive();
             }
         }
@@ -356,7 +370,7 @@ fn on_reroll(
 ) {
     for ev in ev_cklicked.iter() {
         if q_reroll.get(ev.0).is_ok() {
-            networking.request(Method::POST, "games/shops");
+            networking.send(api::reroll_shop());
         }
     }
 }
@@ -368,7 +382,7 @@ fn on_lock(
 ) {
     for ev in ev_cklicked.iter() {
         if q_lock.get(ev.0).is_ok() {
-            networking.request(Method::PATCH, "games/shops");
+            networking.send(api::toggle_lock_shop());
         }
     }
 }
@@ -382,10 +396,7 @@ fn on_sell(
     for ev in ev_droped.iter() {
         if let Ok(character) = q_sell.get(ev.target).and(q_character.get(ev.entity)) {
             debug!("on_sell: {:?}", character);
-            networking.request(
-                Method::DELETE,
-                format!("games/characters/{}", character.0).as_str(),
-            );
+            networking.send(api::sell_character(character.0.into()));
         }
     }
 }
@@ -401,10 +412,7 @@ fn on_move(
         if let Ok(pedestal) = q_pedestal.get(ev.target) {
             if let Ok(god) = q_god.get(ev.entity) {
                 debug!("on_move: {:?} {:?}", pedestal, god);
-                networking.request(
-                    Method::PUT,
-                    format!("games/characters/{}/{}", god.0, pedestal.0).as_str(),
-                );
+                networking.send(api::move_character(god.0.into(), pedestal.0.into()));
                 commands.entity(ev.entity).despawn_recursive();
             }
         }
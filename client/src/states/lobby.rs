@@ -5,8 +5,10 @@ use crate::{
 };
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
-use protocol::protocol::{LobbyInfo, Protocol};
-use reqwest::Method;
+use protocol::{
+    api,
+    protocol::{LobbyInfo, LobbySettings, Protocol, RulesPreset},
+};
 
 use super::menu_login::User;
 
@@ -15,7 +17,10 @@ pub(crate) struct LobbyPlugin;
 
 impl Plugin for LobbyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems((ui_lobby, on_network).in_set(OnUpdate(STATE)))
+        app.init_resource::<LobbySettingsForm>()
+            .init_resource::<InviteForm>()
+            .add_system(setup.in_schedule(OnEnter(STATE)))
+            .add_systems((ui_lobby, on_network).in_set(OnUpdate(STATE)))
             .add_system(cleanup_system::<Cleanup>.in_schedule(OnExit(STATE)));
     }
 }
@@ -23,10 +28,24 @@ impl Plugin for LobbyPlugin {
 #[derive(Resource)]
 pub struct CurrentLobby(pub LobbyInfo);
 
+#[derive(Resource, Default)]
+struct LobbySettingsForm(LobbySettings);
+
+#[derive(Resource, Default)]
+struct InviteForm(String);
+
+fn setup(lobby: Option<Res<CurrentLobby>>, mut settings: ResMut<LobbySettingsForm>) {
+    if let Some(lobby) = lobby {
+        settings.0 = lobby.0.settings.clone();
+    }
+}
+
 fn ui_lobby(
     mut contexts: EguiContexts,
     mut network: ResMut<NetworkingRessource>,
     lobby: ResMut<CurrentLobby>,
+    mut settings: ResMut<LobbySettingsForm>,
+    mut invite: ResMut<InviteForm>,
     res_user: Res<User>,
 ) {
     let ctx = contexts.ctx_mut();
@@ -34,45 +53,91 @@ fn ui_lobby(
         ui.heading(format!("Lobby ({})", lobby.0.name));
         ui.separator();
 
-        let mut ready = false;
-        let mut master = lobby.0.master == res_user.0.id;
+        let own = lobby
+            .0
+            .users
+            .iter()
+            .find(|player| Some(&player.name) == res_user.0.display_name.as_ref());
+        let ready = own.map_or(false, |player| player.ready);
+        let master = own.map_or(false, |player| player.id == lobby.0.master);
 
         for player in &lobby.0.users {
-            ui.label(format!(
-                "{} ({})",
-                player.name,
-                if player.ready { "ready" } else { "not ready" }
-            ));
-            if player.name == *res_user.0.display_name.as_ref().unwrap() {
-                ready = player.ready;
-                master = player.id == lobby.0.master;
-            }
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "{} ({})",
+                    player.name,
+                    if player.ready { "ready" } else { "not ready" }
+                ));
+                if master && own.map_or(true, |own| own.id != player.id) {
+                    if ui.button("Kick").clicked() {
+                        network.send(api::kick_user(player.id));
+                    }
+                    if ui.button("Promote").clicked() {
+                        network.send(api::promote_user(player.id));
+                    }
+                }
+            });
+        }
+
+        if master {
+            ui.horizontal(|ui| {
+                ui.label("Invite:");
+                ui.text_edit_singleline(&mut invite.0);
+                if ui.button("Send").clicked() && !invite.0.is_empty() {
+                    network.send(api::invite_user(&invite.0));
+                    invite.0.clear();
+                }
+            });
         }
 
+        ui.separator();
+        ui.add_enabled_ui(master, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Max players:");
+                ui.add(egui::Slider::new(&mut settings.0.max_players, 1..=8));
+            });
+            ui.checkbox(&mut settings.0.bot_fill, "Fill with bots");
+            ui.checkbox(&mut settings.0.rated, "Rated");
+            egui::ComboBox::from_label("Rules")
+                .selected_text(format!("{:?}", settings.0.rules))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut settings.0.rules, RulesPreset::Standard, "Standard");
+                    ui.selectable_value(&mut settings.0.rules, RulesPreset::Fast, "Fast");
+                });
+            if settings.0 != lobby.0.settings && ui.button("Apply").clicked() {
+                network.send(api::update_lobby_settings(&settings.0));
+            }
+        });
+
         ui.separator();
         ui.horizontal(|ui| {
             if ui.button("Leave").clicked() {
-                network.request_data(Method::DELETE, "lobbies", &lobby.0);
+                network.send(api::leave_lobby());
             }
             if ui
                 .button(if !ready { "Ready" } else { "Not Ready" })
                 .clicked()
             {
-                network.request_data(Method::PATCH, "lobbies/ready", &lobby.0);
+                network.send(api::toggle_ready_state());
             }
             if master && ui.button("Start").clicked() {
-                network.request_data(Method::PATCH, "lobbies/start", &lobby.0);
+                network.send(api::start_lobby_timer());
             }
         });
     });
 }
 
-fn on_network(mut commands: Commands, mut ev_networking: EventReader<NetworkingEvent>) {
+fn on_network(
+    mut commands: Commands,
+    mut ev_networking: EventReader<NetworkingEvent>,
+    mut settings: ResMut<LobbySettingsForm>,
+) {
     for ev in ev_networking.iter() {
         match &ev.0 {
             Protocol::LobbyStatusResponse(lobby) => {
                 debug!("Got lobby info {:?}", lobby);
 
+                settings.0 = lobby.settings.clone();
                 commands.insert_resource(CurrentLobby(lobby.clone()));
             }
             _ => {}
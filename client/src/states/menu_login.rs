@@ -10,8 +10,10 @@ use crate::{
 };
 use bevy::{app::AppExit, prelude::*};
 use bevy_egui::{egui, EguiContexts};
-use protocol::protocol::{Credentials, Protocol, UserData};
-use reqwest::{header::HeaderValue, Method};
+use protocol::{
+    api,
+    protocol::{Credentials, Protocol, UserData},
+};
 
 const STATE: AppState = AppState::MenuLogin;
 pub(crate) struct MenuLoginPlugin;
@@ -33,12 +35,14 @@ pub struct User(pub UserData);
 
 fn logout(
     mut commands: Commands,
+    mut network: ResMut<NetworkingRessource>,
     mut ev_polling_status: EventWriter<PollingStatus>,
     asset_server: Res<AssetServer>,
     res_anchors: Res<Anchors>,
 ) {
     debug!("Logout start");
     commands.remove_resource::<User>();
+    network.clear_session();
     ev_polling_status.send(PollingStatus::Stop);
 
     commands
@@ -74,12 +78,15 @@ fn ui_login(
         ui.separator();
         ui.horizontal(|ui| {
             if ui.button("Login").clicked() {
-                network.request_data(Method::POST, "users", &credentials.0);
+                network.send(api::login(&credentials.0));
             }
             if ui.button("Register").clicked() {
-                network.request_data(Method::PUT, "users", &credentials.0);
+                network.send(api::register(&credentials.0));
             }
         });
+        if ui.button("Play as guest").clicked() {
+            network.send(api::guest_login());
+        }
         if ui.button("Exit").clicked() {
             ev_exit.send(AppExit);
         }
@@ -95,16 +102,16 @@ fn on_login(
 ) {
     for ev in ev_networking.iter() {
         if let Protocol::LoginResponse(login) = &ev.0 {
-            network.headers.insert(
-                "x-api-key",
-                HeaderValue::from_str(login.key.as_str()).unwrap(),
-            );
+            network.set_session(&login.key, &login.refresh_key);
 
             commands.insert_resource(User(login.user.clone()));
             debug!("Logged in as {}", login.user.username);
 
             ev_polling_status.send(PollingStatus::Start);
-            if login.user.display_name.is_none() {
+            if let Some(game) = login.game {
+                debug!("Reconnecting to game {}", game);
+                network.send(api::get_game_state());
+            } else if login.user.display_name.is_none() {
                 ev_state_change.send(StateChangeEvent(AppState::MenuSetDisplayName));
             } else {
                 ev_state_change.send(StateChangeEvent(AppState::MenuMain));
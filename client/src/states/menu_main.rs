@@ -1,20 +1,38 @@
 use bevy::{app::AppExit, prelude::*};
 use bevy_egui::{egui, EguiContexts};
+use protocol::{
+    api,
+    protocol::{Credentials, Protocol},
+};
 
-use crate::{cleanup_system, AppState, Cleanup, StateChangeEvent};
+use crate::{
+    cleanup_system,
+    networking::{networking_events::NetworkingEvent, networking_ressource::NetworkingRessource},
+    AppState, Cleanup, StateChangeEvent,
+};
+
+use super::menu_login::User;
 
 const STATE: AppState = AppState::MenuMain;
 pub(crate) struct MenuMainPlugin;
 
 impl Plugin for MenuMainPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(ui_main_menu.in_set(OnUpdate(STATE)))
+        app.init_resource::<ClaimCredentials>()
+            .add_systems((ui_main_menu, on_claim).in_set(OnUpdate(STATE)))
             .add_system(cleanup_system::<Cleanup>.in_schedule(OnExit(STATE)));
     }
 }
 
+/// Credentials entered to keep a guest account
+#[derive(Resource, Default)]
+struct ClaimCredentials(Credentials);
+
 fn ui_main_menu(
     mut contexts: EguiContexts,
+    mut network: ResMut<NetworkingRessource>,
+    mut claim: ResMut<ClaimCredentials>,
+    user: Option<Res<User>>,
     mut ev_state_change: EventWriter<StateChangeEvent>,
     mut ev_exit: EventWriter<AppExit>,
 ) {
@@ -23,12 +41,55 @@ fn ui_main_menu(
         ui.heading("Main Menu");
         ui.separator();
         ui.horizontal(|ui| {
-            if ui.button("Play").clicked() {
+            if ui.button("Find Game").clicked() {
+                ev_state_change.send(StateChangeEvent(AppState::GameSearch));
+            }
+            if ui.button("Join Lobby").clicked() {
                 ev_state_change.send(StateChangeEvent(AppState::DialogLobbyJoin));
             }
             if ui.button("Exit").clicked() {
                 ev_exit.send(AppExit);
             }
         });
+        if user.is_some_and(|user| user.0.guest) {
+            ui.separator();
+            ui.label("Playing as guest. Set a username and password to keep your progress.");
+            ui.horizontal(|ui| {
+                ui.label("Username:");
+                ui.text_edit_singleline(&mut claim.0.username);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Password:");
+                ui.add(egui::TextEdit::singleline(&mut claim.0.password).password(true));
+            });
+            if ui.button("Save account").clicked() {
+                network.send(api::claim_account(&claim.0));
+            }
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Logout").clicked() {
+                network.send(api::logout());
+                ev_state_change.send(StateChangeEvent(AppState::MenuLogin));
+            }
+            if ui.button("Logout everywhere").clicked() {
+                network.send(api::logout_all());
+                ev_state_change.send(StateChangeEvent(AppState::MenuLogin));
+            }
+        });
     });
 }
+
+fn on_claim(
+    mut commands: Commands,
+    mut claim: ResMut<ClaimCredentials>,
+    mut ev_networking: EventReader<NetworkingEvent>,
+) {
+    for ev in ev_networking.iter() {
+        if let Protocol::UserResponse(user) = &ev.0 {
+            debug!("Account claimed as {}", user.username);
+            claim.0 = Credentials::default();
+            commands.insert_resource(User(user.clone()));
+        }
+    }
+}
@@ -1,8 +1,8 @@
 pub(crate) mod dialog_lobby_join;
 pub(crate) mod game_commander_selection;
-// pub(crate) mod game_search;
 pub(crate) mod game_combat;
 pub(crate) mod game_result;
+pub(crate) mod game_search;
 pub(crate) mod game_shop;
 pub(crate) mod lobby;
 pub(crate) mod menu_login;
@@ -25,7 +25,7 @@ pub(crate) mod game_states {
                 .add(menu_main::MenuMainPlugin)
                 .add(dialog_lobby_join::DialogLobbyJoinPlugin)
                 .add(lobby::LobbyPlugin)
-                // .add(game_search::GameSearchPlugin)
+                .add(game_search::GameSearchPlugin)
                 .add(game_commander_selection::GameCommanderSelectionPlugin)
                 .add(game_shop::GameShopPlugin)
                 .add(game_combat::GameCombatPlugin)
@@ -1,7 +1,9 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
-use protocol::protocol::Protocol;
-use reqwest::Method;
+use protocol::{
+    api,
+    protocol::{ErrorCode, Protocol},
+};
 
 use crate::{
     cleanup_system,
@@ -17,6 +19,7 @@ pub(crate) struct SetDisplayNamePlugin;
 impl Plugin for SetDisplayNamePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<DisplayName>()
+            .init_resource::<DisplayNameError>()
             .add_systems((ui_main_menu, on_network).in_set(OnUpdate(STATE)))
             .add_system(cleanup_system::<Cleanup>.in_schedule(OnExit(STATE)));
     }
@@ -25,11 +28,15 @@ impl Plugin for SetDisplayNamePlugin {
 #[derive(Resource, Default)]
 struct DisplayName(String);
 
+/// Why the server rejected the last display name
+#[derive(Resource, Default)]
+struct DisplayNameError(Option<String>);
+
 fn ui_main_menu(
     mut contexts: EguiContexts,
     mut display_name: ResMut<DisplayName>,
     mut networking: ResMut<NetworkingRessource>,
-    mut res_user: ResMut<User>,
+    error: Res<DisplayNameError>,
 ) {
     let ctx = contexts.ctx_mut();
 
@@ -43,10 +50,12 @@ fn ui_main_menu(
             ui.label("Display Name:");
             ui.add(egui::TextEdit::singleline(&mut display_name.0));
         });
+        if let Some(error) = &error.0 {
+            ui.colored_label(egui::Color32::RED, error);
+        }
         ui.horizontal(|ui| {
             if ui.button("Set Display Name").clicked() {
-                res_user.0.display_name = Some(display_name.0.clone());
-                networking.request_data(Method::PUT, "users/display_name", &display_name.0.clone());
+                networking.send(api::set_display_name(&display_name.0));
             }
         });
     });
@@ -55,10 +64,30 @@ fn ui_main_menu(
 fn on_network(
     mut ev_networking: EventReader<NetworkingEvent>,
     mut ev_state_change: EventWriter<StateChangeEvent>,
+    mut res_user: ResMut<User>,
+    mut error: ResMut<DisplayNameError>,
 ) {
     for ev in ev_networking.iter() {
-        if let Protocol::DisplaynameResponse(_) = &ev.0 {
-            ev_state_change.send(StateChangeEvent(AppState::MenuMain));
+        match &ev.0 {
+            Protocol::DisplaynameResponse(display_name) => {
+                res_user.0.display_name = Some(display_name.clone());
+                error.0 = None;
+                ev_state_change.send(StateChangeEvent(AppState::MenuMain));
+            }
+            Protocol::NetworkingError(err)
+                if matches!(
+                    err.code,
+                    ErrorCode::InvalidDisplayName
+                        | ErrorCode::DisplayNameTooShort
+                        | ErrorCode::DisplayNameTooLong
+                        | ErrorCode::DisplayNameTaken
+                        | ErrorCode::DisplayNameNotAllowed
+                        | ErrorCode::RateLimited
+                ) =>
+            {
+                error.0 = Some(err.message.clone());
+            }
+            _ => {}
         }
     }
 }
@@ -1,6 +1,17 @@
-use crate::{components::background::Background, AppState, StateChangeEvent};
-use bevy::{prelude::*, utils::HashMap};
-use protocol::{characters::get_characters, gods::get_gods};
+use crate::{
+    components::background::Background,
+    networking::{networking_events::NetworkingEvent, networking_ressource::NetworkingRessource},
+    AppState, StateChangeEvent,
+};
+use bevy::{app::AppExit, prelude::*, utils::HashMap};
+use bevy_egui::{egui, EguiContexts};
+use protocol::{
+    api,
+    characters::get_characters,
+    gods::get_gods,
+    protocol::Protocol,
+    version::{self, DATA_HASH, PROTOCOL_VERSION},
+};
 
 const STATE: AppState = AppState::Startup;
 pub(crate) struct StartupPlugin;
@@ -21,10 +32,20 @@ impl Plugin for StartupPlugin {
                     .before(setup)
                     .in_schedule(OnEnter(STATE)),
             )
-            .add_system(setup.in_schedule(OnEnter(STATE)));
+            .add_system(setup.in_schedule(OnEnter(STATE)))
+            .add_system(on_status.in_set(OnUpdate(STATE)))
+            .add_system(
+                ui_update_required
+                    .run_if(resource_exists::<UpdateRequired>())
+                    .in_set(OnUpdate(STATE)),
+            );
     }
 }
 
+/// Server does not accept this client version
+#[derive(Resource, Debug)]
+pub struct UpdateRequired(pub String);
+
 #[derive(Resource, Default)]
 pub struct UiAssets {
     pub font: Handle<Font>,
@@ -126,10 +147,61 @@ fn load_background_assets(
 }
 
 fn setup(
-    mut ev_state_change: EventWriter<StateChangeEvent>,
+    mut network: ResMut<NetworkingRessource>,
     background_assets: ResMut<BackgroundAssets>,
     mut background_resource: ResMut<Background>,
 ) {
-    ev_state_change.send(StateChangeEvent(AppState::MenuLogin));
+    network.send(api::status(Some(PROTOCOL_VERSION), Some(DATA_HASH)));
     background_resource.0 = background_assets.background.clone();
 }
+
+fn on_status(
+    mut commands: Commands,
+    mut ev_networking: EventReader<NetworkingEvent>,
+    mut ev_state_change: EventWriter<StateChangeEvent>,
+) {
+    for ev in ev_networking.iter() {
+        match &ev.0 {
+            Protocol::StatusResponse(status) => {
+                if version::is_compatible(&status.version, &status.data_hash) {
+                    ev_state_change.send(StateChangeEvent(AppState::MenuLogin));
+                } else {
+                    commands.insert_resource(UpdateRequired(format!(
+                        "Server version {} ({}) does not match client version {} ({})",
+                        status.version, status.data_hash, PROTOCOL_VERSION, DATA_HASH
+                    )));
+                }
+            }
+            Protocol::NetworkingError(err) if err.status == 426 => {
+                commands.insert_resource(UpdateRequired(err.message.clone()));
+            }
+            Protocol::NetworkingError(err) => {
+                // Let the login screen report connection problems
+                warn!("Failed to check server version: {:?}", err);
+                ev_state_change.send(StateChangeEvent(AppState::MenuLogin));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn ui_update_required(
+    mut contexts: EguiContexts,
+    update_required: Res<UpdateRequired>,
+    mut ev_exit: EventWriter<AppExit>,
+) {
+    egui::CentralPanel::default().show(contexts.ctx_mut(), |ui| {
+        ui.heading("Update required");
+        ui.separator();
+        ui.label("This version of the game is no longer supported by the server.");
+        #[cfg(target_family = "wasm")]
+        ui.label("Please reload the page to get the latest version.");
+        #[cfg(not(target_family = "wasm"))]
+        ui.label("Please download the latest version to continue playing.");
+        ui.small(update_required.0.as_str());
+        ui.separator();
+        if ui.button("Exit").clicked() {
+            ev_exit.send(AppExit);
+        }
+    });
+}
@@ -17,14 +17,38 @@ macro_rules! warn {
 pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=./data/gods.json");
+    println!("cargo:rerun-if-changed=./data");
 
-    generate_from_json::<GodJson, God>("./data/gods", "gods", Some((256, 256)))?;
-    generate_from_json::<CharacterJson, Character>(
+    let gods = generate_from_json::<GodJson, God>("./data/gods", "gods", Some((256, 256)))?;
+    let characters = generate_from_json::<CharacterJson, Character>(
         "./data/characters",
         "characters",
         Some((512, 512)),
     )?;
 
+    generate_data_hash(&[gods, characters])?;
+
+    Ok(())
+}
+
+/// Hashes the generated tables, so clients with different game data can be detected
+fn generate_data_hash(sources: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    let hash = sources
+        .iter()
+        .flat_map(|source| source.bytes())
+        .fold(FNV_OFFSET, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        });
+
+    let out_dir = std::env::var_os("OUT_DIR").unwrap();
+    std::fs::write(
+        Path::new(&out_dir).join("data_hash.rs"),
+        format!("pub const DATA_HASH: &str = \"{:016x}\";\n", hash),
+    )?;
+
     Ok(())
 }
 
@@ -32,7 +56,7 @@ fn generate_from_json<T, U>(
     path: &str,
     name: &str,
     image: Option<(u32, u32)>,
-) -> Result<(), Box<dyn std::error::Error>>
+) -> Result<String, Box<dyn std::error::Error>>
 where
     T: ToTokens + Entity + DeserializeOwned + std::fmt::Debug,
 {
@@ -116,15 +140,12 @@ where
         }
     };
 
-    std::fs::write(
-        &rs_path,
-        prettyplease::unparse(&syn::parse_file(tokens.to_string().as_str()).unwrap()),
-    )
-    .unwrap();
+    let source = prettyplease::unparse(&syn::parse_file(tokens.to_string().as_str()).unwrap());
+    std::fs::write(&rs_path, &source).unwrap();
 
     warn!("Generated {:?}", rs_path);
 
-    Ok(())
+    Ok(source)
 }
 
 fn generate_masked_img(
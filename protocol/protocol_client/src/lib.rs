@@ -0,0 +1,140 @@
+//! Typed http client for the server api.
+//!
+//! Has one async method per endpoint in `protocol::for_each_endpoint!`. Meant for
+//! bots, load tests and integration tests. The game client queues the
+//! `protocol::api` requests through its own networking instead.
+
+use protocol::{
+    api::{self, ApiRequest},
+    protocol::{
+        BanRequest, BuyRequest, Credentials, Error, LobbyJoinRequest, LobbySettings, Protocol,
+    },
+    uuid::Uuid,
+};
+use reqwest::{Client, Method, RequestBuilder, Url};
+
+pub struct ApiClient {
+    http: Client,
+    base_url: Url,
+    key: Option<String>,
+    refresh_key: Option<String>,
+}
+
+impl ApiClient {
+    /// `base_url` is the api root, e.g. `http://localhost:8000/api/v1/`
+    pub fn new(base_url: Url) -> Self {
+        Self {
+            http: Client::new(),
+            base_url,
+            key: None,
+            refresh_key: None,
+        }
+    }
+
+    /// Session key sent in the `x-api-key` header
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    pub fn set_key(&mut self, key: Option<String>) {
+        self.key = key;
+    }
+
+    /// Key for `refresh_session` once the session expired
+    pub fn refresh_key(&self) -> Option<&str> {
+        self.refresh_key.as_deref()
+    }
+
+    /// Sends `request` and decodes the response. Transport failures are returned as
+    /// `Protocol::NetworkingError`. Session keys are stored on login and refresh
+    /// and dropped on logout.
+    pub async fn send(&mut self, request: ApiRequest) -> Protocol {
+        let response = match self.execute(request).await {
+            Ok(response) => response,
+            Err(err) => {
+                return Error::new_protocol(
+                    err.status().map_or(500, |status| status.as_u16()),
+                    err.to_string(),
+                )
+            }
+        };
+
+        match &response {
+            Protocol::LoginResponse(login) => {
+                self.key = Some(login.key.clone());
+                self.refresh_key = Some(login.refresh_key.clone());
+            }
+            Protocol::SessionResponse(session) => {
+                self.key = Some(session.key.clone());
+                self.refresh_key = Some(session.refresh_key.clone());
+            }
+            Protocol::LogoutResponse => {
+                self.key = None;
+                self.refresh_key = None;
+            }
+            _ => {}
+        }
+
+        response
+    }
+
+    /// Fetches the OpenAPI document. It is the only response that is not a `Protocol`.
+    pub async fn openapi_document(&self) -> Result<serde_json::Value, reqwest::Error> {
+        self.builder(api::openapi())
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    }
+
+    async fn execute(&self, request: ApiRequest) -> Result<Protocol, reqwest::Error> {
+        self.builder(request).send().await?.json::<Protocol>().await
+    }
+
+    fn builder(&self, request: ApiRequest) -> RequestBuilder {
+        let url = self
+            .base_url
+            .join(&request.path)
+            .expect("Api paths are valid relative urls");
+        let method = Method::from_bytes(request.endpoint.method.as_str().as_bytes())
+            .expect("Api methods are valid http methods");
+
+        let mut builder = self.http.request(method, url);
+        if let Some(key) = &self.key {
+            builder = builder.header("x-api-key", key);
+        }
+        if let Some(body) = &request.body {
+            builder = builder.json(body);
+        }
+        builder
+    }
+}
+
+macro_rules! define_client_methods {
+    ($(
+        $(#[doc = $doc:literal])*
+        $name:ident: $method:ident $path:literal $access:ident
+            $(params($($param:ident: $param_ty:ty),*))?
+            $(query($($query:ident: $query_ty:ty),*))?
+            $(body($body:ident: $body_ty:ty))?
+            => [$($response:ident),*];
+    )*) => {
+        impl ApiClient {
+            $(
+                $(#[doc = $doc])*
+                #[allow(clippy::ptr_arg)]
+                pub async fn $name(
+                    &mut self,
+                    $($($param: $param_ty,)*)?
+                    $($($query: Option<$query_ty>,)*)?
+                    $($body: &$body_ty)?
+                ) -> Protocol {
+                    self.send(api::$name($($($param,)*)? $($($query,)*)? $($body)?)).await
+                }
+            )*
+        }
+    };
+}
+
+protocol::for_each_endpoint!(define_client_methods);
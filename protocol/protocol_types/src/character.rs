@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Character {
     pub id: i32,
     pub name: String,
@@ -13,6 +14,7 @@ pub struct Character {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CharacterUpgrade {
     pub name: String,
     pub attack: i32,
@@ -21,6 +23,7 @@ pub struct CharacterUpgrade {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Ability {
     pub name: String,
     pub description: String,
@@ -30,6 +33,7 @@ pub struct Ability {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum AbilityTrigger {
     OnAttack,
     OnDefend,
@@ -39,6 +43,7 @@ pub enum AbilityTrigger {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum AbilityEffect {
     // Summon a character with the given id
     Summon(i32),
@@ -67,6 +72,7 @@ pub enum AbilityEffect {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum AbilityTarget {
     SelfTarget,
     EnemyTarget,
@@ -77,6 +83,7 @@ pub enum AbilityTarget {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum AbilityValue {
     Plain(i32),
     PercentHealth(i32),
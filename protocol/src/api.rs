@@ -0,0 +1,317 @@
+//! Typed description of the REST api mounted below `/api/v1`.
+//!
+//! `for_each_endpoint!` is the single source of the api surface. It generates
+//! the `ENDPOINTS` table the server is checked against and one request
+//! function per route, so typos in paths become compile errors for clients.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::protocol::{
+    ApiCall, BanRequest, BuyRequest, Credentials, LobbyJoinRequest, LobbySettings, Protocol,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Method {
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Get => "GET",
+            Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Patch => "PATCH",
+            Self::Delete => "DELETE",
+        }
+    }
+}
+
+impl std::str::FromStr for Method {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "GET" => Ok(Self::Get),
+            "POST" => Ok(Self::Post),
+            "PUT" => Ok(Self::Put),
+            "PATCH" => Ok(Self::Patch),
+            "DELETE" => Ok(Self::Delete),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Who is allowed to call an endpoint
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Public,
+    /// Requires the session key in the `x-api-key` header
+    User,
+    /// Like `User`, but the user needs the admin role
+    Admin,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct Endpoint {
+    pub name: &'static str,
+    pub summary: &'static str,
+    pub method: Method,
+    /// Path in rocket syntax, e.g. `/games/characters/<character_idx>`
+    pub path: &'static str,
+    /// Path parameters as `(name, type)`
+    pub params: &'static [(&'static str, &'static str)],
+    /// Optional query parameters as `(name, type)`
+    pub query: &'static [(&'static str, &'static str)],
+    pub access: Access,
+    /// Type of the json request body
+    pub body: Option<&'static str>,
+    /// `Protocol` variants returned on success
+    pub responses: &'static [&'static str],
+}
+
+impl Endpoint {
+    /// Path including the query parameters in rocket syntax
+    pub fn route(&self) -> String {
+        let query = self
+            .query
+            .iter()
+            .map(|(name, _)| format!("<{}>", name))
+            .collect::<Vec<_>>();
+        if query.is_empty() {
+            self.path.to_string()
+        } else {
+            format!("{}?{}", self.path, query.join("&"))
+        }
+    }
+}
+
+/// Request for an endpoint. The path is relative to the api base url.
+#[derive(Clone, Debug)]
+pub struct ApiRequest {
+    pub endpoint: &'static Endpoint,
+    pub path: String,
+    pub body: Option<serde_json::Value>,
+}
+
+impl ApiRequest {
+    /// Wraps the request to send it over the websocket
+    pub fn into_call(self, id: u64) -> ApiCall {
+        ApiCall {
+            id,
+            method: self.endpoint.method,
+            path: self.path,
+            body: self.body,
+        }
+    }
+}
+
+/// Invokes `$callback!` with the definition of every api endpoint
+#[macro_export]
+macro_rules! for_each_endpoint {
+    ($callback:ident) => {
+        $callback! {
+            /// Server version and data hash
+            status: Get "/status" Public query(version: &str, data_hash: &str) => [StatusResponse];
+            /// Creates a new account and logs in
+            register: Put "/users" Public body(creds: Credentials) => [LoginResponse];
+            login: Post "/users" Public body(creds: Credentials) => [LoginResponse];
+            /// Creates a temporary account with a generated display name and logs in
+            guest_login: Post "/users/guest" Public => [LoginResponse];
+            /// Sets the credentials of a guest account, keeping its progress
+            claim_account: Put "/users/@me/credentials" User body(creds: Credentials) => [UserResponse];
+            /// Exchanges a refresh key for a new session
+            refresh_session: Post "/sessions/refresh" Public body(refresh_key: String) => [SessionResponse];
+            /// Ends the current session
+            logout: Delete "/sessions" User => [LogoutResponse];
+            /// Ends all sessions of the user
+            logout_all: Delete "/sessions/all" User => [LogoutResponse];
+            me: Get "/users/@me" User => [UserResponse];
+            set_display_name: Put "/users/display_name" User body(display_name: String) => [DisplaynameResponse];
+            leaderboard: Get "/users/leaderboard" Public => [LeaderboardResponse];
+            get_transactions: Get "/users/@me/transactions" User => [TransactionsResponse];
+            /// Users whose chat messages are hidden
+            get_mutes: Get "/users/@me/mutes" User => [MutesResponse];
+            mute_user: Put "/users/@me/mutes/<muted_user_id>" User params(muted_user_id: i32) => [MutesResponse];
+            unmute_user: Delete "/users/@me/mutes/<muted_user_id>" User params(muted_user_id: i32) => [MutesResponse];
+            get_current_lobby_info: Get "/lobbies" User => [LobbyStatusResponse];
+            join_lobby: Put "/lobbies" User body(lobby: LobbyJoinRequest) => [EMPTY];
+            leave_lobby: Delete "/lobbies" User => [LobbyLeaveResponse];
+            toggle_ready_state: Patch "/lobbies/ready" User => [EMPTY];
+            start_lobby_timer: Patch "/lobbies/start" User => [EMPTY];
+            stop_lobby_timer: Patch "/lobbies/stop" User => [EMPTY];
+            list_lobbies: Get "/lobbies/public" Public => [LobbyListResponse];
+            update_lobby_settings: Patch "/lobbies/settings" User body(settings: LobbySettings) => [EMPTY];
+            kick_user: Delete "/lobbies/users/<lobby_user_id>" User params(lobby_user_id: i32) => [EMPTY];
+            promote_user: Patch "/lobbies/users/<lobby_user_id>/promote" User params(lobby_user_id: i32) => [EMPTY];
+            /// Recent messages of the lobby chat
+            get_lobby_chat: Get "/lobbies/chat" User => [ChatHistoryResponse];
+            post_lobby_message: Post "/lobbies/chat" User body(text: String) => [ChatMessageResponse];
+            invite_user: Put "/lobbies/invites" User body(display_name: String) => [EMPTY];
+            get_invites: Get "/lobbies/invites" User => [LobbyInvitesResponse];
+            accept_invite: Put "/lobbies/invites/<invite_id>" User params(invite_id: i32) => [EMPTY];
+            decline_invite: Delete "/lobbies/invites/<invite_id>" User params(invite_id: i32) => [EMPTY];
+            /// Friends and open friend requests with their presence
+            get_friends: Get "/friends" User => [FriendsResponse];
+            /// Sends a friend request or accepts the one from that user
+            add_friend: Put "/friends" User body(display_name: String) => [FriendsResponse];
+            accept_friend: Put "/friends/<friend_id>" User params(friend_id: i32) => [FriendsResponse];
+            /// Removes a friend or declines and cancels requests
+            remove_friend: Delete "/friends/<friend_id>" User params(friend_id: i32) => [FriendsResponse];
+            join_queue: Put "/queue" User => [QueueStatusResponse];
+            get_queue_status: Get "/queue" User => [QueueStatusResponse];
+            leave_queue: Delete "/queue" User => [QueueLeaveResponse];
+            /// Full state of the running game for reconnecting clients
+            get_game_state: Get "/games/state" User => [GameStateResponse];
+            get_own_user: Get "/games/users/me" User => [GameUserInfoResponse];
+            get_users: Get "/games/users" User => [GameUsersResponse];
+            select_avatar: Put "/games/avatar/<avatar_id>" User params(avatar_id: i32) => [AvatarSelectResponse];
+            get_shop: Get "/games/shops" User => [GameShopResponse];
+            toggle_lock_shop: Patch "/games/shops" User => [GameShopResponse];
+            reroll_shop: Post "/games/shops" User => [GameShopResponse];
+            buy_character: Post "/games/shops/buy" User body(buy_request: BuyRequest) => [BuyResponse];
+            /// Recent messages of the game chat
+            get_game_chat: Get "/games/chat" User => [ChatHistoryResponse];
+            post_game_message: Post "/games/chat" User body(text: String) => [ChatMessageResponse];
+            get_board: Get "/games/characters" User => [BoardResponse];
+            move_character: Put "/games/characters/<character_idx>/<target_idx>" User params(character_idx: usize, target_idx: usize) => [BoardResponse];
+            sell_character: Delete "/games/characters/<character_idx>" User params(character_idx: usize) => [SellResponse];
+            /// Long polling for server events
+            poll: Get "/poll" User query(since: u64) => [EventBatchResponse, PollingTimeout];
+            /// Websocket for server events and api calls
+            connect: Get "/ws" User query(since: u64, encoding: &str) => [EventBatchResponse];
+            /// Running games with their turn state and players
+            get_running_games: Get "/admin/games" Admin => [AdminGamesResponse];
+            /// Ends the current phase of the game on the next tick
+            advance_game: Patch "/admin/games/<game_id>/turn" Admin params(game_id: Uuid) => [AdminGamesResponse];
+            /// Ends the game without rewards and sends the players back to the menu
+            end_game: Delete "/admin/games/<game_id>" Admin params(game_id: Uuid) => [AdminGamesResponse];
+            get_player: Get "/admin/games/<game_id>/players/<player_id>" Admin params(game_id: Uuid, player_id: Uuid) => [AdminPlayerResponse];
+            /// Hands the slot of the player to a bot
+            kick_player: Delete "/admin/games/<game_id>/players/<player_id>" Admin params(game_id: Uuid, player_id: Uuid) => [AdminPlayerResponse];
+            /// Recent ban records including expired ones
+            get_bans: Get "/admin/bans" Admin => [UserBansResponse];
+            /// Bans the user, ends their sessions and hands their game slot to a bot
+            ban_user: Put "/admin/bans/<user_id>" Admin params(user_id: i32) body(ban: BanRequest) => [UserBansResponse];
+            /// Lifts all active bans of the user
+            unban_user: Delete "/admin/bans/<user_id>" Admin params(user_id: i32) => [UserBansResponse];
+            /// Sends a message to every online user
+            broadcast: Post "/admin/broadcast" Admin body(text: String) => [ServerMessageResponse];
+            /// OpenAPI description of this api
+            openapi: Get "/openapi.json" Public => [];
+        }
+    };
+}
+
+macro_rules! define_requests {
+    (@body) => { None };
+    (@body $body_ty:ty) => { Some(stringify!($body_ty)) };
+    (@value) => { None };
+    (@value $body:ident) => { serde_json::to_value($body).ok() };
+    ($(
+        $(#[doc = $doc:literal])*
+        $name:ident: $method:ident $path:literal $access:ident
+            $(params($($param:ident: $param_ty:ty),*))?
+            $(query($($query:ident: $query_ty:ty),*))?
+            $(body($body:ident: $body_ty:ty))?
+            => [$($response:ident),*];
+    )*) => {
+        pub const ENDPOINTS: &[Endpoint] = &[$(
+            Endpoint {
+                name: stringify!($name),
+                summary: concat!($($doc),*),
+                method: Method::$method,
+                path: $path,
+                params: &[$($((stringify!($param), stringify!($param_ty))),*)?],
+                query: &[$($((stringify!($query), stringify!($query_ty))),*)?],
+                access: Access::$access,
+                body: define_requests!(@body $($body_ty)?),
+                responses: &[$(stringify!($response)),*],
+            }
+        ),*];
+
+        $(
+            $(#[doc = $doc])*
+            #[allow(unused_mut, clippy::ptr_arg)]
+            pub fn $name(
+                $($($param: $param_ty,)*)?
+                $($($query: Option<$query_ty>,)*)?
+                $($body: &$body_ty)?
+            ) -> ApiRequest {
+                let mut path = $path.trim_start_matches('/').to_string();
+                $($(
+                    path = path.replace(concat!("<", stringify!($param), ">"), &$param.to_string());
+                )*)?
+
+                let mut query = form_urlencoded::Serializer::new(String::new());
+                $($(
+                    if let Some(value) = $query {
+                        query.append_pair(stringify!($query), &value.to_string());
+                    }
+                )*)?
+                let query = query.finish();
+                if !query.is_empty() {
+                    path = format!("{}?{}", path, query);
+                }
+
+                ApiRequest {
+                    endpoint: endpoint(stringify!($name)),
+                    path,
+                    body: define_requests!(@value $($body)?),
+                }
+            }
+        )*
+
+        /// Adds the request bodies to the generator. Returns their schemas by
+        /// type name.
+        #[cfg(feature = "schema")]
+        pub fn body_schemas(
+            generator: &mut schemars::gen::SchemaGenerator,
+        ) -> std::collections::BTreeMap<&'static str, schemars::schema::Schema> {
+            let mut bodies = std::collections::BTreeMap::new();
+            $($(
+                bodies
+                    .entry(stringify!($body_ty))
+                    .or_insert_with(|| generator.subschema_for::<$body_ty>());
+            )?)*
+            bodies
+        }
+
+        /// Keeps the documented responses in sync with the `Protocol` variants
+        #[allow(dead_code)]
+        fn is_documented_response(protocol: &Protocol) -> bool {
+            $($(matches!(protocol, Protocol::$response { .. }) ||)*)* false
+        }
+    };
+}
+
+for_each_endpoint!(define_requests);
+
+fn endpoint(name: &str) -> &'static Endpoint {
+    ENDPOINTS
+        .iter()
+        .find(|endpoint| endpoint.name == name)
+        .expect("Every request function has an endpoint")
+}
+
+/// Finds the endpoint of a path relative to the api base, e.g. `/games/shops`
+pub fn find_endpoint(method: Method, path: &str) -> Option<&'static Endpoint> {
+    let segments = path.split('/').collect::<Vec<_>>();
+
+    ENDPOINTS.iter().find(|endpoint| {
+        let pattern = endpoint.path.split('/').collect::<Vec<_>>();
+
+        endpoint.method == method
+            && pattern.len() == segments.len()
+            && pattern
+                .iter()
+                .zip(segments.iter())
+                .all(|(pattern, segment)| pattern.starts_with('<') || pattern == segment)
+    })
+}
@@ -0,0 +1,86 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+
+pub const JSON: &str = "application/json";
+pub const MSGPACK: &str = "application/msgpack";
+
+/// Wire format of `Protocol` messages. JSON is kept for debugging.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    Json,
+    #[default]
+    MessagePack,
+}
+
+#[derive(Debug)]
+pub enum CodecError {
+    Json(serde_json::Error),
+    MessagePackEncode(rmp_serde::encode::Error),
+    MessagePackDecode(rmp_serde::decode::Error),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(e) => write!(f, "Invalid json: {}", e),
+            Self::MessagePackEncode(e) => write!(f, "Failed to encode msgpack: {}", e),
+            Self::MessagePackDecode(e) => write!(f, "Invalid msgpack: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl Encoding {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => JSON,
+            Self::MessagePack => MSGPACK,
+        }
+    }
+
+    /// Parses a `Content-Type` or single `Accept` value, ignoring parameters
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        let media_type = content_type.split(';').next()?.trim();
+        if media_type.eq_ignore_ascii_case(MSGPACK) {
+            Some(Self::MessagePack)
+        } else if media_type.eq_ignore_ascii_case(JSON) {
+            Some(Self::Json)
+        } else {
+            None
+        }
+    }
+
+    /// Picks the first supported encoding of an `Accept` header
+    pub fn from_accept(accept: &str) -> Option<Self> {
+        accept.split(',').find_map(Self::from_content_type)
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, CodecError> {
+        match self {
+            Self::Json => serde_json::to_vec(value).map_err(CodecError::Json),
+            Self::MessagePack => {
+                rmp_serde::to_vec_named(value).map_err(CodecError::MessagePackEncode)
+            }
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T, CodecError> {
+        match self {
+            Self::Json => serde_json::from_slice(data).map_err(CodecError::Json),
+            Self::MessagePack => rmp_serde::from_slice(data).map_err(CodecError::MessagePackDecode),
+        }
+    }
+}
+
+impl std::str::FromStr for Encoding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Self::Json),
+            "msgpack" => Ok(Self::MessagePack),
+            _ => Err(()),
+        }
+    }
+}
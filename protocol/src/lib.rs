@@ -1,5 +1,10 @@
 pub use ::enum_iterator;
 pub use ::protocol_types;
+#[cfg(feature = "schema")]
+pub use ::schemars;
+pub use ::uuid;
+pub mod api;
+pub mod codec;
 pub mod protocol;
 
 pub mod gods {
@@ -9,3 +14,14 @@ pub mod gods {
 pub mod characters {
     include!(concat!(env!("OUT_DIR"), "/characters.rs"));
 }
+
+pub mod version {
+    /// Clients are only compatible with a server of the same protocol version
+    pub const PROTOCOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+    include!(concat!(env!("OUT_DIR"), "/data_hash.rs"));
+
+    pub fn is_compatible(version: &str, data_hash: &str) -> bool {
+        version == PROTOCOL_VERSION && data_hash == DATA_HASH
+    }
+}
@@ -1,12 +1,15 @@
+use crate::api::Method;
 use chrono::{DateTime, Duration, Utc};
 use protocol_types::{character::Character, heros::God, prelude::Ability};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 const START_TIME: i64 = 45;
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 200;
 const EXP_PER_LEVEL: u8 = 3;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Protocol {
     // Misc
     StatusResponse(Status),
@@ -16,8 +19,12 @@ pub enum Protocol {
     RegistrationRequest(Credentials),
     LoginRequest(Credentials),
     LoginResponse(LoginResponse),
+    SessionResponse(SessionKeys),
+    LogoutResponse,
     UserResponse(UserData),
     DisplaynameResponse(String),
+    LeaderboardResponse(Vec<LeaderboardEntry>),
+    TransactionsResponse(Vec<CurrencyTransaction>),
 
     // Lobby
     LobbyJoinRequest(LobbyJoinRequest),
@@ -25,6 +32,29 @@ pub enum Protocol {
     LobbyLeaveResponse,
     LobbyStartResponse,
     LobbyKickResponse,
+    LobbyListResponse(Vec<LobbyListEntry>),
+    LobbyInviteResponse(LobbyInvite),
+    LobbyInvitesResponse(Vec<LobbyInvite>),
+
+    // Friends
+    FriendsResponse(Vec<Friend>),
+    FriendPresenceResponse(FriendPresence),
+
+    // Chat
+    ChatMessageResponse(ChatMessage),
+    ChatHistoryResponse(Vec<ChatMessage>),
+    MutesResponse(Vec<MutedUser>),
+
+    // Admin
+    AdminGamesResponse(Vec<AdminGame>),
+    AdminPlayerResponse(AdminPlayer),
+    UserBansResponse(Vec<UserBan>),
+    /// Announcement of the server operators
+    ServerMessageResponse(String),
+
+    // Matchmaking
+    QueueStatusResponse(QueueStatus),
+    QueueLeaveResponse,
 
     // Game
     // TODO: Change to [God; 4]
@@ -45,52 +75,293 @@ pub enum Protocol {
     GameEndResponse(GameResult),
     GameUserInfoResponse(GameUserInfo),
     GameUsersResponse(Vec<GameOpponentInfo>),
+    GameStateResponse(GameState),
+    /// The player was removed from the game or the game was ended early
+    GameLeaveResponse,
+    /// A bot plays for the idle user until their next action. Users that were
+    /// offline get their slot back once they poll again.
+    GameAutoplayResponse,
 
     CharacterMoveRequest,
     BoardResponse(Vec<Option<CharacterInstance>>),
 
     // Polling
     PollingTimeout,
+    EventBatchResponse(EventBatch),
+    EventAck(u64),
+    /// Api request sent over the websocket
+    ApiCall(ApiCall),
+    /// Response to the `ApiCall` with the same id
+    ApiReply(u64, Box<Protocol>),
 
     // Error
     NetworkingError(Error),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Credentials {
     pub username: String,
     pub password: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Status {
     pub version: String,
+    /// Hash of the generated character and god tables
+    pub data_hash: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct UserData {
     pub id: i32,
     pub username: String,
     pub display_name: Option<String>,
     pub currency: i32,
+    pub rating: i32,
     pub lobby: Option<LobbyInfo>,
+    /// Guests have no credentials until they claim the account
+    pub guest: bool,
+    pub role: Role,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Role {
+    #[default]
+    Player,
+    /// Can use the `/admin` api
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Player => "player",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "player" => Ok(Role::Player),
+            "admin" => Ok(Role::Admin),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LeaderboardEntry {
+    pub rank: u32,
+    pub name: String,
+    pub rating: i32,
+    pub games: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CurrencyTransaction {
+    pub amount: i32,
+    pub source: String,
+    pub game: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LobbyJoinRequest {
     pub name: String,
     pub passphrase: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SequencedEvent {
+    pub seq: u64,
+    pub event: Protocol,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EventBatch {
+    /// Current state if the requested events could no longer be replayed
+    pub snapshot: Option<Vec<Protocol>>,
+    pub events: Vec<SequencedEvent>,
+    /// Sequence id to resume from
+    pub last_seq: u64,
+}
+
+/// Request for an endpoint of `api::ENDPOINTS`. The path is relative to the
+/// api base and includes the query.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ApiCall {
+    pub id: u64,
+    pub method: Method,
+    pub path: String,
+    pub body: Option<serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct QueueStatus {
+    pub players: u32,
+    pub rating_window: i32,
+    pub queued_since: DateTime<Utc>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LobbyInfo {
     pub name: String,
     pub users: Vec<LobbyUser>,
     pub master: i32,
     pub start_at: Option<DateTime<Utc>>,
+    pub settings: LobbySettings,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LobbySettings {
+    pub max_players: u8,
+    pub bot_fill: bool,
+    pub rules: RulesPreset,
+    pub rated: bool,
+}
+
+impl Default for LobbySettings {
+    fn default() -> Self {
+        Self {
+            max_players: 8,
+            bot_fill: true,
+            rules: RulesPreset::default(),
+            rated: true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum RulesPreset {
+    #[default]
+    Standard,
+    /// Shorter shop phases
+    Fast,
+}
+
+impl RulesPreset {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RulesPreset::Standard => "standard",
+            RulesPreset::Fast => "fast",
+        }
+    }
+}
+
+impl std::str::FromStr for RulesPreset {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(RulesPreset::Standard),
+            "fast" => Ok(RulesPreset::Fast),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LobbyListEntry {
+    pub name: String,
+    pub players: u8,
+    pub settings: LobbySettings,
+    pub has_passphrase: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LobbyInvite {
+    pub id: i32,
+    pub lobby: String,
+    pub from: String,
+}
+
+/// What a user is currently doing. Only shared with friends.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Presence {
+    #[default]
+    Offline,
+    Menu,
+    Lobby,
+    Game,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum FriendStatus {
+    Friend,
+    /// Request from the other user waiting for an answer
+    Incoming,
+    /// Request to the other user waiting for an answer
+    Outgoing,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Friend {
+    /// User id of the friend
+    pub id: i32,
+    pub name: String,
+    pub status: FriendStatus,
+    /// Always `Offline` until the request is accepted
+    pub presence: Presence,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FriendPresence {
+    /// User id of the friend
+    pub id: i32,
+    pub presence: Presence,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ChatChannel {
+    Lobby,
+    Game,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ChatMessage {
+    pub channel: ChatChannel,
+    /// User id of the sender
+    pub from_id: i32,
+    pub from: String,
+    pub text: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MutedUser {
+    pub id: i32,
+    pub name: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LobbyUser {
     pub id: i32,
     pub name: String,
@@ -98,12 +369,26 @@ pub struct LobbyUser {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct LoginResponse {
     pub key: String,
+    /// Exchanged for a new session once `key` expired
+    pub refresh_key: String,
     pub user: UserData,
+    /// Game the user is still playing in
+    pub game: Option<Uuid>,
+}
+
+/// Keys of a refreshed session
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SessionKeys {
+    pub key: String,
+    pub refresh_key: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BattleResponse {
     pub actions: Vec<BattleAction>,
     pub start_own: Vec<Option<CharacterInstance>>,
@@ -127,6 +412,7 @@ impl BattleResponse {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BattleAction {
     pub action: BattleActionType,
     pub source: Uuid,
@@ -146,6 +432,7 @@ impl BattleAction {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum BattleActionType {
     Attack,
     Die,
@@ -153,16 +440,19 @@ pub enum BattleActionType {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BattleResult {
     pub dmg: i32,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GameUpdate {
     pub turn: Turn,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GameUserInfo {
     pub name: String,
     pub experience: u8,
@@ -171,7 +461,23 @@ pub struct GameUserInfo {
     pub avatar: Option<i32>,
 }
 
+/// Everything a client needs to rebuild a running game
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GameState {
+    pub turn: Turn,
+    pub user: GameUserInfo,
+    pub god_choices: [i32; 4],
+    pub shop_locked: bool,
+    pub shop: Vec<Option<CharacterInstance>>,
+    pub board: Vec<Option<CharacterInstance>>,
+    /// Players ordered by standing. The next opponent is flagged with `is_next_opponent`
+    pub opponents: Vec<GameOpponentInfo>,
+    pub last_battle: Option<BattleResponse>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GameOpponentInfo {
     pub name: String,
     pub experience: u8,
@@ -187,19 +493,86 @@ impl GameOpponentInfo {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct GameResult {
     pub place: u8,
     pub reward: i32,
     pub ranking: i32,
+    pub ranking_change: i32,
+}
+
+/// Running game as seen by the server operators
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AdminGame {
+    pub id: Uuid,
+    pub turn: Turn,
+    pub rated: bool,
+    pub rules: RulesPreset,
+    pub players: Vec<AdminPlayer>,
+}
+
+/// Complete state of a player slot in a running game
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AdminPlayer {
+    pub id: Uuid,
+    /// `None` for bots
+    pub user_id: Option<i32>,
+    pub name: String,
+    pub god: Option<i32>,
+    pub god_choices: [i32; 4],
+    pub health: i16,
+    pub money: u16,
+    pub experience: u8,
+    pub placement: Option<u8>,
+    pub rating: i32,
+    pub shop_locked: bool,
+    pub shop: Vec<Option<CharacterInstance>>,
+    pub board: Vec<Option<CharacterInstance>>,
+    /// A bot plays for the idle user
+    pub autoplay: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UserBan {
+    pub id: i32,
+    pub user_id: i32,
+    pub username: String,
+    /// Admin that issued the ban, if the account still exists
+    pub issued_by: Option<i32>,
+    pub reason: String,
+    /// `None` for permanent bans
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BanRequest {
+    pub reason: String,
+    /// Bans without an end are permanent
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Shown to banned users when they are rejected
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BanInfo {
+    pub reason: String,
+    pub expires_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct BuyRequest {
     pub character_idx: u8,
     pub target_idx: u8,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct CharacterInstance {
     pub id: Uuid,
     pub character_id: i32,
@@ -278,6 +651,7 @@ impl CharacterInstance {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Turn {
     Shop(u16, DateTime<Utc>),
     Combat(u16, DateTime<Utc>),
@@ -324,10 +698,16 @@ impl Turn {
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Error {
     pub message: String,
     pub status: u16,
     pub reference: Option<Box<Protocol>>,
+    #[serde(default)]
+    pub code: ErrorCode,
+    /// Set for `AccountBanned`
+    #[serde(default)]
+    pub ban: Option<BanInfo>,
 }
 
 impl Error {
@@ -348,6 +728,7 @@ impl Error {
             message,
             status,
             reference: Some(Box::new(reference)),
+            ..Default::default()
         })
     }
 
@@ -356,3 +737,148 @@ impl Error {
         self
     }
 }
+
+impl From<ErrorCode> for Error {
+    fn from(code: ErrorCode) -> Self {
+        Self {
+            message: code.message().to_string(),
+            status: code.status(),
+            code,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<BanInfo> for Error {
+    fn from(ban: BanInfo) -> Self {
+        let mut error = Error::from(ErrorCode::AccountBanned);
+        error.message = match ban.expires_at {
+            Some(expires_at) => format!(
+                "Account is banned until {}: {}",
+                expires_at.format("%Y-%m-%d %H:%M UTC"),
+                ban.reason
+            ),
+            None => format!("Account is banned permanently: {}", ban.reason),
+        };
+        error.ban = Some(ban);
+        error
+    }
+}
+
+impl From<ErrorCode> for Protocol {
+    fn from(code: ErrorCode) -> Self {
+        Protocol::NetworkingError(code.into())
+    }
+}
+
+/// Reason of a failed request. Errors without a specific reason are `Unknown`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ErrorCode {
+    #[default]
+    Unknown,
+    Internal,
+    InvalidRequest,
+    NotFound,
+    Unauthorized,
+    Forbidden,
+    AccountBanned,
+    InvalidSession,
+    InvalidCredentials,
+    AccountLocked,
+    UsernameTaken,
+    AlreadyClaimed,
+    InvalidDisplayName,
+    DisplayNameTooShort,
+    DisplayNameTooLong,
+    DisplayNameTaken,
+    DisplayNameNotAllowed,
+    DisplayNameRequired,
+    NotInGame,
+    AlreadyInGame,
+    WrongPhase,
+    NotEnoughMoney,
+    BoardFull,
+    InvalidShopIndex,
+    InvalidBoardIndex,
+    ShopSlotEmpty,
+    BoardSlotEmpty,
+    AvatarAlreadyChosen,
+    AvatarNotAvailable,
+    CannotFriendSelf,
+    InvalidChatMessage,
+    RateLimited,
+}
+
+impl ErrorCode {
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::Unknown | Self::Internal => 500,
+            Self::Unauthorized | Self::InvalidSession | Self::InvalidCredentials => 401,
+            Self::Forbidden | Self::AccountBanned => 403,
+            Self::NotFound | Self::NotInGame => 404,
+            Self::InvalidRequest
+            | Self::CannotFriendSelf
+            | Self::InvalidChatMessage
+            | Self::InvalidDisplayName
+            | Self::DisplayNameTooShort
+            | Self::DisplayNameTooLong
+            | Self::DisplayNameNotAllowed
+            | Self::InvalidShopIndex
+            | Self::InvalidBoardIndex => 400,
+            Self::AlreadyInGame
+            | Self::WrongPhase
+            | Self::NotEnoughMoney
+            | Self::BoardFull
+            | Self::ShopSlotEmpty
+            | Self::BoardSlotEmpty
+            | Self::AvatarAlreadyChosen
+            | Self::AvatarNotAvailable
+            | Self::UsernameTaken
+            | Self::AlreadyClaimed
+            | Self::DisplayNameTaken
+            | Self::DisplayNameRequired => 409,
+            Self::AccountLocked => 423,
+            Self::RateLimited => 429,
+        }
+    }
+
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::Unknown => "Unknown error",
+            Self::Internal => "Internal server error",
+            Self::InvalidRequest => "Malformed request",
+            Self::NotFound => "Not found",
+            Self::Unauthorized => "Not logged in",
+            Self::Forbidden => "Not allowed",
+            Self::AccountBanned => "Account is banned",
+            Self::InvalidSession => "Session is invalid or expired",
+            Self::InvalidCredentials => "Invalid username or password",
+            Self::AccountLocked => "Too many failed logins, try again later",
+            Self::UsernameTaken => "Username is already taken",
+            Self::AlreadyClaimed => "Account already has credentials",
+            Self::InvalidDisplayName => {
+                "Display name must start with a letter and may only contain letters, digits and single spaces, - or _"
+            }
+            Self::DisplayNameTooShort => "Display name is too short",
+            Self::DisplayNameTooLong => "Display name is too long",
+            Self::DisplayNameTaken => "Display name is already taken",
+            Self::DisplayNameNotAllowed => "Display name is not allowed",
+            Self::DisplayNameRequired => "Set a display name first",
+            Self::NotInGame => "Not in a game",
+            Self::AlreadyInGame => "Already in a game",
+            Self::WrongPhase => "Not possible in the current phase",
+            Self::NotEnoughMoney => "Not enough gold",
+            Self::BoardFull => "Board is full",
+            Self::InvalidShopIndex => "Invalid shop slot",
+            Self::InvalidBoardIndex => "Invalid board slot",
+            Self::ShopSlotEmpty => "Shop slot is empty",
+            Self::BoardSlotEmpty => "Board slot is empty",
+            Self::AvatarAlreadyChosen => "Avatar already chosen",
+            Self::AvatarNotAvailable => "Avatar not available",
+            Self::CannotFriendSelf => "You can not add yourself as a friend",
+            Self::InvalidChatMessage => "Chat messages must be 1 to 200 characters of text",
+            Self::RateLimited => "Too many requests, try again later",
+        }
+    }
+}
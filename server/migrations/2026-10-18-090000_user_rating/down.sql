@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX idx_users_rating;
+ALTER TABLE users DROP COLUMN rated_games;
+ALTER TABLE users DROP COLUMN rating;
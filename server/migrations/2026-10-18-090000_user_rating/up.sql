@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE users
+ADD COLUMN rating INT NOT NULL DEFAULT 1000;
+ALTER TABLE users
+ADD COLUMN rated_games INT NOT NULL DEFAULT 0;
+CREATE INDEX idx_users_rating ON users(rating DESC)
+WHERE rated_games > 0;
@@ -0,0 +1,19 @@
+-- Your SQL goes here
+CREATE TABLE currency_transactions (
+	id SERIAL PRIMARY KEY,
+	user_id INT NOT NULL,
+	amount INT NOT NULL,
+	source VARCHAR(32) NOT NULL,
+	game_id UUID,
+	created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	CONSTRAINT uq_currency_transaction_game UNIQUE(user_id, game_id, source),
+	CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_currency_transactions_user ON currency_transactions(user_id);
+-- Seed the ledger with the existing balances so it can be used to rebuild them
+INSERT INTO currency_transactions (user_id, amount, source)
+SELECT id,
+	currency,
+	'initial_balance'
+FROM users
+WHERE currency <> 0;
@@ -0,0 +1,5 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE lobbies DROP COLUMN rated;
+ALTER TABLE lobbies DROP COLUMN rules;
+ALTER TABLE lobbies DROP COLUMN bot_fill;
+ALTER TABLE lobbies DROP COLUMN max_players;
@@ -0,0 +1,11 @@
+-- Your SQL goes here
+ALTER TABLE lobbies
+ADD COLUMN max_players INT NOT NULL DEFAULT 8 CHECK (
+		max_players BETWEEN 1 AND 8
+	);
+ALTER TABLE lobbies
+ADD COLUMN bot_fill BOOLEAN NOT NULL DEFAULT 't';
+ALTER TABLE lobbies
+ADD COLUMN rules VARCHAR(32) NOT NULL DEFAULT 'standard';
+ALTER TABLE lobbies
+ADD COLUMN rated BOOLEAN NOT NULL DEFAULT 't';
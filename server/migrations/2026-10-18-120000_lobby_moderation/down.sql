@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE lobby_invites;
+DROP TABLE lobby_bans;
@@ -0,0 +1,28 @@
+-- Your SQL goes here
+CREATE TABLE lobby_bans (
+	id SERIAL PRIMARY KEY,
+	lobby_id INT NOT NULL,
+	user_id INT NOT NULL,
+	expires_at TIMESTAMP NOT NULL,
+	created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	CONSTRAINT uq_lobby_ban UNIQUE(lobby_id, user_id),
+	CONSTRAINT fk_lobby FOREIGN KEY(lobby_id) REFERENCES lobbies(id) ON DELETE CASCADE,
+	CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TRIGGER update_lobby_bans_updated_at BEFORE
+UPDATE ON lobby_bans FOR EACH ROW EXECUTE PROCEDURE set_updated_at_date();
+CREATE TABLE lobby_invites (
+	id SERIAL PRIMARY KEY,
+	lobby_id INT NOT NULL,
+	user_id INT NOT NULL,
+	inviter_id INT NOT NULL,
+	created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	CONSTRAINT uq_lobby_invite UNIQUE(lobby_id, user_id),
+	CONSTRAINT fk_lobby FOREIGN KEY(lobby_id) REFERENCES lobbies(id) ON DELETE CASCADE,
+	CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE,
+	CONSTRAINT fk_inviter FOREIGN KEY(inviter_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TRIGGER update_lobby_invites_updated_at BEFORE
+UPDATE ON lobby_invites FOR EACH ROW EXECUTE PROCEDURE set_updated_at_date();
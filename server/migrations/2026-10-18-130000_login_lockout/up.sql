@@ -0,0 +1,5 @@
+-- Your SQL goes here
+ALTER TABLE users
+ADD COLUMN failed_logins INT NOT NULL DEFAULT 0;
+ALTER TABLE users
+ADD COLUMN locked_until TIMESTAMP;
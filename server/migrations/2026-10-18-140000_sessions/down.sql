@@ -0,0 +1,6 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE users
+ADD COLUMN session_token UUID UNIQUE;
+ALTER TABLE users
+ADD COLUMN session_expires TIMESTAMP;
+DROP TABLE sessions;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE sessions (
+	id SERIAL PRIMARY KEY,
+	user_id INT NOT NULL,
+	token UUID UNIQUE NOT NULL,
+	refresh_token UUID UNIQUE NOT NULL,
+	expires_at TIMESTAMP NOT NULL,
+	refresh_expires_at TIMESTAMP NOT NULL,
+	created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_sessions_user_id ON sessions(user_id);
+CREATE TRIGGER update_sessions_updated_at BEFORE
+UPDATE ON sessions FOR EACH ROW EXECUTE PROCEDURE set_updated_at_date();
+-- Existing sessions are dropped. Users have to log in again.
+ALTER TABLE users DROP COLUMN session_token;
+ALTER TABLE users DROP COLUMN session_expires;
@@ -0,0 +1,3 @@
+-- Your SQL goes here
+ALTER TABLE users
+ADD COLUMN guest BOOLEAN NOT NULL DEFAULT 'f';
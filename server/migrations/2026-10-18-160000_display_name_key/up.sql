@@ -0,0 +1,6 @@
+-- Your SQL goes here
+ALTER TABLE users
+ADD COLUMN display_name_key VARCHAR(64) UNIQUE;
+-- Existing names are kept as they are. Normalizing them could collide.
+UPDATE users
+SET display_name_key = display_name;
@@ -0,0 +1,18 @@
+-- Your SQL goes here
+CREATE TABLE friendships (
+	id SERIAL PRIMARY KEY,
+	user_id INT NOT NULL,
+	friend_id INT NOT NULL,
+	accepted BOOLEAN NOT NULL DEFAULT 'f',
+	created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	CONSTRAINT ck_friendship_self CHECK (user_id <> friend_id),
+	CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE,
+	CONSTRAINT fk_friend FOREIGN KEY(friend_id) REFERENCES users(id) ON DELETE CASCADE
+);
+-- One row per pair, no matter who sent the request
+CREATE UNIQUE INDEX uq_friendship ON friendships(LEAST(user_id, friend_id), GREATEST(user_id, friend_id));
+CREATE INDEX idx_friendships_user_id ON friendships(user_id);
+CREATE INDEX idx_friendships_friend_id ON friendships(friend_id);
+CREATE TRIGGER update_friendships_updated_at BEFORE
+UPDATE ON friendships FOR EACH ROW EXECUTE PROCEDURE set_updated_at_date();
@@ -0,0 +1,13 @@
+-- Your SQL goes here
+CREATE TABLE user_mutes (
+	id SERIAL PRIMARY KEY,
+	user_id INT NOT NULL,
+	muted_user_id INT NOT NULL,
+	created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	CONSTRAINT uq_user_mute UNIQUE(user_id, muted_user_id),
+	CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE,
+	CONSTRAINT fk_muted_user FOREIGN KEY(muted_user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+CREATE TRIGGER update_user_mutes_updated_at BEFORE
+UPDATE ON user_mutes FOR EACH ROW EXECUTE PROCEDURE set_updated_at_date();
@@ -0,0 +1,16 @@
+-- Your SQL goes here
+ALTER TABLE users
+ADD COLUMN role VARCHAR(16) NOT NULL DEFAULT 'player',
+	ADD CONSTRAINT chk_user_role CHECK (role IN ('player', 'admin'));
+CREATE TABLE user_bans (
+	id SERIAL PRIMARY KEY,
+	user_id INT NOT NULL,
+	issued_by INT,
+	created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+	CONSTRAINT uq_user_ban UNIQUE(user_id),
+	CONSTRAINT fk_user FOREIGN KEY(user_id) REFERENCES users(id) ON DELETE CASCADE,
+	CONSTRAINT fk_issued_by FOREIGN KEY(issued_by) REFERENCES users(id) ON DELETE SET NULL
+);
+CREATE TRIGGER update_user_bans_updated_at BEFORE
+UPDATE ON user_bans FOR EACH ROW EXECUTE PROCEDURE set_updated_at_date();
@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX idx_user_bans_user_id;
+DELETE FROM user_bans
+WHERE expires_at <= CURRENT_TIMESTAMP;
+DELETE FROM user_bans a USING user_bans b
+WHERE a.user_id = b.user_id
+	AND a.id < b.id;
+ALTER TABLE user_bans DROP COLUMN reason,
+	DROP COLUMN expires_at,
+	ADD CONSTRAINT uq_user_ban UNIQUE(user_id);
@@ -0,0 +1,7 @@
+-- Your SQL goes here
+ALTER TABLE user_bans DROP CONSTRAINT uq_user_ban,
+	ADD COLUMN reason VARCHAR(256) NOT NULL DEFAULT '',
+	ADD COLUMN expires_at TIMESTAMP;
+ALTER TABLE user_bans
+ALTER COLUMN reason DROP DEFAULT;
+CREATE INDEX idx_user_bans_user_id ON user_bans(user_id);
@@ -1,2 +1,3 @@
 pub(crate) mod cache;
 pub(crate) mod perf_log;
+pub(crate) mod rate_limit;
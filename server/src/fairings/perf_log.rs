@@ -56,7 +56,7 @@ impl<'r> FromRequest<'r> for StartTime {
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
         match *request.local_cache(|| TimerStart(None)) {
             TimerStart(Some(time)) => Outcome::Success(StartTime(time)),
-            TimerStart(None) => Outcome::Failure((Status::InternalServerError, ())),
+            TimerStart(None) => Outcome::Error((Status::InternalServerError, ())),
         }
     }
 }
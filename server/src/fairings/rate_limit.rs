@@ -0,0 +1,510 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    env,
+    hash::Hash,
+    mem,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use protocol::{
+    api::{find_endpoint, Access, ENDPOINTS},
+    protocol::ErrorCode,
+};
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    http::{uri::Origin, Header},
+    Data, Request, Response,
+};
+
+use crate::model::{users, GuardError};
+
+const API_BASE: &str = "/api/v1";
+/// Not mounted. Limited requests are rewritten to it so no route is executed.
+const LIMITED_PATH: &str = "/api/v1/rate_limited";
+/// Number of recently used buckets that are kept
+const BUCKET_CAPACITY: usize = 10_000;
+
+/// Defaults for `RATE_LIMIT_<ENDPOINT>` as `(endpoint, requests, seconds)`
+const ROUTE_LIMITS: &[(&str, u32, u64)] = &[
+    ("register", 5, 600),
+    ("login", 10, 60),
+    ("guest_login", 5, 600),
+    ("claim_account", 5, 60),
+    ("refresh_session", 10, 60),
+    ("set_display_name", 5, 60),
+    ("invite_user", 10, 60),
+    ("add_friend", 10, 60),
+    ("mute_user", 10, 60),
+    ("post_lobby_message", 5, 5),
+    ("post_game_message", 5, 5),
+    ("reroll_shop", 5, 1),
+    ("buy_character", 5, 1),
+    ("sell_character", 5, 1),
+    ("move_character", 10, 1),
+];
+
+/// Allows `requests` requests in a burst, refilled over `per`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limit {
+    pub requests: u32,
+    pub per: Duration,
+}
+
+impl Limit {
+    pub fn new(requests: u32, seconds: u64) -> Self {
+        Self {
+            requests,
+            per: Duration::from_secs(seconds),
+        }
+    }
+
+    /// Parses `<requests>/<seconds>`, e.g. `10/60`
+    pub fn parse(value: &str) -> Option<Self> {
+        let (requests, seconds) = value.trim().split_once('/')?;
+        let limit = Self::new(requests.trim().parse().ok()?, seconds.trim().parse().ok()?);
+
+        (limit.requests > 0 && !limit.per.is_zero()).then_some(limit)
+    }
+
+    /// Tokens refilled per second
+    fn per_second(&self) -> f64 {
+        self.requests as f64 / self.per.as_secs_f64()
+    }
+
+    /// Reads the limit from `name`. `off` disables the limit.
+    fn from_env(name: &str, default: Option<Limit>) -> Option<Limit> {
+        match env::var(name) {
+            Ok(value) if value.trim().eq_ignore_ascii_case("off") => None,
+            Ok(value) => Limit::parse(&value).or_else(|| {
+                warn!(
+                    "{} {} is not in the format <requests>/<seconds>",
+                    name, value
+                );
+                default
+            }),
+            Err(_) => default,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// Limit for all unauthenticated api requests of an ip address
+    pub ip: Option<Limit>,
+    /// Limit for all api requests of a user
+    pub user: Option<Limit>,
+    /// Limits for single endpoints by endpoint name
+    pub routes: HashMap<&'static str, Limit>,
+    /// Header with the client address set by a trusted reverse proxy. Without
+    /// it the address of the connection is used.
+    pub proxy_header: Option<String>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            ip: Some(Limit::new(120, 10)),
+            user: Some(Limit::new(60, 10)),
+            routes: ROUTE_LIMITS
+                .iter()
+                .map(|(name, requests, seconds)| (*name, Limit::new(*requests, *seconds)))
+                .collect(),
+            proxy_header: None,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Overrides the defaults with `RATE_LIMIT_IP`, `RATE_LIMIT_USER` and
+    /// `RATE_LIMIT_<ENDPOINT>`, e.g. `RATE_LIMIT_LOGIN=10/60`. Behind a reverse
+    /// proxy `RATE_LIMIT_PROXY_HEADER` names the header with the client address.
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            ip: Limit::from_env("RATE_LIMIT_IP", default.ip),
+            user: Limit::from_env("RATE_LIMIT_USER", default.user),
+            routes: ENDPOINTS
+                .iter()
+                .filter_map(|endpoint| {
+                    Limit::from_env(
+                        &format!("RATE_LIMIT_{}", endpoint.name.to_uppercase()),
+                        default.routes.get(endpoint.name).copied(),
+                    )
+                    .map(|limit| (endpoint.name, limit))
+                })
+                .collect(),
+            proxy_header: env::var("RATE_LIMIT_PROXY_HEADER")
+                .ok()
+                .filter(|header| !header.trim().is_empty()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Client {
+    Ip(IpAddr),
+    User(i32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum BucketKey {
+    Global(Client),
+    Route(&'static str, Client),
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: &Limit, now: Instant) -> Self {
+        Self {
+            tokens: limit.requests as f64,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, limit: &Limit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * limit.per_second()).min(limit.requests as f64);
+        self.updated = now;
+    }
+
+    /// Time until a token is available
+    fn wait_time(&self, limit: &Limit) -> Duration {
+        Duration::from_secs_f64(((1.0 - self.tokens) / limit.per_second()).max(0.0))
+    }
+}
+
+/// Map that keeps about the `capacity` most recently used entries. Once the
+/// current generation is full it replaces the previous one, so entries that
+/// were not used during two generations are dropped.
+struct GenerationMap<K, V> {
+    capacity: usize,
+    current: HashMap<K, V>,
+    previous: HashMap<K, V>,
+}
+
+impl<K: Eq + Hash, V> GenerationMap<K, V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            current: HashMap::new(),
+            previous: HashMap::new(),
+        }
+    }
+
+    fn get_or_insert_with(&mut self, key: K, default: impl FnOnce() -> V) -> &mut V {
+        if !self.current.contains_key(&key) && self.current.len() >= self.capacity {
+            self.previous = mem::take(&mut self.current);
+        }
+
+        match self.current.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = self.previous.remove(entry.key()).unwrap_or_else(default);
+                entry.insert(value)
+            }
+        }
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        match self.current.get_mut(key) {
+            Some(value) => Some(value),
+            None => self.previous.get_mut(key),
+        }
+    }
+}
+
+/// Time a limited request has to wait before retrying
+#[derive(Clone, Copy)]
+struct RetryAfter(Option<Duration>);
+
+/// Token bucket rate limiting for the api. Requests over the limit are answered
+/// with a `RateLimited` error. Clones share their buckets.
+#[derive(Clone)]
+pub struct RateLimitFairing {
+    config: RateLimitConfig,
+    buckets: Arc<Mutex<GenerationMap<BucketKey, TokenBucket>>>,
+}
+
+impl RateLimitFairing {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Arc::new(Mutex::new(GenerationMap::new(BUCKET_CAPACITY))),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(RateLimitConfig::from_env())
+    }
+
+    /// Address of the client. Forwarded addresses are only used from the
+    /// configured proxy header, as clients can set any other header.
+    fn client_ip(&self, req: &Request<'_>) -> Option<IpAddr> {
+        let forwarded = self.config.proxy_header.as_ref().and_then(|header| {
+            req.headers()
+                .get_one(header)
+                .and_then(|ip| ip.trim().parse().ok())
+        });
+
+        forwarded.or_else(|| req.remote().map(|remote| remote.ip()))
+    }
+
+    /// Takes a token from every bucket applying to the request. Returns the
+    /// time to wait if any of them is empty.
+    fn check(&self, req: &Request<'_>, user_id: Option<i32>) -> Option<Duration> {
+        let path = req.uri().path();
+        let path = path.as_str().strip_prefix(API_BASE)?;
+
+        let ip = self.client_ip(req).map(Client::Ip);
+        let user = user_id.map(Client::User);
+
+        // Authenticated requests are limited per user instead of sharing the
+        // bucket of their address
+        let mut limits = vec![];
+        match (&user, &ip) {
+            (Some(user), _) => {
+                if let Some(limit) = self.config.user {
+                    limits.push((BucketKey::Global(user.clone()), limit));
+                }
+            }
+            (None, Some(ip)) => {
+                if let Some(limit) = self.config.ip {
+                    limits.push((BucketKey::Global(ip.clone()), limit));
+                }
+            }
+            (None, None) => {}
+        }
+        let endpoint = req
+            .method()
+            .as_str()
+            .parse()
+            .ok()
+            .and_then(|method| find_endpoint(method, path));
+        if let Some(endpoint) = endpoint {
+            // Sessions are free to create, so public endpoints are limited by ip
+            let client = match endpoint.access {
+                Access::Public => ip,
+                _ => user.or(ip),
+            };
+            if let (Some(limit), Some(client)) = (self.config.routes.get(endpoint.name), client) {
+                limits.push((BucketKey::Route(endpoint.name, client), *limit));
+            }
+        }
+
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+
+        let mut wait_time = None;
+        for (key, limit) in limits.iter() {
+            let bucket = buckets.get_or_insert_with(key.clone(), || TokenBucket::new(limit, now));
+            bucket.refill(limit, now);
+
+            if bucket.tokens < 1.0 {
+                wait_time = wait_time.max(Some(bucket.wait_time(limit)));
+            }
+        }
+
+        // Rejected requests do not use up the other buckets
+        if wait_time.is_none() {
+            for (key, _) in limits {
+                if let Some(bucket) = buckets.get_mut(&key) {
+                    bucket.tokens -= 1.0;
+                }
+            }
+        }
+
+        wait_time
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for RateLimitFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Rate Limiting",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _data: &mut Data<'_>) {
+        if !req.uri().path().as_str().starts_with(API_BASE) {
+            return;
+        }
+        // Cached for the guards of the request
+        let user_id = match users::authenticate(req).await {
+            Ok((user, _)) => Some(user.id),
+            Err(_) => None,
+        };
+        let Some(wait_time) = self.check(req, user_id) else {
+            return;
+        };
+
+        debug!("Rate limited {} {}", req.method(), req.uri());
+        req.local_cache(|| GuardError(Some(ErrorCode::RateLimited.into())));
+        req.local_cache(|| RetryAfter(Some(wait_time)));
+        match Origin::parse(LIMITED_PATH) {
+            Ok(uri) => req.set_uri(uri),
+            Err(err) => error!("Invalid rate limit path: {}", err),
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, response: &mut Response<'r>) {
+        if let RetryAfter(Some(wait_time)) = req.local_cache(|| RetryAfter(None)) {
+            response.set_header(Header::new(
+                "Retry-After",
+                wait_time.as_secs_f64().ceil().max(1.0).to_string(),
+            ));
+        }
+    }
+}
+
+#[test]
+fn test_token_bucket() {
+    use protocol::api::Method;
+
+    let limit = Limit::new(2, 10);
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(&limit, start);
+
+    bucket.tokens -= 2.0;
+    assert_eq!(bucket.wait_time(&limit), Duration::from_secs(5));
+
+    bucket.refill(&limit, start + Duration::from_secs(5));
+    assert!((bucket.tokens - 1.0).abs() < f64::EPSILON);
+
+    bucket.refill(&limit, start + Duration::from_secs(60));
+    assert!((bucket.tokens - 2.0).abs() < f64::EPSILON);
+
+    assert_eq!(Limit::parse("10/60"), Some(Limit::new(10, 60)));
+    assert_eq!(Limit::parse(" 3 / 1 "), Some(Limit::new(3, 1)));
+    assert_eq!(Limit::parse("0/60"), None);
+    assert_eq!(Limit::parse("10/0"), None);
+    assert_eq!(Limit::parse("10"), None);
+
+    assert_eq!(
+        find_endpoint(Method::Post, "/users").map(|e| e.name),
+        Some("login")
+    );
+    assert_eq!(
+        find_endpoint(Method::Put, "/games/characters/1/2").map(|e| e.name),
+        Some("move_character")
+    );
+    assert!(find_endpoint(Method::Get, "/games/characters/1").is_none());
+}
+
+#[test]
+fn test_generation_map() {
+    let mut map = GenerationMap::new(2);
+    *map.get_or_insert_with(1, || 0) += 1;
+    *map.get_or_insert_with(2, || 0) += 1;
+
+    // Full generations move to the previous one and are kept while used
+    *map.get_or_insert_with(3, || 0) += 1;
+    assert_eq!(*map.get_or_insert_with(1, || 0), 1);
+    assert_eq!(map.current.len() + map.previous.len(), 3);
+
+    // Entries unused for two generations are dropped
+    map.get_or_insert_with(4, || 0);
+    map.get_or_insert_with(5, || 0);
+    assert!(map.get_mut(&2).is_none());
+    assert_eq!(*map.get_or_insert_with(2, || 0), 0);
+    assert!(map.current.len() + map.previous.len() <= 4);
+}
+
+#[test]
+fn test_rate_limited_requests() {
+    use crate::{
+        model::{
+            routes::{get_api, get_catchers, without_sentinels},
+            sessions::SessionConfig,
+        },
+        service::display_name_service::DisplayNameRules,
+        RunningGames,
+    };
+    use protocol::protocol::Protocol;
+    use rocket::{http::Status, local::blocking::Client};
+
+    let mut config = RateLimitConfig {
+        ip: Some(Limit::new(3, 60)),
+        user: None,
+        routes: HashMap::new(),
+        proxy_header: None,
+    };
+    config.routes.insert("leaderboard", Limit::new(1, 60));
+
+    let rocket = rocket::build()
+        .attach(RateLimitFairing::new(config))
+        .mount(API_BASE, without_sentinels(get_api()))
+        .register(API_BASE, get_catchers())
+        .manage(RunningGames {
+            games: Default::default(),
+        })
+        .manage(SessionConfig::default())
+        .manage(DisplayNameRules::default());
+    let client = Client::untracked(rocket).expect("valid rocket instance");
+    let remote = "127.0.0.1:8000".parse().unwrap();
+
+    assert_eq!(
+        client
+            .get("/api/v1/status")
+            .remote(remote)
+            .dispatch()
+            .status(),
+        Status::Ok
+    );
+    // Route limit of the leaderboard
+    client
+        .get("/api/v1/users/leaderboard")
+        .remote(remote)
+        .dispatch();
+    let response = client
+        .get("/api/v1/users/leaderboard")
+        .remote(remote)
+        .dispatch();
+    assert_eq!(response.status(), Status::TooManyRequests);
+
+    // Ip limit
+    assert_eq!(
+        client
+            .get("/api/v1/status")
+            .remote(remote)
+            .dispatch()
+            .status(),
+        Status::Ok
+    );
+    let response = client.get("/api/v1/status").remote(remote).dispatch();
+    assert_eq!(response.status(), Status::TooManyRequests);
+    assert_eq!(response.headers().get_one("Retry-After"), Some("20"));
+
+    // Forwarded addresses are ignored without a configured proxy header
+    let response = client
+        .get("/api/v1/status")
+        .remote(remote)
+        .header(Header::new("X-Real-IP", "10.0.0.1"))
+        .dispatch();
+    assert_eq!(response.status(), Status::TooManyRequests);
+    match response.into_json::<Protocol>() {
+        Some(Protocol::NetworkingError(err)) => assert_eq!(err.code, ErrorCode::RateLimited),
+        other => panic!("Expected a rate limit error, got {:?}", other),
+    }
+
+    // Other clients are not affected
+    let other = "127.0.0.2:8000".parse().unwrap();
+    assert_eq!(
+        client
+            .get("/api/v1/status")
+            .remote(other)
+            .dispatch()
+            .status(),
+        Status::Ok
+    );
+}
@@ -1,29 +1,51 @@
 use chrono::{DateTime, Utc};
-use protocol::protocol::{BattleResponse, GameOpponentInfo, Protocol, Turn};
+use protocol::protocol::{
+    AdminGame, BattleResponse, ErrorCode, GameOpponentInfo, GameState, Protocol, RulesPreset, Turn,
+};
 use uuid::Uuid;
 
 use crate::{
     model::polling::ActivePolls,
     service::{combat_service, game_service, simple_bot_service},
+    Database,
 };
 
 use super::{
     game_instance_player::GameInstancePlayer, COMBAT_DURATION_MULTIPLIER, DEFAULT_COMBAT_DURATION,
 };
 
+#[derive(Debug, Clone, Copy)]
+pub struct GameSettings {
+    pub rated: bool,
+    pub bot_fill: bool,
+    pub rules: RulesPreset,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            rated: true,
+            bot_fill: true,
+            rules: RulesPreset::default(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct GameInstance {
     pub game_id: Uuid,
     pub players: [GameInstancePlayer; 8],
     pub turn: Turn,
+    pub settings: GameSettings,
 }
 
 impl GameInstance {
-    pub fn new(players: [GameInstancePlayer; 8]) -> Self {
+    pub fn new(players: [GameInstancePlayer; 8], settings: GameSettings) -> Self {
         Self {
             game_id: Uuid::new_v4(),
             players,
             turn: Turn::default(),
+            settings,
         }
     }
 
@@ -51,6 +73,20 @@ impl GameInstance {
             .find(|player| player.id == id && player.is_active())
     }
 
+    /// Returns the player of `user_id` if shop actions are currently allowed
+    pub fn get_shop_user_mut(
+        &mut self,
+        user_id: i32,
+    ) -> Result<&mut GameInstancePlayer, ErrorCode> {
+        if !matches!(self.turn, Turn::Shop(..)) {
+            return Err(ErrorCode::WrongPhase);
+        }
+
+        let player = self.get_user_mut(user_id).ok_or(ErrorCode::NotInGame)?;
+        player.record_action();
+        Ok(player)
+    }
+
     pub fn has_user(&self, user_id: i32) -> bool {
         self.players
             .iter()
@@ -58,9 +94,13 @@ impl GameInstance {
     }
 
     // TODO: Move back to service
-    pub async fn next_turn(&mut self) -> bool {
+    pub async fn next_turn(&mut self, db: &Database) -> bool {
+        if matches!(self.turn, Turn::Combat(0, _)) {
+            self.assign_missing_gods().await;
+        }
+
         let (turn_time, ended) = match self.turn {
-            Turn::Combat(turn, _) => self.start_shop(turn + 1).await,
+            Turn::Combat(turn, _) => self.start_shop(db, turn + 1).await,
             Turn::Shop(_, _) => (self.start_combat().await, false),
         };
 
@@ -72,8 +112,23 @@ impl GameInstance {
         false
     }
 
-    async fn start_shop(&mut self, _turn: u16) -> (DateTime<Utc>, bool) {
-        game_service::update_player_placements(self).await;
+    /// Picks a random god for players that did not choose one in time
+    async fn assign_missing_gods(&mut self) {
+        for player in self.players.iter_mut().filter(|p| p.god.is_none()) {
+            player.choose_random_god();
+
+            if let (Some(user_id), Some(god)) = (player.user_id, &player.god) {
+                debug!(
+                    "User {} did not choose a god in time, selected {}",
+                    user_id, god.name
+                );
+                ActivePolls::notify(user_id, Protocol::AvatarSelectResponse(god.clone())).await;
+            }
+        }
+    }
+
+    async fn start_shop(&mut self, db: &Database, _turn: u16) -> (DateTime<Utc>, bool) {
+        game_service::update_player_placements(db, self).await;
 
         if self.is_game_over() {
             return (Utc::now(), true);
@@ -86,12 +141,42 @@ impl GameInstance {
             player.money = (turn + 2).min(16);
         }
 
+        self.update_idle_players().await;
         simple_bot_service::perform_bot_turns(self).await;
 
-        (
-            Utc::now() + chrono::Duration::seconds(90.min(30 + (turn as i64 / 2 - 1) * 5)),
-            false,
-        )
+        let shop_duration = 90.min(30 + (turn as i64 / 2 - 1) * 5);
+        let shop_duration = match self.settings.rules {
+            RulesPreset::Standard => shop_duration,
+            RulesPreset::Fast => shop_duration / 2,
+        };
+
+        (Utc::now() + chrono::Duration::seconds(shop_duration), false)
+    }
+
+    /// Lets bots play for users that stopped acting or polling
+    async fn update_idle_players(&mut self) {
+        let online = ActivePolls::online_users();
+        for player in self.players.iter_mut() {
+            let Some(user_id) = player.user_id else {
+                continue;
+            };
+            let online = online.contains(&user_id);
+
+            if player.should_autoplay(online) {
+                debug!(
+                    "User {} is idle in game {:?}, a bot takes over",
+                    user_id, self.game_id
+                );
+                player.autoplay = true;
+                player.choose_random_god();
+                // Connected users take their slot back by acting again
+                if !online {
+                    game_service::track_offline_player(user_id, self.game_id);
+                }
+                ActivePolls::notify(user_id, Protocol::GameAutoplayResponse).await;
+            }
+            player.idle_phases = player.idle_phases.saturating_add(1);
+        }
     }
 
     async fn start_combat(&mut self) -> DateTime<Utc> {
@@ -129,21 +214,8 @@ impl GameInstance {
     async fn execute_combat(
         mut pairing: (&mut GameInstancePlayer, &mut GameInstancePlayer),
     ) -> usize {
-        let user_b_id = pairing.1.user_id;
-        let player_a_op_info = GameOpponentInfo {
-            name: pairing.0.display_name.clone(),
-            health: pairing.0.health,
-            experience: pairing.0.experience,
-            character_id: pairing.0.god.clone().unwrap().id,
-            is_next_opponent: false,
-        };
-        let player_b_op_info = GameOpponentInfo {
-            name: pairing.1.display_name.clone(),
-            health: pairing.1.health,
-            experience: pairing.1.experience,
-            character_id: pairing.1.god.clone().unwrap().id,
-            is_next_opponent: false,
-        };
+        let player_a_op_info = pairing.0.opponent_info(false);
+        let player_b_op_info = pairing.1.opponent_info(false);
 
         let (actions, start_own, start_opponent) =
             combat_service::calculate_combat(&mut pairing).await;
@@ -159,25 +231,93 @@ impl GameInstance {
 
         let action_len = combat_result.actions.len();
 
-        if pairing.0.placement.is_none() && pairing.0.user_id.is_some() {
-            ActivePolls::notify(
-                pairing.0.user_id.unwrap(),
-                Protocol::GameBattleResponse(combat_result),
-            )
-            .await;
+        pairing.0.last_battle = Some(combat_result.clone());
+        pairing.1.last_battle = Some(swapped_result.clone());
+
+        if let (None, Some(user_id)) = (pairing.0.placement, pairing.0.user_id) {
+            ActivePolls::notify(user_id, Protocol::GameBattleResponse(combat_result)).await;
         }
 
-        if pairing.1.placement.is_none() && user_b_id.is_some() {
-            ActivePolls::notify(
-                user_b_id.unwrap(),
-                Protocol::GameBattleResponse(swapped_result),
-            )
-            .await;
+        if let (None, Some(user_id)) = (pairing.1.placement, pairing.1.user_id) {
+            ActivePolls::notify(user_id, Protocol::GameBattleResponse(swapped_result)).await;
         }
 
         action_len
     }
 
+    /// Returns all players ordered by their standing from the view of `player_id`
+    pub fn get_opponents(&self, player_id: Uuid) -> Vec<GameOpponentInfo> {
+        let pairings =
+            combat_service::get_pairing(self.turn.into(), self.players.iter().collect::<Vec<_>>());
+
+        let next_opponent = pairings
+            .iter()
+            .find(|p| p.0 == player_id || p.1 == player_id)
+            .map(|p| if p.0 == player_id { p.1 } else { p.0 });
+
+        debug!(
+            "Next opponent: {:?} based on pairings: {:?}",
+            next_opponent, pairings
+        );
+
+        let mut players = self.players.iter().filter(|p| !p.empty).collect::<Vec<_>>();
+        players.sort_by(|a, b| {
+            if a.placement.is_some() && b.placement.is_some() {
+                a.placement.cmp(&b.placement)
+            } else if a.placement.is_some() {
+                std::cmp::Ordering::Greater
+            } else if b.placement.is_some() {
+                std::cmp::Ordering::Less
+            } else {
+                b.health.cmp(&a.health)
+            }
+        });
+
+        players
+            .iter()
+            .map(|p| p.opponent_info(Some(p.id) == next_opponent))
+            .collect::<Vec<_>>()
+    }
+
+    /// Snapshot of the game from the view of the user
+    pub fn get_state(&self, user_id: i32) -> Option<GameState> {
+        let player = self.get_user(user_id)?;
+
+        Some(GameState {
+            turn: self.turn,
+            user: player.user_info(),
+            god_choices: player.god_choices,
+            shop_locked: player.shop.locked,
+            shop: player.shop.characters.clone(),
+            board: player.board.to_vec(),
+            opponents: self.get_opponents(player.id),
+            last_battle: player.last_battle.clone(),
+        })
+    }
+
+    /// Ends the current phase on the next scheduler tick
+    pub fn skip_timer(&mut self) {
+        self.turn = match self.turn {
+            Turn::Shop(turn, _) => Turn::Shop(turn, Utc::now()),
+            Turn::Combat(turn, _) => Turn::Combat(turn, Utc::now()),
+        };
+    }
+
+    pub fn admin_info(&self) -> AdminGame {
+        AdminGame {
+            id: self.game_id,
+            turn: self.turn,
+            rated: self.settings.rated,
+            rules: self.settings.rules,
+            players: self
+                .players
+                .iter()
+                .filter(|player| !player.empty)
+                .map(GameInstancePlayer::admin_info)
+                .collect(),
+        }
+    }
+
     pub fn is_game_over(&self) -> bool {
         self.players
             .iter()
@@ -1,9 +1,16 @@
-use super::{shop::Shop, BOARD_SIZE, EXP_PER_LVL, MAX_LVL, START_EXP, START_HEALTH, START_MONEY};
+use super::{
+    shop::Shop, BOARD_SIZE, DEFAULT_RATING, EXP_PER_LVL, MAX_IDLE_PHASES, MAX_LVL,
+    MAX_OFFLINE_IDLE_PHASES, START_EXP, START_HEALTH, START_MONEY,
+};
 use protocol::{
     characters::get_characters,
-    protocol::{CharacterInstance, GameOpponentInfo},
+    gods::get_gods,
+    protocol::{
+        AdminPlayer, BattleResponse, CharacterInstance, ErrorCode, GameOpponentInfo, GameUserInfo,
+    },
     protocol_types::prelude::God,
 };
+use rand::seq::SliceRandom;
 use uuid::Uuid;
 
 #[derive(Debug, Clone)]
@@ -19,6 +26,14 @@ pub struct GameInstancePlayer {
     pub money: u16,
     pub experience: u8,
     pub placement: Option<u8>,
+    pub rating: i32,
+    /// Unused slot in games without bot fill
+    pub empty: bool,
+    pub last_battle: Option<BattleResponse>,
+    /// A bot plays for the user until they act again
+    pub autoplay: bool,
+    /// Shop phases started since the last action of the user
+    pub idle_phases: u8,
 }
 
 impl std::default::Default for GameInstancePlayer {
@@ -35,6 +50,11 @@ impl std::default::Default for GameInstancePlayer {
             money: START_MONEY,
             experience: START_EXP,
             placement: None,
+            rating: DEFAULT_RATING,
+            empty: false,
+            last_battle: None,
+            autoplay: false,
+            idle_phases: 0,
         }
     }
 }
@@ -55,6 +75,23 @@ impl GameInstancePlayer {
         self
     }
 
+    /// Creates an already eliminated player for games that are not filled up with bots
+    pub fn empty_slot(god: God, placement: u8) -> Self {
+        Self {
+            display_name: "[EMPTY]".to_string(),
+            god: Some(god),
+            health: 0,
+            placement: Some(placement),
+            empty: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_rating(mut self, rating: i32) -> Self {
+        self.rating = rating;
+        self
+    }
+
     pub fn generate_shop(&mut self) {
         if self.shop.locked {
             self.shop.fill(self.get_lvl());
@@ -63,10 +100,9 @@ impl GameInstancePlayer {
         }
     }
 
-    pub fn reroll(&mut self) -> Result<(), ()> {
+    pub fn reroll(&mut self) -> Result<(), ErrorCode> {
         if self.money < 1 {
-            // Not enough money
-            return Err(());
+            return Err(ErrorCode::NotEnoughMoney);
         }
 
         self.money -= 1;
@@ -74,21 +110,18 @@ impl GameInstancePlayer {
         Ok(())
     }
 
-    pub fn buy(&mut self, shop_idx: usize, board_idx: usize) -> Result<(), ()> {
+    pub fn buy(&mut self, shop_idx: usize, board_idx: usize) -> Result<(), ErrorCode> {
         let Some(shop_character) = self.shop.characters.get(shop_idx) else {
-            // Invalid shop index
-            return Err(());
+            return Err(ErrorCode::InvalidShopIndex);
         };
 
         let Some(mut shop_character) = shop_character.clone() else {
-            // Character is not in shop
-            return Err(());
+            return Err(ErrorCode::ShopSlotEmpty);
         };
 
         let cost = shop_character.cost as u16;
         if self.money < cost {
-            // Not enough money
-            return Err(());
+            return Err(ErrorCode::NotEnoughMoney);
         }
 
         let mut upgradeable = self.get_upgradeable(shop_character.character_id);
@@ -101,15 +134,13 @@ impl GameInstancePlayer {
         let free_index = self.get_free_board_index();
 
         let Some(board_character) = self.board.get_mut(board_idx) else {
-            // Invalid board index
-            return Err(());
+            return Err(ErrorCode::InvalidBoardIndex);
         };
 
         if let Some(board_character) = board_character {
             // Board is occupied at index
             let Some(free_index) = free_index else {
-                // Board is full
-                return Err(());
+                return Err(ErrorCode::BoardFull);
             };
 
             self.board[free_index] = Some(board_character.clone());
@@ -127,20 +158,17 @@ impl GameInstancePlayer {
         Ok(())
     }
 
-    pub fn sell(&mut self, character_idx: usize) -> Result<(), ()> {
-        if character_idx > self.board.len() {
-            // Invalid board index
-            return Err(());
-        }
+    pub fn sell(&mut self, character_idx: usize) -> Result<(), ErrorCode> {
+        let Some(board_character) = self.board.get_mut(character_idx) else {
+            return Err(ErrorCode::InvalidBoardIndex);
+        };
 
-        if self.board.get(character_idx).unwrap().is_some() {
-            self.money += 1;
-            self.board[character_idx] = None;
-            Ok(())
-        } else {
-            // Board is empty at index
-            Err(())
+        if board_character.take().is_none() {
+            return Err(ErrorCode::BoardSlotEmpty);
         }
+
+        self.money += 1;
+        Ok(())
     }
 
     pub fn get_upgradeable(&self, character_id: i32) -> Vec<CharacterInstance> {
@@ -151,10 +179,13 @@ impl GameInstancePlayer {
             .collect::<Vec<_>>()
     }
 
-    pub fn upgrade(&mut self, characters: Vec<CharacterInstance>) -> Result<CharacterInstance, ()> {
+    pub fn upgrade(
+        &mut self,
+        characters: Vec<CharacterInstance>,
+    ) -> Result<CharacterInstance, ErrorCode> {
         // 3 characters must be provided to upgrade
         if characters.len() != 3 {
-            return Err(());
+            return Err(ErrorCode::Internal);
         }
 
         // All characters must be the same and not be upgraded
@@ -164,7 +195,7 @@ impl GameInstancePlayer {
             .iter()
             .any(|c| c.upgraded || c.character_id != character.character_id)
         {
-            return Err(());
+            return Err(ErrorCode::Internal);
         }
 
         // Calculate bonuses
@@ -193,11 +224,10 @@ impl GameInstancePlayer {
         )
     }
 
-    pub fn move_character(&mut self, from_idx: usize, to_idx: usize) -> Result<(), ()> {
+    pub fn move_character(&mut self, from_idx: usize, to_idx: usize) -> Result<(), ErrorCode> {
         let board_len = self.board.len();
         if from_idx >= board_len || to_idx >= board_len {
-            // Invalid board index
-            return Err(());
+            return Err(ErrorCode::InvalidBoardIndex);
         }
 
         self.board.swap(from_idx, to_idx);
@@ -223,7 +253,179 @@ impl GameInstancePlayer {
         }
     }
 
+    pub fn user_info(&self) -> GameUserInfo {
+        GameUserInfo {
+            name: self.display_name.clone(),
+            experience: self.experience,
+            health: self.health,
+            money: self.money,
+            avatar: self.god.as_ref().map(|g| g.id),
+        }
+    }
+
+    /// Picks a random god of the choices if none was selected yet
+    pub fn choose_random_god(&mut self) {
+        if self.god.is_none() {
+            self.god = self
+                .god_choices
+                .choose(&mut rand::thread_rng())
+                .map(|id| get_gods()[*id as usize].clone());
+        }
+    }
+
+    /// Hands the slot over to the bot logic. Players that did not choose a
+    /// god yet get a random one of their choices.
+    pub fn replace_with_bot(&mut self) {
+        self.user_id = None;
+        self.display_name = format!("[BOT] {}", self.display_name);
+        self.choose_random_god();
+    }
+
+    pub fn is_bot(&self) -> bool {
+        self.user_id.is_none() || self.autoplay
+    }
+
+    /// Called for every action of the user. Gives the slot back if a bot was playing.
+    pub fn record_action(&mut self) {
+        self.idle_phases = 0;
+        self.autoplay = false;
+    }
+
+    /// Whether the user has been idle for long enough to let a bot play
+    pub fn should_autoplay(&self, online: bool) -> bool {
+        let max_idle_phases = if online {
+            MAX_IDLE_PHASES
+        } else {
+            MAX_OFFLINE_IDLE_PHASES
+        };
+
+        self.user_id.is_some()
+            && !self.autoplay
+            && self.is_active()
+            && self.idle_phases >= max_idle_phases
+    }
+
+    pub fn admin_info(&self) -> AdminPlayer {
+        AdminPlayer {
+            id: self.id,
+            user_id: self.user_id,
+            name: self.display_name.clone(),
+            god: self.god.as_ref().map(|god| god.id),
+            god_choices: self.god_choices,
+            health: self.health,
+            money: self.money,
+            experience: self.experience,
+            placement: self.placement,
+            rating: self.rating,
+            shop_locked: self.shop.locked,
+            shop: self.shop.characters.clone(),
+            board: self.board.to_vec(),
+            autoplay: self.autoplay,
+        }
+    }
+
     pub(crate) fn is_active(&self) -> bool {
         self.health > 0 && self.placement.is_none()
     }
 }
+
+#[test]
+fn test_shop_error_codes() {
+    let mut player = GameInstancePlayer {
+        money: 0,
+        ..Default::default()
+    };
+
+    assert_eq!(player.reroll(), Err(ErrorCode::NotEnoughMoney));
+    assert_eq!(player.buy(usize::MAX, 0), Err(ErrorCode::InvalidShopIndex));
+    assert_eq!(player.sell(BOARD_SIZE), Err(ErrorCode::InvalidBoardIndex));
+    assert_eq!(player.sell(0), Err(ErrorCode::BoardSlotEmpty));
+    assert_eq!(
+        player.move_character(0, BOARD_SIZE),
+        Err(ErrorCode::InvalidBoardIndex)
+    );
+}
+
+#[test]
+fn test_random_shop_actions() {
+    use super::SHOP_SIZE;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut player = GameInstancePlayer::default();
+    for _ in 0..10_000 {
+        player.money = rng.gen_range(0..20);
+        let idx = rng.gen_range(0..BOARD_SIZE + 2);
+        let target = rng.gen_range(0..BOARD_SIZE + 2);
+        let _ = match rng.gen_range(0..4) {
+            0 => player.reroll(),
+            1 => player.buy(idx % (SHOP_SIZE + 2), target),
+            2 => player.sell(idx),
+            _ => player.move_character(idx, target),
+        };
+    }
+}
+
+#[test]
+fn test_replace_with_bot() {
+    let choices = std::array::from_fn(|i| get_gods()[i].id);
+    let mut player = GameInstancePlayer::new(Some(1), "Player".to_string(), choices);
+
+    player.replace_with_bot();
+    assert_eq!(player.user_id, None);
+    assert_eq!(player.display_name, "[BOT] Player");
+    assert!(player
+        .god
+        .as_ref()
+        .is_some_and(|god| choices.contains(&god.id)));
+}
+
+#[test]
+fn test_choose_random_god() {
+    let choices = [7, 12, 19, 30];
+    for _ in 0..20 {
+        let mut player = GameInstancePlayer::new(Some(1), "Player".to_string(), choices);
+        player.choose_random_god();
+        assert!(player
+            .god
+            .as_ref()
+            .is_some_and(|god| choices.contains(&god.id)));
+    }
+
+    // A god that was already chosen is kept, even if it is not one of the choices
+    let mut player = GameInstancePlayer::new(Some(1), "Player".to_string(), choices);
+    player.god = Some(get_gods()[0].clone());
+    player.choose_random_god();
+    assert_eq!(
+        player.god.as_ref().map(|god| god.id),
+        Some(get_gods()[0].id)
+    );
+}
+
+#[test]
+fn test_should_autoplay() {
+    let mut player = GameInstancePlayer::new(Some(1), "Player".to_string(), [0; 4]);
+    assert!(!player.should_autoplay(false));
+
+    player.idle_phases = MAX_OFFLINE_IDLE_PHASES;
+    assert!(player.should_autoplay(false));
+    assert!(!player.should_autoplay(true));
+
+    player.idle_phases = MAX_IDLE_PHASES;
+    assert!(player.should_autoplay(true));
+
+    player.autoplay = true;
+    assert!(player.is_bot());
+    assert!(!player.should_autoplay(true));
+
+    player.record_action();
+    assert!(!player.is_bot());
+    assert_eq!(player.idle_phases, 0);
+
+    let bot = GameInstancePlayer::new(None, "Bot".to_string(), [0; 4]);
+    assert!(!GameInstancePlayer {
+        idle_phases: MAX_IDLE_PHASES,
+        ..bot
+    }
+    .should_autoplay(true));
+}
@@ -13,3 +13,11 @@ pub(crate) const DEFAULT_COMBAT_DURATION: i64 = 5;
 pub(crate) const START_HEALTH: i16 = 10;
 pub(crate) const START_EXP: u8 = 5;
 pub(crate) const START_MONEY: u16 = 2;
+
+/// Shop phases without an action before a bot plays for a connected user
+pub(crate) const MAX_IDLE_PHASES: u8 = 3;
+/// Same for users that stopped polling
+pub(crate) const MAX_OFFLINE_IDLE_PHASES: u8 = 1;
+
+pub(crate) const DEFAULT_RATING: i32 = 1000;
+pub(crate) const PLACEMENT_REWARDS: [i32; 8] = [100, 80, 65, 50, 40, 30, 20, 10];
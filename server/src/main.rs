@@ -14,20 +14,30 @@ pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations");
 use std::{collections::HashMap, env, error::Error, sync::Arc};
 
 use dotenv::dotenv;
-use model::routes::get_api;
+use model::{
+    routes::{get_api, get_catchers},
+    sessions::SessionConfig,
+};
 use rocket::{
     fairing::AdHoc,
     figment::{
         map,
         value::{Map, Value},
+        Figment,
     },
     fs::FileServer,
     tokio::{self, sync::Mutex},
-    Build, Rocket,
+    Build, Rocket, Route,
 };
 use rocket_sync_db_pools::database;
+use service::{
+    display_name_service::{self, DisplayNameRules},
+    websocket_service::ApiDispatcher,
+};
 
-use crate::fairings::{cache::CacheFairing, perf_log::PerfLogFairing};
+use crate::fairings::{
+    cache::CacheFairing, perf_log::PerfLogFairing, rate_limit::RateLimitFairing,
+};
 
 mod fairings;
 pub mod game;
@@ -41,8 +51,10 @@ pub mod util;
 #[database("db")]
 pub struct Database(diesel::PgConnection);
 
+type GameMap = Arc<Mutex<HashMap<Uuid, Arc<Mutex<GameInstance>>>>>;
+
 pub struct RunningGames {
-    pub games: Arc<Mutex<HashMap<Uuid, Arc<Mutex<GameInstance>>>>>,
+    pub games: GameMap,
 }
 
 #[rocket::main]
@@ -72,19 +84,28 @@ async fn main() -> Result<(), rocket::Error> {
         .merge(("databases", map!["db" => db]));
 
     let games = Arc::new(Mutex::new(HashMap::new()));
-
-    let r = rocket::custom(figment)
-        .attach(Database::fairing())
+    let rate_limit = RateLimitFairing::from_env();
+
+    // Websocket calls are answered by a local instance of the api. It can not
+    // open websockets itself, so it has no dispatcher.
+    let dispatched_routes = get_api()
+        .into_iter()
+        .filter(|route| route.name.as_deref() != Some("connect"))
+        .collect();
+    let dispatcher = ApiDispatcher::new(
+        api(figment.clone(), &games, dispatched_routes).attach(rate_limit.clone()),
+    )
+    .await?;
+
+    let r = api(figment, &games, get_api())
         .attach(AdHoc::try_on_ignite(
             "Database Migrations",
             run_db_migrations,
         ))
         .attach(CacheFairing)
         .attach(PerfLogFairing)
-        .manage(RunningGames {
-            games: games.clone(),
-        })
-        .mount("/api/v1", get_api())
+        .attach(rate_limit)
+        .manage(dispatcher)
         .mount("/", FileServer::from("./static"))
         .ignite()
         .await?;
@@ -97,13 +118,33 @@ async fn main() -> Result<(), rocket::Error> {
     Ok(())
 }
 
+/// The api with its state, shared by the server and the websocket dispatcher
+fn api(figment: Figment, games: &GameMap, routes: Vec<Route>) -> Rocket<Build> {
+    rocket::custom(figment)
+        .attach(Database::fairing())
+        .manage(RunningGames {
+            games: games.clone(),
+        })
+        .manage(SessionConfig::from_env())
+        .manage(DisplayNameRules::from_env())
+        .mount("/api/v1", routes)
+        .register("/api/v1", get_catchers())
+}
+
 async fn run_db_migrations(rocket: Rocket<Build>) -> Result<Rocket<Build>, Rocket<Build>> {
     let db = Database::get_one(&rocket)
         .await
         .expect("Unable to open database connection for migration");
 
     db.run(|conn| match run_migrations(conn) {
-        Ok(()) => Ok(rocket),
+        Ok(()) => {
+            match display_name_service::normalize_keys(conn) {
+                Ok(0) => {}
+                Ok(count) => info!("Normalized {} display name keys", count),
+                Err(e) => warn!("Failed to normalize display name keys: {:?}", e),
+            }
+            Ok(rocket)
+        }
         Err(e) => {
             error!("Failed to run database migrations: {:?}", e);
             Err(rocket)
@@ -0,0 +1,158 @@
+use super::{guard_failure, negotiated::Negotiated, polling::ActivePolls, users::User};
+use crate::{
+    game::game_instance::GameInstance,
+    service::{chat_service, game_service},
+    RunningGames,
+};
+use protocol::protocol::{ErrorCode, Protocol, Role};
+use rocket::{
+    request::{FromRequest, Outcome},
+    serde::json::Json,
+    tokio::sync::Mutex,
+    Request, State,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// User with the admin role
+pub struct Admin<'r>(pub &'r User);
+
+#[derive(Debug)]
+pub enum AdminError {
+    Unauthorized,
+    Forbidden,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin<'r> {
+    type Error = AdminError;
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.guard::<&User>().await {
+            Outcome::Success(user) if user.role() == Role::Admin => Outcome::Success(Admin(user)),
+            Outcome::Success(user) => {
+                warn!("User {} tried to use the admin api", user.id);
+                guard_failure(req, ErrorCode::Forbidden, AdminError::Forbidden)
+            }
+            Outcome::Error((status, _)) => Outcome::Error((status, AdminError::Unauthorized)),
+            Outcome::Forward(status) => Outcome::Forward(status),
+        }
+    }
+}
+
+async fn get_game(games: &RunningGames, game_id: Uuid) -> Option<Arc<Mutex<GameInstance>>> {
+    games.games.lock().await.get(&game_id).cloned()
+}
+
+async fn running_games(games: &RunningGames) -> Negotiated<Protocol> {
+    let games = games
+        .games
+        .lock()
+        .await
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    let mut infos = Vec::with_capacity(games.len());
+    for game in games {
+        infos.push(game.lock().await.admin_info());
+    }
+
+    Negotiated(Protocol::AdminGamesResponse(infos))
+}
+
+#[get("/admin/games")]
+pub async fn get_running_games(
+    _admin: Admin<'_>,
+    games: &State<RunningGames>,
+) -> Negotiated<Protocol> {
+    running_games(games).await
+}
+
+#[patch("/admin/games/<game_id>/turn")]
+pub async fn advance_game(
+    admin: Admin<'_>,
+    games: &State<RunningGames>,
+    game_id: Uuid,
+) -> Negotiated<Protocol> {
+    let Some(game) = get_game(games, game_id).await else {
+        return Negotiated(ErrorCode::NotFound.into());
+    };
+
+    info!("Admin {} advanced game {}", admin.0.id, game_id);
+    game.lock().await.skip_timer();
+    running_games(games).await
+}
+
+#[delete("/admin/games/<game_id>")]
+pub async fn end_game(
+    admin: Admin<'_>,
+    games: &State<RunningGames>,
+    game_id: Uuid,
+) -> Negotiated<Protocol> {
+    let Some(game) = games.games.lock().await.remove(&game_id) else {
+        return Negotiated(ErrorCode::NotFound.into());
+    };
+
+    info!("Admin {} ended game {}", admin.0.id, game_id);
+    game_service::end_game(&*game.lock().await).await;
+    running_games(games).await
+}
+
+#[get("/admin/games/<game_id>/players/<player_id>")]
+pub async fn get_player(
+    _admin: Admin<'_>,
+    games: &State<RunningGames>,
+    game_id: Uuid,
+    player_id: Uuid,
+) -> Negotiated<Protocol> {
+    let Some(game) = get_game(games, game_id).await else {
+        return Negotiated(ErrorCode::NotFound.into());
+    };
+
+    let game = game.lock().await;
+    match game.players.iter().find(|player| player.id == player_id) {
+        Some(player) => Negotiated(Protocol::AdminPlayerResponse(player.admin_info())),
+        None => Negotiated(ErrorCode::NotFound.into()),
+    }
+}
+
+#[delete("/admin/games/<game_id>/players/<player_id>")]
+pub async fn kick_player(
+    admin: Admin<'_>,
+    games: &State<RunningGames>,
+    game_id: Uuid,
+    player_id: Uuid,
+) -> Negotiated<Protocol> {
+    let Some(game) = get_game(games, game_id).await else {
+        return Negotiated(ErrorCode::NotFound.into());
+    };
+
+    let mut game = game.lock().await;
+    let Some(user_id) = game_service::kick_player(&mut game, player_id).await else {
+        return Negotiated(ErrorCode::NotFound.into());
+    };
+
+    info!(
+        "Admin {} kicked user {} from game {}",
+        admin.0.id, user_id, game_id
+    );
+    match game.get_game_user(player_id) {
+        Some(player) => Negotiated(Protocol::AdminPlayerResponse(player.admin_info())),
+        None => Negotiated(ErrorCode::NotFound.into()),
+    }
+}
+
+#[post("/admin/broadcast", data = "<text>")]
+pub async fn broadcast(admin: Admin<'_>, text: Json<String>) -> Negotiated<Protocol> {
+    let text = match chat_service::validate_message(&text) {
+        Ok(text) => text,
+        Err(code) => return Negotiated(code.into()),
+    };
+
+    let reached = ActivePolls::broadcast(Protocol::ServerMessageResponse(text.clone())).await;
+    info!(
+        "Admin {} broadcast a message to {} users",
+        admin.0.id, reached
+    );
+
+    Negotiated(Protocol::ServerMessageResponse(text))
+}
@@ -0,0 +1,118 @@
+use chrono::Utc;
+use protocol::protocol::{ChatChannel, ChatMessage, ErrorCode, Protocol};
+use rocket::serde::json::Json;
+
+use crate::{service::chat_service, Database};
+
+use super::{
+    game::GameGuard, lobbies::LobbyWithUsers, negotiated::Negotiated, polling::Channel, users::User,
+};
+
+async fn get_history(db: &Database, channel: Channel, user_id: i32) -> Negotiated<Protocol> {
+    match db
+        .run(move |con| chat_service::get_muted_users(con, user_id))
+        .await
+    {
+        Ok(muted) => Negotiated(Protocol::ChatHistoryResponse(chat_service::get_history(
+            &channel, &muted,
+        ))),
+        Err(e) => {
+            warn!("Failed to load mutes of user {}: {:?}", user_id, e);
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
+
+async fn post_message(
+    db: &Database,
+    channel: Channel,
+    recipients: Vec<i32>,
+    message: ChatMessage,
+) -> Negotiated<Protocol> {
+    match chat_service::send_message(db, channel, recipients, message.clone()).await {
+        Ok(()) => Negotiated(Protocol::ChatMessageResponse(message)),
+        Err(e) => {
+            warn!("Failed to send chat message to {:?}: {:?}", channel, e);
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
+
+#[get("/lobbies/chat")]
+pub async fn get_lobby_chat(
+    user: &User,
+    lobby: LobbyWithUsers,
+    db: Database,
+) -> Negotiated<Protocol> {
+    get_history(&db, Channel::Lobby(lobby.lobby.id), user.id).await
+}
+
+#[post("/lobbies/chat", data = "<text>")]
+pub async fn post_lobby_message(
+    user: &User,
+    lobby: LobbyWithUsers,
+    db: Database,
+    text: Json<String>,
+) -> Negotiated<Protocol> {
+    let text = match chat_service::validate_message(&text) {
+        Ok(text) => text,
+        Err(code) => return Negotiated(code.into()),
+    };
+
+    let message = ChatMessage {
+        channel: ChatChannel::Lobby,
+        from_id: user.id,
+        from: user.display_name.clone().unwrap_or_default(),
+        text,
+        sent_at: Utc::now(),
+    };
+    let recipients = lobby.users.iter().map(|u| u.user_id).collect();
+
+    post_message(&db, Channel::Lobby(lobby.lobby.id), recipients, message).await
+}
+
+#[get("/games/chat")]
+pub async fn get_game_chat(user: &User, game: GameGuard, db: Database) -> Negotiated<Protocol> {
+    let game_id = game.0.lock().await.game_id;
+    get_history(&db, Channel::Game(game_id), user.id).await
+}
+
+#[post("/games/chat", data = "<text>")]
+pub async fn post_game_message(
+    user: &User,
+    game: GameGuard,
+    db: Database,
+    text: Json<String>,
+) -> Negotiated<Protocol> {
+    let text = match chat_service::validate_message(&text) {
+        Ok(text) => text,
+        Err(code) => return Negotiated(code.into()),
+    };
+
+    let (game_id, from, recipients) = {
+        let game = game.0.lock().await;
+        let from = game
+            .players
+            .iter()
+            .find(|player| player.user_id == Some(user.id))
+            .map(|player| player.display_name.clone())
+            .unwrap_or_default();
+        let recipients = game
+            .players
+            .iter()
+            .filter(|player| player.is_active())
+            .filter_map(|player| player.user_id)
+            .collect::<Vec<_>>();
+        (game.game_id, from, recipients)
+    };
+
+    let message = ChatMessage {
+        channel: ChatChannel::Game,
+        from_id: user.id,
+        from,
+        text,
+        sent_at: Utc::now(),
+    };
+
+    post_message(&db, Channel::Game(game_id), recipients, message).await
+}
@@ -0,0 +1,94 @@
+use crate::{schema::currency_transactions, Database};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use protocol::protocol;
+use rocket::http::Status;
+use uuid::Uuid;
+
+use super::negotiated::Negotiated;
+use super::users::User;
+
+const TRANSACTION_HISTORY_SIZE: i64 = 100;
+
+#[derive(Identifiable, Queryable, Associations, Clone, Debug)]
+#[diesel(belongs_to(User))]
+pub struct CurrencyTransaction {
+    pub id: i32,
+    pub user_id: i32,
+    pub amount: i32,
+    pub source: String,
+    pub game_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+impl From<CurrencyTransaction> for protocol::CurrencyTransaction {
+    fn from(val: CurrencyTransaction) -> Self {
+        protocol::CurrencyTransaction {
+            amount: val.amount,
+            source: val.source,
+            game: val.game_id,
+            created_at: DateTime::from_utc(val.created_at, Utc),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum TransactionSource {
+    GameReward,
+}
+
+impl TransactionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionSource::GameReward => "game_reward",
+        }
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = currency_transactions)]
+pub struct NewCurrencyTransaction {
+    pub user_id: i32,
+    pub amount: i32,
+    pub source: String,
+    pub game_id: Option<Uuid>,
+}
+
+impl NewCurrencyTransaction {
+    pub fn new(
+        user_id: i32,
+        amount: i32,
+        source: TransactionSource,
+        game_id: Option<Uuid>,
+    ) -> Self {
+        Self {
+            user_id,
+            amount,
+            source: source.as_str().to_string(),
+            game_id,
+        }
+    }
+}
+
+#[get("/users/@me/transactions")]
+pub async fn get_transactions(db: Database, user: &User) -> Negotiated<protocol::Protocol> {
+    let user_id = user.id;
+    match db
+        .run(move |con| {
+            currency_transactions::table
+                .filter(currency_transactions::user_id.eq(user_id))
+                .order(currency_transactions::id.desc())
+                .limit(TRANSACTION_HISTORY_SIZE)
+                .load::<CurrencyTransaction>(con)
+        })
+        .await
+    {
+        Ok(transactions) => Negotiated(protocol::Protocol::TransactionsResponse(
+            transactions.into_iter().map(|t| t.into()).collect(),
+        )),
+        Err(_) => Negotiated(protocol::Error::new_protocol(
+            Status::InternalServerError.code,
+            "Failed to load transactions".to_string(),
+        )),
+    }
+}
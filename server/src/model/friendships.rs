@@ -0,0 +1,199 @@
+use crate::{
+    schema::{friendships, users},
+    service::friend_service,
+    Database,
+};
+use chrono::NaiveDateTime;
+use diesel::{delete, insert_into, pg::Pg, prelude::*, sql_types::Bool, update};
+use protocol::protocol::{ErrorCode, Protocol};
+use rocket::serde::json::Json;
+
+use super::negotiated::Negotiated;
+use super::users::User;
+
+#[derive(Identifiable, Queryable, Clone, Debug)]
+pub struct Friendship {
+    pub id: i32,
+    /// User that sent the request
+    pub user_id: i32,
+    pub friend_id: i32,
+    pub accepted: bool,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = friendships)]
+pub struct NewFriendship {
+    user_id: i32,
+    friend_id: i32,
+}
+
+/// Both directions of the friendship between the two users
+fn between(
+    user_id: i32,
+    friend_id: i32,
+) -> Box<dyn BoxableExpression<friendships::table, Pg, SqlType = Bool>> {
+    Box::new(
+        (friendships::user_id
+            .eq(user_id)
+            .and(friendships::friend_id.eq(friend_id)))
+        .or(friendships::user_id
+            .eq(friend_id)
+            .and(friendships::friend_id.eq(user_id))),
+    )
+}
+
+/// Answers with the friends list of the user and pushes the change to the other side
+async fn friends_changed(db: &Database, user_id: i32, friend_id: i32) -> Negotiated<Protocol> {
+    friend_service::notify_friends_changed(db, friend_id).await;
+
+    match db
+        .run(move |con| friend_service::load_friends(con, user_id))
+        .await
+    {
+        Ok(friends) => Negotiated(Protocol::FriendsResponse(friends)),
+        Err(e) => {
+            warn!("Failed to load friends of user {}: {:?}", user_id, e);
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
+
+#[get("/friends")]
+pub async fn get_friends(user: &User, db: Database) -> Negotiated<Protocol> {
+    let user_id = user.id;
+    match db
+        .run(move |con| friend_service::load_friends(con, user_id))
+        .await
+    {
+        Ok(friends) => Negotiated(Protocol::FriendsResponse(friends)),
+        Err(e) => {
+            warn!("Failed to load friends of user {}: {:?}", user_id, e);
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
+
+#[put("/friends", data = "<display_name>")]
+pub async fn add_friend(
+    user: &User,
+    db: Database,
+    display_name: Json<String>,
+) -> Negotiated<Protocol> {
+    if user.display_name.is_none() {
+        return Negotiated(ErrorCode::DisplayNameRequired.into());
+    }
+
+    let display_name = display_name.into_inner();
+    let friend_id = match db
+        .run(move |con| {
+            users::table
+                .filter(users::display_name.eq(display_name))
+                .select(users::id)
+                .first::<i32>(con)
+                .optional()
+        })
+        .await
+    {
+        Ok(Some(friend_id)) => friend_id,
+        Ok(None) => return Negotiated(ErrorCode::NotFound.into()),
+        Err(e) => {
+            warn!("Failed to find friend: {:?}", e);
+            return Negotiated(ErrorCode::Internal.into());
+        }
+    };
+    if friend_id == user.id {
+        return Negotiated(ErrorCode::CannotFriendSelf.into());
+    }
+
+    let user_id = user.id;
+    let result = db
+        .run(move |con| {
+            con.transaction(|con| {
+                let existing = friendships::table
+                    .filter(between(user_id, friend_id))
+                    .first::<Friendship>(con)
+                    .optional()?;
+
+                match existing {
+                    // Asking back accepts the open request
+                    Some(friendship) if friendship.user_id == friend_id && !friendship.accepted => {
+                        update(&friendship)
+                            .set(friendships::accepted.eq(true))
+                            .execute(con)?;
+                    }
+                    Some(_) => {}
+                    None => {
+                        // A concurrent request from the other side already created the pair
+                        insert_into(friendships::table)
+                            .values(NewFriendship { user_id, friend_id })
+                            .on_conflict_do_nothing()
+                            .execute(con)?;
+                    }
+                }
+
+                QueryResult::Ok(())
+            })
+        })
+        .await;
+
+    if let Err(e) = result {
+        warn!(
+            "Failed to add friend {} for {}: {:?}",
+            friend_id, user_id, e
+        );
+        return Negotiated(ErrorCode::Internal.into());
+    }
+
+    friends_changed(&db, user_id, friend_id).await
+}
+
+#[put("/friends/<friend_id>")]
+pub async fn accept_friend(user: &User, db: Database, friend_id: i32) -> Negotiated<Protocol> {
+    let user_id = user.id;
+    match db
+        .run(move |con| {
+            update(friendships::table)
+                .filter(friendships::user_id.eq(friend_id))
+                .filter(friendships::friend_id.eq(user_id))
+                .filter(friendships::accepted.eq(false))
+                .set(friendships::accepted.eq(true))
+                .execute(con)
+        })
+        .await
+    {
+        Ok(0) => Negotiated(ErrorCode::NotFound.into()),
+        Ok(_) => friends_changed(&db, user_id, friend_id).await,
+        Err(e) => {
+            warn!(
+                "Failed to accept friend {} for {}: {:?}",
+                friend_id, user_id, e
+            );
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
+
+#[delete("/friends/<friend_id>")]
+pub async fn remove_friend(user: &User, db: Database, friend_id: i32) -> Negotiated<Protocol> {
+    let user_id = user.id;
+    match db
+        .run(move |con| {
+            delete(friendships::table)
+                .filter(between(user_id, friend_id))
+                .execute(con)
+        })
+        .await
+    {
+        Ok(0) => Negotiated(ErrorCode::NotFound.into()),
+        Ok(_) => friends_changed(&db, user_id, friend_id).await,
+        Err(e) => {
+            warn!(
+                "Failed to remove friend {} for {}: {:?}",
+                friend_id, user_id, e
+            );
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
@@ -1,7 +1,8 @@
-use super::users::User;
+use super::{guard_failure, negotiated::Negotiated, users::User};
 use crate::{game::game_instance::GameInstance, schema::games, RunningGames};
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
+use protocol::protocol::{ErrorCode, Protocol};
 use rocket::{
     http::Status,
     request::{FromRequest, Outcome},
@@ -51,6 +52,7 @@ pub struct GameUpdate {
 #[derive(Debug)]
 pub enum GameError {
     Internal,
+    NotInGame,
 }
 
 pub struct GameGuard(pub Arc<Mutex<GameInstance>>);
@@ -63,7 +65,9 @@ impl<'r> FromRequest<'r> for GameGuard {
         if let Outcome::Success(user) = req.guard::<&User>().await {
             let user = user.clone();
             debug!("Getting user took {:?}", start.elapsed());
-            let games = req.guard::<&State<RunningGames>>().await.unwrap();
+            let Some(games) = req.guard::<&State<RunningGames>>().await.succeeded() else {
+                return guard_failure(req, ErrorCode::Internal, GameError::Internal);
+            };
             debug!("Getting running games took {:?}", start.elapsed());
             let games = games.games.lock().await;
             debug!("Getting games lock took {:?}", start.elapsed());
@@ -74,8 +78,17 @@ impl<'r> FromRequest<'r> for GameGuard {
                 }
             }
 
-            return Outcome::Failure((Status::ServiceUnavailable, GameError::Internal));
+            return guard_failure(req, ErrorCode::NotInGame, GameError::NotInGame);
         }
-        Outcome::Failure((Status::Unauthorized, GameError::Internal))
+        Outcome::Error((Status::Unauthorized, GameError::Internal))
+    }
+}
+
+#[get("/games/state")]
+pub async fn get_game_state(game: GameGuard, user: &User) -> Negotiated<Protocol> {
+    let game = game.0.lock().await;
+    match game.get_state(user.id) {
+        Some(state) => Negotiated(Protocol::GameStateResponse(state)),
+        None => Negotiated(ErrorCode::NotInGame.into()),
     }
 }
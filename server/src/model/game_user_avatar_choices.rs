@@ -1,16 +1,17 @@
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use protocol::protocol::Protocol;
+use protocol::protocol::{ErrorCode, Protocol};
 
 use crate::{
     model::{game_users::GameUser, users::User},
     schema::game_user_avatar_choices,
     service::game_service::notify_users,
+    Database,
 };
 use protocol::gods::get_gods;
-use rocket::{http::Status, serde::json::Json};
 
 use super::game::{Game, GameGuard};
+use super::negotiated::Negotiated;
 
 #[derive(Identifiable, Queryable, Associations, Clone, Debug)]
 #[diesel(belongs_to(Game))]
@@ -48,14 +49,16 @@ pub enum GameUserAvatarChoiceError {
 }
 
 #[put("/games/avatar/<avatar_id>")]
-pub async fn select_avatar(game: GameGuard, user: &User, avatar_id: i32) -> Json<Protocol> {
+pub async fn select_avatar(
+    db: Database,
+    game: GameGuard,
+    user: &User,
+    avatar_id: i32,
+) -> Negotiated<Protocol> {
     let mut game = game.0.lock().await;
     if let Some(game_user) = game.get_user_mut(user.id) {
         if game_user.god.is_some() {
-            return Json(protocol::protocol::Error::new_protocol(
-                Status::BadRequest.code,
-                "Avatar already chosen".to_string(),
-            ));
+            return Negotiated(ErrorCode::AvatarAlreadyChosen.into());
         }
 
         if game_user.god_choices.contains(&avatar_id) {
@@ -63,25 +66,19 @@ pub async fn select_avatar(game: GameGuard, user: &User, avatar_id: i32) -> Json
             game_user.god = Some(god.clone());
 
             if !game.players.iter().any(|p| p.god.is_none()) {
-                game.next_turn().await;
+                game.next_turn(&db).await;
             }
 
             notify_users(&game).await;
-            Json(Protocol::AvatarSelectResponse(god))
+            Negotiated(Protocol::AvatarSelectResponse(god))
         } else {
             debug!(
                 "Requested avatar {} not available. Possible choices: {:?}",
                 avatar_id, game_user.god_choices
             );
-            Json(protocol::protocol::Error::new_protocol(
-                Status::Conflict.code,
-                "Avatar not available".to_string(),
-            ))
+            Negotiated(ErrorCode::AvatarNotAvailable.into())
         }
     } else {
-        Json(protocol::protocol::Error::new_protocol(
-            Status::BadRequest.code,
-            "User not in game".to_string(),
-        ))
+        Negotiated(ErrorCode::NotInGame.into())
     }
 }
@@ -1,15 +1,14 @@
 use std::time::SystemTime;
 
 use super::game::GameGuard;
+use super::negotiated::Negotiated;
 use crate::fairings::perf_log::StartTime;
 use crate::model::game_users::GameUser;
 use crate::model::users::User;
 use crate::schema::game_user_characters;
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use protocol::protocol::{Error, GameUserInfo, Protocol};
-use rocket::http::Status;
-use rocket::serde::json::Json;
+use protocol::protocol::{Error, ErrorCode, Protocol};
 
 #[derive(Identifiable, Associations, Queryable, Clone, Debug)]
 #[diesel(belongs_to(GameUser))]
@@ -96,16 +95,12 @@ pub enum GameUserCharacterError {
 }
 
 #[get("/games/characters")]
-pub async fn get_board(game: GameGuard, user: &User) -> Json<Protocol> {
+pub async fn get_board(game: GameGuard, user: &User) -> Negotiated<Protocol> {
     let game = game.0.lock().await;
     if let Some(game_user) = game.get_user(user.id) {
-        Json(Protocol::BoardResponse(game_user.board.to_vec()))
+        Negotiated(Protocol::BoardResponse(game_user.board.to_vec()))
     } else {
-        Json(Error::new_protocol_response(
-            Status::NotFound.code,
-            "Character not found".to_string(),
-            Protocol::CharacterMoveRequest,
-        ))
+        Negotiated(ErrorCode::NotInGame.into())
     }
 }
 
@@ -115,55 +110,40 @@ pub async fn move_character(
     user: &User,
     character_idx: usize,
     target_idx: usize,
-) -> Json<Protocol> {
+) -> Negotiated<Protocol> {
     let mut game = game.0.lock().await;
-    if let Some(game_user) = game.get_user_mut(user.id) {
-        if game_user.move_character(character_idx, target_idx).is_ok() {
-            let board = game_user.board.to_vec();
-            Json(Protocol::BoardResponse(board))
-        } else {
-            Json(Error::new_protocol_response(
-                Status::NotFound.code,
-                "Character not found".to_string(),
-                Protocol::CharacterMoveRequest,
-            ))
-        }
-    } else {
-        Json(Error::new_protocol_response(
-            Status::NotFound.code,
-            "Character not found".to_string(),
-            Protocol::CharacterMoveRequest,
-        ))
+    let game_user = game.get_shop_user_mut(user.id).and_then(|game_user| {
+        game_user.move_character(character_idx, target_idx)?;
+        Ok(game_user)
+    });
+
+    match game_user {
+        Ok(game_user) => Negotiated(Protocol::BoardResponse(game_user.board.to_vec())),
+        Err(e) => Negotiated(Protocol::NetworkingError(
+            Error::from(e).with_reference(Protocol::CharacterMoveRequest),
+        )),
     }
 }
 
 #[delete("/games/characters/<character_idx>")]
-pub async fn sell_character(user: &User, game: GameGuard, character_idx: usize) -> Json<Protocol> {
+pub async fn sell_character(
+    user: &User,
+    game: GameGuard,
+    character_idx: usize,
+) -> Negotiated<Protocol> {
     let mut game = game.0.lock().await;
-    let Some(game_user) = game.get_user_mut(user.id) else {
-        return Json(Error::new_protocol_response(
-            Status::NotFound.code,
-            "Character not found".to_string(),
-            Protocol::CharacterMoveRequest,
-        ))
-    };
+    let game_user = game.get_shop_user_mut(user.id).and_then(|game_user| {
+        game_user.sell(character_idx)?;
+        Ok(game_user)
+    });
 
-    if game_user.sell(character_idx).is_ok() {
-        Json(Protocol::SellResponse(
-            GameUserInfo {
-                experience: game_user.experience,
-                health: game_user.health,
-                money: game_user.money,
-                name: user.username.clone(),
-                avatar: game_user.god.clone().map(|g| g.id),
-            },
+    match game_user {
+        Ok(game_user) => Negotiated(Protocol::SellResponse(
+            game_user.user_info(),
             game_user.board.to_vec(),
-        ))
-    } else {
-        Json(Error::new_protocol_response(
-            Status::NotFound.code,
-            "Character not found".to_string(),
-            Protocol::CharacterMoveRequest,
-        ))
+        )),
+        Err(e) => Negotiated(Protocol::NetworkingError(
+            Error::from(e).with_reference(Protocol::CharacterMoveRequest),
+        )),
     }
 }
@@ -1,13 +1,12 @@
 use super::game::GameGuard;
+use super::negotiated::Negotiated;
 use crate::{
     model::{game::Game, users::User},
     schema::game_users,
-    service::combat_service,
 };
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use protocol::protocol::{GameUserInfo, Protocol};
-use rocket::serde::json::Json;
+use protocol::protocol::{ErrorCode, GameUserInfo, Protocol};
 
 #[derive(Identifiable, Associations, Queryable, Clone, Default, PartialEq, Debug)]
 #[diesel(belongs_to(Game))]
@@ -70,22 +69,14 @@ pub struct GameUserUpdate {
 #[derive(Debug)]
 pub struct GameUsers(pub Vec<GameUser>);
 
-#[derive(Debug)]
-pub enum GameUserError {
-    Internal,
-}
-
 #[get("/games/users/me")]
-pub async fn get_own_user(user: &User, game: GameGuard) -> Json<Protocol> {
-    let mut game = game.0.lock().await;
-    let game_user = game
-        .players
-        .iter_mut()
-        .find(|p| p.user_id == Some(user.id))
-        .ok_or(GameUserError::Internal)
-        .unwrap();
+pub async fn get_own_user(user: &User, game: GameGuard) -> Negotiated<Protocol> {
+    let game = game.0.lock().await;
+    let Some(game_user) = game.players.iter().find(|p| p.user_id == Some(user.id)) else {
+        return Negotiated(ErrorCode::NotInGame.into());
+    };
 
-    Json(Protocol::GameUserInfoResponse(GameUserInfo {
+    Negotiated(Protocol::GameUserInfoResponse(GameUserInfo {
         experience: game_user.experience,
         health: game_user.health,
         money: game_user.money,
@@ -95,41 +86,11 @@ pub async fn get_own_user(user: &User, game: GameGuard) -> Json<Protocol> {
 }
 
 #[get("/games/users")]
-pub async fn get_users(game: GameGuard, user: &User) -> Json<Protocol> {
+pub async fn get_users(game: GameGuard, user: &User) -> Negotiated<Protocol> {
     let game = game.0.lock().await;
-    let id = game.get_user(user.id).unwrap().id;
-
-    let pairings =
-        combat_service::get_pairing(game.turn.into(), game.players.iter().collect::<Vec<_>>());
-
-    let mut players = game.players.to_vec();
-    players.sort_by(|a, b| {
-        if a.placement.is_some() && b.placement.is_some() {
-            a.placement.cmp(&b.placement)
-        } else if a.placement.is_some() {
-            std::cmp::Ordering::Greater
-        } else if b.placement.is_some() {
-            std::cmp::Ordering::Less
-        } else {
-            b.health.cmp(&a.health)
-        }
-    });
-
-    let next_opponent = pairings
-        .iter()
-        .find(|p| p.0 == id || p.1 == id)
-        .map(|p| if p.0 == id { p.1 } else { p.0 })
-        .unwrap();
-
-    debug!(
-        "Next opponent: {} based on pairings: {:?}",
-        next_opponent, pairings
-    );
+    let Some(id) = game.get_user(user.id).map(|game_user| game_user.id) else {
+        return Negotiated(ErrorCode::NotInGame.into());
+    };
 
-    Json(Protocol::GameUsersResponse(
-        players
-            .iter()
-            .map(|u| u.opponent_info(u.id == next_opponent))
-            .collect::<Vec<_>>(),
-    ))
+    Negotiated(Protocol::GameUsersResponse(game.get_opponents(id)))
 }
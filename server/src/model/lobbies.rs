@@ -1,18 +1,23 @@
 use super::lobby_users::LobbyUser;
+use super::negotiated::Negotiated;
 use crate::diesel::{BelongingToDsl, ExpressionMethods, RunQueryDsl};
+use crate::game::game_instance::GameSettings;
 use crate::model::users::User;
 use crate::schema::{lobbies, lobby_users};
-use crate::service::lobby_service;
-use crate::Database;
+use crate::service::matchmaking_service::MatchmakingQueue;
+use crate::service::{game_service, lobby_service};
+use crate::{Database, RunningGames};
 
 use chrono::{DateTime, NaiveDateTime, Utc};
 use diesel::{prelude::*, QueryDsl};
-use protocol::protocol::{Error, LobbyInfo, LobbyJoinRequest, Protocol};
+use protocol::protocol::{
+    Error, ErrorCode, LobbyInfo, LobbyJoinRequest, LobbyListEntry, LobbySettings, Protocol,
+};
 use rocket::{
     http::Status,
     request::{self, FromRequest, Outcome},
     serde::json::Json,
-    Request,
+    Request, State,
 };
 
 #[derive(Identifiable, Associations, Queryable, Clone, Debug)]
@@ -26,9 +31,39 @@ pub struct Lobby {
     pub start_at: Option<NaiveDateTime>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub max_players: i32,
+    pub bot_fill: bool,
+    pub rules: String,
+    pub rated: bool,
 }
 
 impl Lobby {
+    pub fn has_passphrase(&self) -> bool {
+        !self.passphrase.as_deref().unwrap_or_default().is_empty()
+    }
+
+    /// Lobbies with a passphrase are private and do not affect the players rating
+    pub fn is_rated(&self) -> bool {
+        self.rated && !self.has_passphrase()
+    }
+
+    pub fn settings(&self) -> LobbySettings {
+        LobbySettings {
+            max_players: self.max_players as u8,
+            bot_fill: self.bot_fill,
+            rules: self.rules.parse().unwrap_or_default(),
+            rated: self.rated,
+        }
+    }
+
+    pub fn game_settings(&self) -> GameSettings {
+        GameSettings {
+            rated: self.is_rated(),
+            bot_fill: self.bot_fill,
+            rules: self.rules.parse().unwrap_or_default(),
+        }
+    }
+
     pub fn into_lobby_info(&self, users: &[LobbyUser]) -> LobbyInfo {
         LobbyInfo {
             name: self.name.clone(),
@@ -39,6 +74,7 @@ impl Lobby {
                 .unwrap_or_default(),
             users: users.iter().map(|user| user.clone().into()).collect(),
             start_at: self.start_at.map(|start| DateTime::from_utc(start, Utc)),
+            settings: self.settings(),
         }
     }
 }
@@ -75,6 +111,9 @@ impl NewLobby {
 #[derive(Debug)]
 pub enum LobbyError {
     Full,
+    Banned,
+    NotFound,
+    Conflict,
     Internal,
 }
 
@@ -102,20 +141,20 @@ impl<'r> FromRequest<'r> for LobbyWithUsers {
                                 .unwrap_or_default();
                             Outcome::Success(LobbyWithUsers { lobby, users })
                         } else {
-                            Outcome::Forward(())
+                            Outcome::Forward(Status::NotFound)
                         }
                     })
                     .await;
             }
-            return Outcome::Failure((Status::ServiceUnavailable, LobbyError::Internal));
+            return Outcome::Error((Status::ServiceUnavailable, LobbyError::Internal));
         }
-        Outcome::Failure((Status::Unauthorized, LobbyError::Internal))
+        Outcome::Error((Status::Unauthorized, LobbyError::Internal))
     }
 }
 
 #[get("/lobbies")]
-pub async fn get_current_loby_info(lobby: LobbyWithUsers) -> Json<Protocol> {
-    Json(Protocol::LobbyStatusResponse(lobby.into()))
+pub async fn get_current_loby_info(lobby: LobbyWithUsers) -> Negotiated<Protocol> {
+    Negotiated(Protocol::LobbyStatusResponse(lobby.into()))
 }
 
 #[patch("/lobbies/ready")]
@@ -157,19 +196,45 @@ pub async fn join_lobby(
     lobby: Json<LobbyJoinRequest>,
     user: &User,
     db: Database,
-) -> (Status, Option<Json<Protocol>>) {
+    games: &State<RunningGames>,
+) -> (Status, Option<Negotiated<Protocol>>) {
+    if game_service::get_user_game(games, user.id).await.is_some() {
+        return (
+            Status::Conflict,
+            Some(Negotiated(ErrorCode::AlreadyInGame.into())),
+        );
+    }
+
+    if user.display_name.is_none() {
+        return (
+            Status::Conflict,
+            Some(Negotiated(ErrorCode::DisplayNameRequired.into())),
+        );
+    }
+
+    if MatchmakingQueue::leave(user.id) {
+        MatchmakingQueue::notify_users().await;
+    }
+
     match lobby_service::join_lobby(&db, lobby.into_inner(), user).await {
         Ok(_) => (Status::Ok, None),
         Err(LobbyError::Full) => (
             Status::Conflict,
-            Some(Json(Error::new_protocol(
+            Some(Negotiated(Error::new_protocol(
                 Status::Conflict.code,
                 "Lobby is full".to_string(),
             ))),
         ),
+        Err(LobbyError::Banned) => (
+            Status::Forbidden,
+            Some(Negotiated(Error::new_protocol(
+                Status::Forbidden.code,
+                "You have been kicked from this lobby".to_string(),
+            ))),
+        ),
         Err(_) => (
             Status::InternalServerError,
-            Some(Json(Error::new_protocol(
+            Some(Negotiated(Error::new_protocol(
                 Status::InternalServerError.code,
                 "Failed to join lobby".to_string(),
             ))),
@@ -178,8 +243,153 @@ pub async fn join_lobby(
 }
 
 #[delete("/lobbies")]
-pub async fn leave_lobby(user: &User, db: Database) -> Json<Protocol> {
+pub async fn leave_lobby(user: &User, db: Database) -> Negotiated<Protocol> {
     lobby_service::remove_user_from_lobbies(&db, user).await;
 
-    Json(Protocol::LobbyLeaveResponse)
+    Negotiated(Protocol::LobbyLeaveResponse)
+}
+
+#[get("/lobbies/public")]
+pub async fn list_lobbies(db: Database) -> Negotiated<Protocol> {
+    match lobby_service::list_lobbies(&db).await {
+        Ok(lobbies) => Negotiated(Protocol::LobbyListResponse(
+            lobbies
+                .into_iter()
+                .map(|(lobby, players)| LobbyListEntry {
+                    name: lobby.name.clone(),
+                    players: players as u8,
+                    settings: lobby.settings(),
+                    has_passphrase: lobby.has_passphrase(),
+                })
+                .collect(),
+        )),
+        Err(_) => Negotiated(Error::new_protocol(
+            Status::InternalServerError.code,
+            "Failed to load lobbies".to_string(),
+        )),
+    }
+}
+
+#[patch("/lobbies/settings", data = "<settings>")]
+pub async fn update_lobby_settings(
+    settings: Json<LobbySettings>,
+    user: &User,
+    lobby: LobbyWithUsers,
+    db: Database,
+) -> (Status, Option<Negotiated<Protocol>>) {
+    if user.id != lobby.lobby.master_id {
+        return (Status::Unauthorized, None);
+    }
+
+    if settings.max_players < 1
+        || settings.max_players > 8
+        || (settings.max_players as usize) < lobby.users.len()
+    {
+        return (
+            Status::BadRequest,
+            Some(Negotiated(Error::new_protocol(
+                Status::BadRequest.code,
+                "Invalid number of players".to_string(),
+            ))),
+        );
+    }
+
+    match lobby_service::update_settings(&db, lobby.lobby.id, settings.into_inner()).await {
+        Ok(_) => (Status::Ok, None),
+        Err(_) => (
+            Status::InternalServerError,
+            Some(Negotiated(Error::new_protocol(
+                Status::InternalServerError.code,
+                "Failed to update lobby settings".to_string(),
+            ))),
+        ),
+    }
+}
+
+#[delete("/lobbies/users/<lobby_user_id>")]
+pub async fn kick_user(
+    lobby_user_id: i32,
+    user: &User,
+    lobby: LobbyWithUsers,
+    db: Database,
+) -> (Status, Option<Negotiated<Protocol>>) {
+    if user.id != lobby.lobby.master_id {
+        return (
+            Status::Unauthorized,
+            Some(Negotiated(Error::new_protocol(
+                Status::Unauthorized.code,
+                "Only the lobby master can kick users".to_string(),
+            ))),
+        );
+    }
+
+    let Some(target) = lobby.users.iter().find(|u| u.id == lobby_user_id) else {
+        return (
+            Status::NotFound,
+            Some(Negotiated(Error::new_protocol(
+                Status::NotFound.code,
+                "User is not in the lobby".to_string(),
+            ))),
+        );
+    };
+
+    if target.user_id == user.id {
+        return (
+            Status::BadRequest,
+            Some(Negotiated(Error::new_protocol(
+                Status::BadRequest.code,
+                "You can not kick yourself".to_string(),
+            ))),
+        );
+    }
+
+    match lobby_service::kick_user(&db, &lobby.lobby, target).await {
+        Ok(_) => (Status::Ok, None),
+        Err(_) => (
+            Status::InternalServerError,
+            Some(Negotiated(Error::new_protocol(
+                Status::InternalServerError.code,
+                "Failed to kick user".to_string(),
+            ))),
+        ),
+    }
+}
+
+#[patch("/lobbies/users/<lobby_user_id>/promote")]
+pub async fn promote_user(
+    lobby_user_id: i32,
+    user: &User,
+    lobby: LobbyWithUsers,
+    db: Database,
+) -> (Status, Option<Negotiated<Protocol>>) {
+    if user.id != lobby.lobby.master_id {
+        return (
+            Status::Unauthorized,
+            Some(Negotiated(Error::new_protocol(
+                Status::Unauthorized.code,
+                "Only the lobby master can promote users".to_string(),
+            ))),
+        );
+    }
+
+    let Some(target) = lobby.users.iter().find(|u| u.id == lobby_user_id) else {
+        return (
+            Status::NotFound,
+            Some(Negotiated(Error::new_protocol(
+                Status::NotFound.code,
+                "User is not in the lobby".to_string(),
+            ))),
+        );
+    };
+
+    match lobby_service::set_master(&db, lobby.lobby.id, target.user_id).await {
+        Ok(_) => (Status::Ok, None),
+        Err(_) => (
+            Status::InternalServerError,
+            Some(Negotiated(Error::new_protocol(
+                Status::InternalServerError.code,
+                "Failed to promote user".to_string(),
+            ))),
+        ),
+    }
 }
@@ -0,0 +1,34 @@
+use crate::schema::lobby_bans;
+use chrono::NaiveDateTime;
+
+use super::{lobbies::Lobby, users::User};
+
+#[derive(Identifiable, Queryable, Associations, Clone, Debug)]
+#[diesel(belongs_to(Lobby))]
+#[diesel(belongs_to(User))]
+pub struct LobbyBan {
+    pub id: i32,
+    pub lobby_id: i32,
+    pub user_id: i32,
+    pub expires_at: NaiveDateTime,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = lobby_bans)]
+pub struct NewLobbyBan {
+    lobby_id: i32,
+    user_id: i32,
+    expires_at: NaiveDateTime,
+}
+
+impl NewLobbyBan {
+    pub fn new(lobby_id: i32, user_id: i32, expires_at: NaiveDateTime) -> Self {
+        Self {
+            lobby_id,
+            user_id,
+            expires_at,
+        }
+    }
+}
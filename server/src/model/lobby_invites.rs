@@ -0,0 +1,172 @@
+use crate::{
+    schema::{lobbies, lobby_invites, users},
+    service::{lobby_service, matchmaking_service::MatchmakingQueue},
+    Database,
+};
+use chrono::NaiveDateTime;
+use diesel::{delete, prelude::*};
+use protocol::protocol::{Error, LobbyInvite as LobbyInviteInfo, Protocol};
+use rocket::{http::Status, serde::json::Json};
+
+use super::{
+    lobbies::{Lobby, LobbyError, LobbyWithUsers},
+    negotiated::Negotiated,
+    users::User,
+};
+
+#[derive(Identifiable, Queryable, Associations, Clone, Debug)]
+#[diesel(belongs_to(Lobby))]
+#[diesel(belongs_to(User))]
+pub struct LobbyInvite {
+    pub id: i32,
+    pub lobby_id: i32,
+    pub user_id: i32,
+    pub inviter_id: i32,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = lobby_invites)]
+pub struct NewLobbyInvite {
+    lobby_id: i32,
+    user_id: i32,
+    inviter_id: i32,
+}
+
+impl NewLobbyInvite {
+    pub fn new(lobby_id: i32, user_id: i32, inviter_id: i32) -> Self {
+        Self {
+            lobby_id,
+            user_id,
+            inviter_id,
+        }
+    }
+}
+
+#[put("/lobbies/invites", data = "<display_name>")]
+pub async fn invite_user(
+    display_name: Json<String>,
+    user: &User,
+    lobby: LobbyWithUsers,
+    db: Database,
+) -> (Status, Option<Negotiated<Protocol>>) {
+    if user.id != lobby.lobby.master_id {
+        return (Status::Unauthorized, None);
+    }
+
+    match lobby_service::invite_user(&db, &lobby, user, display_name.into_inner()).await {
+        Ok(_) => (Status::Ok, None),
+        Err(LobbyError::NotFound) => (
+            Status::NotFound,
+            Some(Negotiated(Error::new_protocol(
+                Status::NotFound.code,
+                "User not found".to_string(),
+            ))),
+        ),
+        Err(LobbyError::Conflict) => (
+            Status::Conflict,
+            Some(Negotiated(Error::new_protocol(
+                Status::Conflict.code,
+                "User already in lobby".to_string(),
+            ))),
+        ),
+        Err(_) => (
+            Status::InternalServerError,
+            Some(Negotiated(Error::new_protocol(
+                Status::InternalServerError.code,
+                "Failed to invite user".to_string(),
+            ))),
+        ),
+    }
+}
+
+#[get("/lobbies/invites")]
+pub async fn get_invites(user: &User, db: Database) -> Negotiated<Protocol> {
+    let user_id = user.id;
+    match db
+        .run(move |con| {
+            lobby_invites::table
+                .inner_join(lobbies::table)
+                .inner_join(users::table.on(users::id.eq(lobby_invites::inviter_id)))
+                .filter(lobby_invites::user_id.eq(user_id))
+                .select((
+                    lobby_invites::id,
+                    lobbies::name,
+                    users::display_name,
+                    users::username,
+                ))
+                .load::<(i32, String, Option<String>, String)>(con)
+        })
+        .await
+    {
+        Ok(invites) => Negotiated(Protocol::LobbyInvitesResponse(
+            invites
+                .into_iter()
+                .map(|(id, lobby, display_name, username)| LobbyInviteInfo {
+                    id,
+                    lobby,
+                    from: display_name.unwrap_or(username),
+                })
+                .collect(),
+        )),
+        Err(_) => Negotiated(Error::new_protocol(
+            Status::InternalServerError.code,
+            "Failed to load invites".to_string(),
+        )),
+    }
+}
+
+#[put("/lobbies/invites/<invite_id>")]
+pub async fn accept_invite(
+    invite_id: i32,
+    user: &User,
+    db: Database,
+) -> (Status, Option<Negotiated<Protocol>>) {
+    if MatchmakingQueue::leave(user.id) {
+        MatchmakingQueue::notify_users().await;
+    }
+
+    match lobby_service::accept_invite(&db, invite_id, user).await {
+        Ok(_) => (Status::Ok, None),
+        Err(LobbyError::NotFound) => (
+            Status::NotFound,
+            Some(Negotiated(Error::new_protocol(
+                Status::NotFound.code,
+                "Invite not found".to_string(),
+            ))),
+        ),
+        Err(LobbyError::Full) => (
+            Status::Conflict,
+            Some(Negotiated(Error::new_protocol(
+                Status::Conflict.code,
+                "Lobby is full".to_string(),
+            ))),
+        ),
+        Err(_) => (
+            Status::InternalServerError,
+            Some(Negotiated(Error::new_protocol(
+                Status::InternalServerError.code,
+                "Failed to join lobby".to_string(),
+            ))),
+        ),
+    }
+}
+
+#[delete("/lobbies/invites/<invite_id>")]
+pub async fn decline_invite(invite_id: i32, user: &User, db: Database) -> Status {
+    let user_id = user.id;
+    match db
+        .run(move |con| {
+            delete(lobby_invites::table)
+                .filter(lobby_invites::id.eq(invite_id))
+                .filter(lobby_invites::user_id.eq(user_id))
+                .execute(con)
+        })
+        .await
+    {
+        Ok(0) => Status::NotFound,
+        Ok(_) => Status::Ok,
+        Err(_) => Status::InternalServerError,
+    }
+}
@@ -1,41 +1,163 @@
+pub(crate) mod admin;
+pub(crate) mod chat;
+pub(crate) mod currency_transactions;
+pub(crate) mod friendships;
 pub(crate) mod game;
 pub(crate) mod game_user_avatar_choices;
 pub(crate) mod game_user_characters;
 pub(crate) mod game_users;
 pub mod lobbies;
+pub(crate) mod lobby_bans;
+pub(crate) mod lobby_invites;
 pub mod lobby_users;
+pub(crate) mod negotiated;
 pub mod polling;
+pub(crate) mod queue;
+pub(crate) mod sessions;
 pub(crate) mod shop;
+pub(crate) mod user_bans;
+pub(crate) mod user_mutes;
 pub mod users;
+pub(crate) mod websocket;
+
+use protocol::protocol::{Error, ErrorCode};
+use rocket::{http::Status, request::Outcome, Request};
+
+/// Error of a failed request guard. Used by the catchers to build the response.
+pub(crate) struct GuardError(pub Option<Error>);
+
+/// Fails a request guard with the status of `code`
+pub(crate) fn guard_failure<S, E>(req: &Request<'_>, code: ErrorCode, error: E) -> Outcome<S, E> {
+    guard_failure_with(req, code.into(), error)
+}
+
+/// Fails a request guard with a prepared error response
+pub(crate) fn guard_failure_with<S, E>(
+    req: &Request<'_>,
+    response: Error,
+    error: E,
+) -> Outcome<S, E> {
+    let status = Status::from_code(response.status).unwrap_or(Status::InternalServerError);
+    req.local_cache(|| GuardError(Some(response)));
+    Outcome::Error((status, error))
+}
 
 pub mod routes {
-    use protocol::protocol::{Protocol, Status};
-    use rocket::{serde::json::Json, Route};
+    use protocol::{
+        protocol::{Error, ErrorCode, Protocol, Status},
+        version::{self, DATA_HASH, PROTOCOL_VERSION},
+    };
+    use rocket::{http, serde::json::Json, Catcher, Request, Route};
+
+    use crate::service::openapi_service;
 
     use super::{
-        game_user_avatar_choices, game_user_characters, game_users, lobbies, polling, shop, users,
+        admin, chat, currency_transactions, friendships, game, game_user_avatar_choices,
+        game_user_characters, game_users, lobbies, lobby_invites, negotiated::Negotiated, polling,
+        queue, sessions, shop, user_bans, user_mutes, users, websocket, GuardError,
     };
 
-    #[get("/status")]
-    fn status() -> Json<Protocol> {
-        Json(Protocol::StatusResponse(Status {
-            version: "v0.1".to_string(),
+    #[get("/status?<version>&<data_hash>")]
+    fn status(version: Option<&str>, data_hash: Option<&str>) -> Negotiated<Protocol> {
+        if let (Some(version), Some(data_hash)) = (version, data_hash) {
+            if !version::is_compatible(version, data_hash) {
+                return Negotiated(Error::new_protocol(
+                    http::Status::UpgradeRequired.code,
+                    format!(
+                        "Client version {} ({}) is not compatible with server version {} ({})",
+                        version, data_hash, PROTOCOL_VERSION, DATA_HASH
+                    ),
+                ));
+            }
+        }
+
+        Negotiated(Protocol::StatusResponse(Status {
+            version: PROTOCOL_VERSION.to_string(),
+            data_hash: DATA_HASH.to_string(),
         }))
     }
 
+    #[get("/openapi.json")]
+    fn openapi() -> Json<&'static serde_json::Value> {
+        Json(openapi_service::get_document())
+    }
+
+    /// Responds with a `Protocol` error instead of the html error page
+    #[catch(default)]
+    fn default_catcher(
+        status: http::Status,
+        req: &Request,
+    ) -> (http::Status, Negotiated<Protocol>) {
+        // Requests rejected by a fairing are routed to a missing path
+        if let Some(error) = &req.local_cache(|| GuardError(None)).0 {
+            let status = http::Status::from_code(error.status).unwrap_or(status);
+            return (status, Negotiated(Protocol::NetworkingError(error.clone())));
+        }
+
+        let code = match status.code {
+            400 | 422 => ErrorCode::InvalidRequest,
+            401 => ErrorCode::Unauthorized,
+            404 => ErrorCode::NotFound,
+            500 => ErrorCode::Internal,
+            _ => ErrorCode::Unknown,
+        };
+
+        let mut error = Error::from(code);
+        error.status = status.code;
+        if code == ErrorCode::Unknown {
+            error.message = status.reason().unwrap_or("Unknown error").to_string();
+        }
+
+        (status, Negotiated(Protocol::NetworkingError(error)))
+    }
+
+    pub fn get_catchers() -> Vec<Catcher> {
+        catchers![default_catcher]
+    }
+
     pub fn get_api() -> Vec<Route> {
         routes![
             status,
             users::register,
             users::login,
+            users::guest_login,
+            users::claim_account,
+            sessions::refresh_session,
+            sessions::logout,
+            sessions::logout_all,
             users::me,
             users::set_display_name,
+            users::leaderboard,
+            friendships::get_friends,
+            friendships::add_friend,
+            friendships::accept_friend,
+            friendships::remove_friend,
+            currency_transactions::get_transactions,
+            user_mutes::get_mutes,
+            user_mutes::mute_user,
+            user_mutes::unmute_user,
+            chat::get_lobby_chat,
+            chat::post_lobby_message,
+            chat::get_game_chat,
+            chat::post_game_message,
             lobbies::get_current_loby_info,
             lobbies::join_lobby,
             lobbies::leave_lobby,
             lobbies::toggle_ready_state,
             lobbies::start_lobby_timer,
             lobbies::stop_lobby_timer,
+            lobbies::list_lobbies,
+            lobbies::update_lobby_settings,
+            lobbies::kick_user,
+            lobbies::promote_user,
+            lobby_invites::invite_user,
+            lobby_invites::get_invites,
+            lobby_invites::accept_invite,
+            lobby_invites::decline_invite,
+            queue::join_queue,
+            queue::get_queue_status,
+            queue::leave_queue,
+            game::get_game_state,
             game_users::get_own_user,
             game_users::get_users,
             game_user_avatar_choices::select_avatar,
@@ -47,6 +169,244 @@ pub mod routes {
             game_user_characters::move_character,
             game_user_characters::sell_character,
             polling::poll,
+            websocket::connect,
+            admin::get_running_games,
+            admin::advance_game,
+            admin::end_game,
+            admin::get_player,
+            admin::kick_player,
+            user_bans::get_bans,
+            user_bans::ban_user,
+            user_bans::unban_user,
+            admin::broadcast,
+            openapi,
         ]
     }
+
+    /// Test rockets run without a database pool. Dropping the sentinels lets
+    /// them launch, requests that need a connection then fail their guard.
+    #[cfg(test)]
+    pub(crate) fn without_sentinels(routes: Vec<Route>) -> Vec<Route> {
+        use rocket::{
+            route::{BoxFuture, Outcome},
+            Data,
+        };
+
+        fn placeholder<'r>(req: &'r Request<'_>, _: Data<'r>) -> BoxFuture<'r> {
+            Outcome::from(req, ()).pin()
+        }
+
+        routes
+            .into_iter()
+            .map(|route| {
+                let mut stripped =
+                    Route::ranked(route.rank, route.method, route.uri.as_str(), placeholder);
+                stripped.name = route.name;
+                stripped.format = route.format;
+                stripped.handler = route.handler;
+                stripped
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_endpoints_match_routes() {
+        use protocol::api::ENDPOINTS;
+
+        let routes = get_api()
+            .into_iter()
+            .map(|route| (route.method.as_str().to_string(), route.uri.to_string()))
+            .collect::<Vec<_>>();
+        let endpoints = ENDPOINTS
+            .iter()
+            .map(|endpoint| (endpoint.method.as_str().to_string(), endpoint.route()))
+            .collect::<Vec<_>>();
+
+        for route in routes.iter() {
+            assert!(endpoints.contains(route), "{:?} is not in ENDPOINTS", route);
+        }
+        for endpoint in endpoints.iter() {
+            assert!(routes.contains(endpoint), "{:?} is not mounted", endpoint);
+        }
+    }
+
+    #[test]
+    fn test_malformed_requests() {
+        use crate::{
+            game::{
+                game_instance::{GameInstance, GameSettings},
+                game_instance_player::GameInstancePlayer,
+            },
+            service::display_name_service::DisplayNameRules,
+            RunningGames,
+        };
+        use chrono::{Duration, Utc};
+        use protocol::{
+            api::{Access, ENDPOINTS},
+            protocol::{Role, Turn},
+        };
+        use rocket::{
+            fairing::AdHoc,
+            http::{Header, Method, Status},
+            local::blocking::Client,
+            tokio::sync::Mutex,
+        };
+        use std::{
+            collections::HashMap,
+            panic,
+            sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            },
+            thread,
+        };
+        use uuid::Uuid;
+
+        const PARAMS: [&str; 5] = ["-1", "abc", "99", "99999999999999999999", "%00"];
+        const KEYS: [Option<&str>; 3] = [None, Some("not-a-uuid"), Some("")];
+        const BODIES: [&str; 4] = ["", "{", "[1, 2", "{\"username\": 1}"];
+        const SESSION_KEY: &str = "00000000-0000-4000-8000-000000000001";
+
+        // Rocket answers panicking handlers with a 500, so count them instead
+        let panics = Arc::new(AtomicUsize::new(0));
+        let test_thread = thread::current().id();
+        let default_hook = panic::take_hook();
+        let counter = panics.clone();
+        panic::set_hook(Box::new(move |info| {
+            if thread::current().id() == test_thread {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+            default_hook(info);
+        }));
+
+        // Authenticated admin that is in a game, so requests reach the handlers
+        let now = Utc::now().naive_utc();
+        let user = users::User {
+            id: 1,
+            username: "user".to_string(),
+            password: String::new(),
+            salt: String::new(),
+            display_name: Some("User".to_string()),
+            currency: 0,
+            tutorial: false,
+            created_at: now,
+            updated_at: now,
+            rating: 0,
+            rated_games: 0,
+            failed_logins: 0,
+            locked_until: None,
+            guest: false,
+            display_name_key: Some("user".to_string()),
+            role: Role::Admin.as_str().to_string(),
+        };
+        let session = sessions::Session {
+            id: 1,
+            user_id: user.id,
+            token: Uuid::parse_str(SESSION_KEY).expect("valid uuid"),
+            refresh_token: Uuid::new_v4(),
+            expires_at: now + Duration::hours(1),
+            refresh_expires_at: now + Duration::days(1),
+            created_at: now,
+            updated_at: now,
+        };
+        let mut players = [(); 8].map(|_| GameInstancePlayer::new(None, "Bot".to_string(), [0; 4]));
+        players[0] = GameInstancePlayer::new(Some(user.id), "User".to_string(), [0, 1, 2, 3]);
+        let mut game = GameInstance::new(players, GameSettings::default());
+        game.turn = Turn::Shop(1, Utc::now() + Duration::hours(1));
+        let games = HashMap::from([(game.game_id, Arc::new(Mutex::new(game)))]);
+
+        let rocket = rocket::build()
+            .mount("/api/v1", without_sentinels(get_api()))
+            .register("/api/v1", get_catchers())
+            .attach(AdHoc::on_request("Test session", move |req, _| {
+                if req.headers().get_one("x-api-key") == Some(SESSION_KEY) {
+                    let auth = (user.clone(), session.clone());
+                    req.local_cache(|| Ok::<_, users::ApiKeyError>(auth));
+                }
+                Box::pin(async {})
+            }))
+            .manage(RunningGames {
+                games: Arc::new(Mutex::new(games)),
+            })
+            .manage(sessions::SessionConfig::default())
+            .manage(DisplayNameRules::default());
+        let client = Client::untracked(rocket).expect("valid rocket instance");
+        let dispatch = |method: Method, uri: &str, body: &str, key: Option<&str>| {
+            let mut request = client.req(method, format!("/api/v1{}", uri)).body(body);
+            if let Some(key) = key {
+                request.add_header(Header::new("x-api-key", key.to_string()));
+            }
+            let response = request.dispatch();
+            let status = response.status();
+            (status, response.into_string().unwrap_or_default())
+        };
+
+        for route in get_api() {
+            let path = route.uri.path().to_string();
+            let Some(endpoint) = ENDPOINTS
+                .iter()
+                .find(|e| e.path == path && e.method.as_str() == route.method.as_str())
+            else {
+                panic!("{} {} is not in ENDPOINTS", route.method, path);
+            };
+            let assert_protocol = |uri: &str, status: Status, body: &str| {
+                if !endpoint.responses.is_empty() || !status.class().is_success() {
+                    assert!(
+                        serde_json::from_str::<Protocol>(body).is_ok(),
+                        "{} {} responded with {:?}",
+                        route.method,
+                        uri,
+                        body
+                    );
+                }
+            };
+
+            for (i, param) in PARAMS.iter().enumerate() {
+                let uri = path
+                    .split('/')
+                    .map(|segment| {
+                        if segment.starts_with('<') {
+                            param
+                        } else {
+                            segment
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/");
+                let body = BODIES[i % BODIES.len()];
+
+                let (status, response) = dispatch(route.method, &uri, body, KEYS[i % KEYS.len()]);
+                assert_protocol(&uri, status, &response);
+                if endpoint.access != Access::Public {
+                    // Errors of reached handlers are answered with a 200
+                    assert!(
+                        !status.class().is_success(),
+                        "{} {} responded with {}",
+                        route.method,
+                        uri,
+                        status
+                    );
+                }
+
+                // Streaming endpoints wait for events instead of answering
+                if ["poll", "connect"].contains(&endpoint.name) {
+                    continue;
+                }
+                for body in BODIES {
+                    let (status, response) = dispatch(route.method, &uri, body, Some(SESSION_KEY));
+                    assert_protocol(&uri, status, &response);
+                    assert_ne!(
+                        status,
+                        Status::Unauthorized,
+                        "{} {} rejected the session",
+                        route.method,
+                        uri
+                    );
+                }
+            }
+        }
+
+        let _ = panic::take_hook();
+        assert_eq!(panics.load(Ordering::SeqCst), 0, "handlers panicked");
+    }
 }
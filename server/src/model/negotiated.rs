@@ -0,0 +1,77 @@
+use std::io::Cursor;
+
+use protocol::codec::Encoding;
+use rocket::{
+    http::{Header, Status},
+    response::{self, Responder},
+    Request, Response,
+};
+use serde::Serialize;
+
+/// Responds in the encoding preferred by the `Accept` header of the request.
+/// Clients that do not ask for msgpack get json.
+#[derive(Debug)]
+pub struct Negotiated<T>(pub T);
+
+impl<'r, 'o: 'r, T: Serialize> Responder<'r, 'o> for Negotiated<T> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let encoding = req
+            .headers()
+            .get_one("Accept")
+            .and_then(Encoding::from_accept)
+            .unwrap_or(Encoding::Json);
+        let body = encoding.encode(&self.0).map_err(|e| {
+            error!("Failed to encode response: {}", e);
+            Status::InternalServerError
+        })?;
+
+        Response::build()
+            .header(Header::new("Content-Type", encoding.content_type()))
+            .header(Header::new("Vary", "Accept"))
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+#[get("/")]
+fn negotiated_route() -> Negotiated<protocol::protocol::Protocol> {
+    Negotiated(protocol::protocol::Protocol::PollingTimeout)
+}
+
+#[test]
+fn test_negotiated_encoding() {
+    use protocol::protocol::Protocol;
+    use rocket::local::blocking::Client;
+
+    let client = Client::untracked(rocket::build().mount("/", routes![negotiated_route]))
+        .expect("valid rocket instance");
+
+    let response = client.get("/").dispatch();
+    assert_eq!(
+        response.headers().get_one("Content-Type"),
+        Some(Encoding::Json.content_type())
+    );
+    assert!(matches!(
+        response.into_json::<Protocol>(),
+        Some(Protocol::PollingTimeout)
+    ));
+
+    let response = client
+        .get("/")
+        .header(Header::new(
+            "Accept",
+            "application/msgpack, application/json",
+        ))
+        .dispatch();
+    assert_eq!(
+        response.headers().get_one("Content-Type"),
+        Some(Encoding::MessagePack.content_type())
+    );
+    assert_eq!(response.headers().get_one("Vary"), Some("Accept"));
+    let body = response.into_bytes().expect("a body");
+    assert!(matches!(
+        Encoding::MessagePack.decode::<Protocol>(&body),
+        Ok(Protocol::PollingTimeout)
+    ));
+}
@@ -1,21 +1,139 @@
-use async_channel::{Receiver, SendError, Sender};
 use async_std::future;
-use protocol::protocol::{Error, Protocol};
-use rocket::futures::future::join_all;
-use rocket::serde::json::Json;
+use protocol::protocol::{ErrorCode, EventBatch, Protocol, SequencedEvent};
+use rocket::request::{FromRequest, Outcome};
+use rocket::tokio::sync::watch;
+use rocket::{Orbit, Request, Rocket, State};
 use static_init::dynamic;
-use std::collections::{HashMap, HashSet};
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-use crate::model::users::User;
+use crate::{
+    model::{negotiated::Negotiated, sessions::Session, users::User},
+    service::{game_service, polling_service},
+    Database, RunningGames,
+};
+
+/// Events kept per user until they are acknowledged
+const MAX_QUEUED_EVENTS: usize = 256;
+/// Users without a connection count as online for this long after their last
+/// poll, so the gap between two long polls does not flicker their presence
+const ONLINE_GRACE: Duration = Duration::from_secs(45);
+
+/// Pending events of a single user. Events stay queued until the client
+/// acknowledges them, so a reconnecting client can resume where it left off.
+struct EventQueue {
+    events: VecDeque<SequencedEvent>,
+    last_seq: u64,
+    /// Highest sequence id that is no longer queued. Acknowledgements are
+    /// shared by all sessions of the user, so a session behind this falls back
+    /// to a snapshot.
+    dropped_seq: u64,
+    signal: watch::Sender<u64>,
+    /// Last time the user started polling
+    last_seen: Option<Instant>,
+}
+
+impl EventQueue {
+    fn new(last_seq: u64) -> Self {
+        Self {
+            events: VecDeque::new(),
+            last_seq,
+            dropped_seq: last_seq,
+            signal: watch::channel(last_seq).0,
+            last_seen: None,
+        }
+    }
+
+    /// Polling or connected through a websocket
+    fn is_online(&self) -> bool {
+        self.signal.receiver_count() > 0
+            || self
+                .last_seen
+                .is_some_and(|last_seen| last_seen.elapsed() < ONLINE_GRACE)
+    }
+
+    fn push(&mut self, event: Protocol) -> u64 {
+        self.last_seq += 1;
+        self.events.push_back(SequencedEvent {
+            seq: self.last_seq,
+            event,
+        });
+
+        while self.events.len() > MAX_QUEUED_EVENTS {
+            if let Some(dropped) = self.events.pop_front() {
+                self.dropped_seq = dropped.seq;
+            }
+        }
+
+        self.signal.send_replace(self.last_seq);
+        self.last_seq
+    }
+
+    fn ack(&mut self, seq: u64) {
+        while self.events.front().is_some_and(|e| e.seq <= seq) {
+            if let Some(acked) = self.events.pop_front() {
+                self.dropped_seq = acked.seq;
+            }
+        }
+    }
+
+    /// Returns the events after `since` or `None` if they can not be replayed
+    fn events_since(&self, since: Option<u64>) -> Option<Vec<SequencedEvent>> {
+        let since = since.unwrap_or(self.dropped_seq);
+        if since < self.dropped_seq || since > self.last_seq {
+            return None;
+        }
+
+        Some(
+            self.events
+                .iter()
+                .filter(|e| e.seq > since)
+                .cloned()
+                .collect(),
+        )
+    }
+}
 
-type NotificationChannels = Arc<(Sender<Protocol>, Receiver<Protocol>)>;
 #[derive(Default)]
 pub struct ActivePolls {
-    polls: HashMap<i32, NotificationChannels>,
+    polls: HashMap<i32, EventQueue>,
     channels: HashMap<Channel, HashSet<i32>>,
+    /// Dropped when the session logs out, which ends its polls and websockets
+    sessions: HashMap<i32, watch::Sender<()>>,
+}
+
+/// Subscription of a poll or websocket of a session to the events of its user
+pub struct Subscription {
+    session: i32,
+    events: watch::Receiver<u64>,
+    closed: watch::Receiver<()>,
+}
+
+impl Subscription {
+    /// Waits for new events. Errors once the session logged out or the queue
+    /// of the user was discarded.
+    pub async fn changed(&mut self) -> Result<(), watch::error::RecvError> {
+        rocket::tokio::select! {
+            changed = self.events.changed() => changed,
+            closed = self.closed.changed() => closed,
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let mut polls = ActivePolls::get().lock().unwrap();
+        // This subscription is the last receiver of the session
+        if polls
+            .sessions
+            .get(&self.session)
+            .is_some_and(|sender| sender.receiver_count() <= 1)
+        {
+            polls.sessions.remove(&self.session);
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -32,65 +150,114 @@ impl ActivePolls {
         &ACTIVE_POLLS
     }
 
-    pub fn register(user: &User) -> NotificationChannels {
-        let channel = {
-            let polls = &Self::get().lock().unwrap().polls;
-            polls.get(&user.id).cloned()
-        };
+    /// Subscribes the session to new events of the user
+    pub fn register(user: i32, session: i32) -> Subscription {
+        let mut polls = Self::get().lock().unwrap();
+        let queue = polls
+            .polls
+            .entry(user)
+            .or_insert_with(|| EventQueue::new(0));
+        queue.last_seen = Some(Instant::now());
+        let events = queue.signal.subscribe();
+        let closed = polls
+            .sessions
+            .entry(session)
+            .or_insert_with(|| watch::channel(()).0)
+            .subscribe();
 
-        match channel {
-            Some(c) => c,
-            None => {
-                let new_channel = Arc::new(async_channel::unbounded());
-                Self::get()
-                    .lock()
-                    .unwrap()
-                    .polls
-                    .insert(user.id, new_channel.clone());
-                new_channel
-            }
+        Subscription {
+            session,
+            events,
+            closed,
         }
     }
 
-    pub async fn notify(user: i32, data: Protocol) -> Result<(), SendError<Protocol>> {
-        let channel = {
-            let polls = &Self::get().lock().unwrap().polls;
-            polls.get(&user).cloned()
-        };
+    /// Ends the polls and websockets of a session that logged out
+    pub fn close_session(session: i32) {
+        Self::get().lock().unwrap().sessions.remove(&session);
+    }
+
+    /// Queues an event for the user and returns its sequence id
+    pub async fn notify(user: i32, data: Protocol) -> u64 {
+        Self::get()
+            .lock()
+            .unwrap()
+            .polls
+            .entry(user)
+            .or_insert_with(|| EventQueue::new(0))
+            .push(data)
+    }
+
+    pub fn ack(user: i32, seq: u64) {
+        if let Some(queue) = Self::get().lock().unwrap().polls.get_mut(&user) {
+            queue.ack(seq);
+        }
+    }
+
+    /// Returns the pending events after `since` together with the current
+    /// sequence id. If events were dropped in between, `Err` with the current
+    /// sequence id is returned instead and all pending events are discarded.
+    pub fn events_since(user: i32, since: Option<u64>) -> Result<(Vec<SequencedEvent>, u64), u64> {
+        let mut polls = Self::get().lock().unwrap();
+        let queue = polls
+            .polls
+            .entry(user)
+            .or_insert_with(|| EventQueue::new(0));
 
-        let channel = match channel {
-            Some(channel) => channel,
+        match queue.events_since(since) {
+            Some(events) => Ok((events, queue.last_seq)),
             None => {
-                let channel = Arc::new(async_channel::unbounded());
-                Self::get()
-                    .lock()
-                    .unwrap()
-                    .polls
-                    .insert(user, channel.clone());
-                channel
+                queue.ack(queue.last_seq);
+                Err(queue.last_seq)
             }
-        };
+        }
+    }
+
+    pub fn online_users() -> HashSet<i32> {
+        Self::get()
+            .lock()
+            .unwrap()
+            .polls
+            .iter()
+            .filter(|(_, queue)| queue.is_online())
+            .map(|(user, _)| *user)
+            .collect()
+    }
+
+    /// Queues the event for every online user and returns how many were reached
+    pub async fn broadcast(data: Protocol) -> usize {
+        let mut polls = Self::get().lock().unwrap();
+        let mut reached = 0;
+        for queue in polls.polls.values_mut().filter(|queue| queue.is_online()) {
+            queue.push(data.clone());
+            reached += 1;
+        }
+        reached
+    }
 
-        channel.0.send(data).await
+    pub fn lobby_users() -> HashSet<i32> {
+        Self::get()
+            .lock()
+            .unwrap()
+            .channels
+            .iter()
+            .filter(|(channel, _)| matches!(channel, Channel::Lobby(_)))
+            .flat_map(|(_, users)| users.iter().copied())
+            .collect()
     }
 
-    pub async fn notify_channel(
-        channel: &Channel,
-        data: Protocol,
-    ) -> Vec<Result<(), SendError<Protocol>>> {
-        let users = {
-            let polls = &Self::get().lock().unwrap().channels;
-            polls.get(channel).cloned()
+    pub async fn notify_channel(channel: &Channel, data: Protocol) {
+        let mut polls = Self::get().lock().unwrap();
+        let Some(users) = polls.channels.get(channel).cloned() else {
+            return;
         };
-        if let Some(users) = users {
-            return join_all(
-                users
-                    .iter()
-                    .map(|user| ActivePolls::notify(*user, data.clone())),
-            )
-            .await;
+        for user in users {
+            polls
+                .polls
+                .entry(user)
+                .or_insert_with(|| EventQueue::new(0))
+                .push(data.clone());
         }
-        Vec::new()
     }
 
     fn join(&mut self, channel: Channel, user: i32) {
@@ -127,28 +294,160 @@ impl ActivePolls {
         polls.channels.remove(channel);
     }
 
+    /// Removes the user from all channels and discards pending events, which
+    /// ends the polls and websockets of all sessions. The sequence continues,
+    /// so clients detect the gap.
     pub(crate) fn clear_user(id: i32) {
         let mut polls = Self::get().lock().unwrap();
-        polls.channels.retain(|_, users| !users.contains(&id));
-        if let Some(c) = polls.polls.remove(&id) {
-            c.0.close();
-            c.1.close();
-            drop(c);
+        for users in polls.channels.values_mut() {
+            users.remove(&id);
         }
+        let last_seq = polls.polls.get(&id).map_or(0, |queue| queue.last_seq);
+        polls.polls.insert(id, EventQueue::new(last_seq));
     }
 }
 
-#[get("/poll")]
-pub async fn poll(user: &User) -> Json<Protocol> {
-    let notify = ActivePolls::register(user);
+/// Database that is only connected on demand. Long running requests should not
+/// block a pooled connection while waiting for events.
+pub struct LazyDatabase<'r>(&'r Rocket<Orbit>);
 
-    let dur = Duration::from_secs(30);
+impl LazyDatabase<'_> {
+    pub async fn get(&self) -> Option<Database> {
+        Database::get_one(self.0).await
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for LazyDatabase<'r> {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(LazyDatabase(req.rocket()))
+    }
+}
 
-    match future::timeout(dur, notify.1.recv()).await {
-        Ok(res) => match res {
-            Ok(res) => Json(res),
-            Err(err) => Json(Error::new_protocol(500, err.to_string())),
+/// Collects the events after `since` into a batch. Falls back to a snapshot
+/// of the current state if the events can not be replayed.
+pub async fn get_event_batch(
+    db: &LazyDatabase<'_>,
+    games: &RunningGames,
+    user: i32,
+    since: Option<u64>,
+) -> EventBatch {
+    match ActivePolls::events_since(user, since) {
+        Ok((events, last_seq)) => EventBatch {
+            snapshot: None,
+            events,
+            last_seq,
         },
-        Err(_) => Json(Protocol::PollingTimeout),
+        Err(last_seq) => {
+            debug!("Events for user {} since {:?} are gone", user, since);
+            let snapshot = match db.get().await {
+                Some(db) => polling_service::get_snapshot(&db, games, user).await,
+                None => Vec::new(),
+            };
+            EventBatch {
+                snapshot: Some(snapshot),
+                events: Vec::new(),
+                last_seq,
+            }
+        }
+    }
+}
+
+#[get("/poll?<since>")]
+pub async fn poll(
+    user: &User,
+    session: &Session,
+    since: Option<u64>,
+    db: LazyDatabase<'_>,
+    games: &State<RunningGames>,
+) -> Negotiated<Protocol> {
+    let mut signal = ActivePolls::register(user.id, session.id);
+    if let Some(since) = since {
+        ActivePolls::ack(user.id, since);
     }
+    game_service::resume_player(games, user.id).await;
+
+    let batch = get_event_batch(&db, games, user.id, since).await;
+    if batch.snapshot.is_some() || !batch.events.is_empty() {
+        return Negotiated(Protocol::EventBatchResponse(batch));
+    }
+
+    let dur = Duration::from_secs(30);
+
+    match future::timeout(dur, signal.changed()).await {
+        Ok(Ok(())) => Negotiated(Protocol::EventBatchResponse(
+            get_event_batch(&db, games, user.id, since).await,
+        )),
+        // Logged out or banned while waiting
+        Ok(Err(_)) => Negotiated(ErrorCode::InvalidSession.into()),
+        Err(_) => Negotiated(Protocol::PollingTimeout),
+    }
+}
+
+#[test]
+fn test_event_queue_replay() {
+    let mut queue = EventQueue::new(0);
+    for _ in 0..3 {
+        queue.push(Protocol::PollingTimeout);
+    }
+
+    let seqs = |events: Option<Vec<SequencedEvent>>| {
+        events.map(|events| events.iter().map(|e| e.seq).collect::<Vec<_>>())
+    };
+
+    assert_eq!(seqs(queue.events_since(None)), Some(vec![1, 2, 3]));
+    assert_eq!(seqs(queue.events_since(Some(1))), Some(vec![2, 3]));
+
+    // Acknowledged events are not replayed
+    queue.ack(2);
+    assert_eq!(seqs(queue.events_since(None)), Some(vec![3]));
+    assert_eq!(seqs(queue.events_since(Some(2))), Some(vec![3]));
+
+    // Another session that did not see them yet can not replay them
+    assert_eq!(seqs(queue.events_since(Some(1))), None);
+
+    // Unknown sequence
+    assert_eq!(seqs(queue.events_since(Some(4))), None);
+
+    // Overflow drops the oldest events
+    for _ in 0..MAX_QUEUED_EVENTS {
+        queue.push(Protocol::PollingTimeout);
+    }
+    assert_eq!(queue.events.len(), MAX_QUEUED_EVENTS);
+    assert_eq!(seqs(queue.events_since(Some(2))), None);
+    assert_eq!(
+        queue.events_since(Some(3)).map(|events| events.len()),
+        Some(MAX_QUEUED_EVENTS)
+    );
+}
+
+#[test]
+fn test_close_session() {
+    use rocket::tokio::time::timeout;
+
+    // Not used by other tests
+    const USER: i32 = -42;
+
+    rocket::async_test(async {
+        let mut phone = ActivePolls::register(USER, -1);
+        let mut desktop = ActivePolls::register(USER, -2);
+
+        // Logging out one device keeps the streams of the others
+        ActivePolls::close_session(-1);
+        assert!(phone.changed().await.is_err());
+
+        ActivePolls::notify(USER, Protocol::PollingTimeout).await;
+        let changed = timeout(Duration::from_secs(1), desktop.changed()).await;
+        assert!(matches!(changed, Ok(Ok(()))));
+
+        drop(phone);
+        drop(desktop);
+        assert!(!ActivePolls::get()
+            .lock()
+            .unwrap()
+            .sessions
+            .contains_key(&-2));
+    });
 }
@@ -0,0 +1,55 @@
+use protocol::protocol::{Error, ErrorCode, Protocol};
+use rocket::{http::Status, State};
+
+use crate::{
+    service::{
+        game_service, lobby_service,
+        matchmaking_service::{MatchmakingQueue, QueueEntry},
+    },
+    Database, RunningGames,
+};
+
+use super::negotiated::Negotiated;
+use super::users::User;
+
+#[put("/queue")]
+pub async fn join_queue(
+    user: &User,
+    db: Database,
+    games: &State<RunningGames>,
+) -> Negotiated<Protocol> {
+    if game_service::get_user_game(games, user.id).await.is_some() {
+        return Negotiated(ErrorCode::AlreadyInGame.into());
+    }
+
+    let Some(display_name) = user.display_name.clone() else {
+        return Negotiated(ErrorCode::DisplayNameRequired.into());
+    };
+
+    lobby_service::remove_user_from_lobbies(&db, user).await;
+
+    let status = MatchmakingQueue::join(QueueEntry::new(user.id, display_name, user.rating));
+    MatchmakingQueue::notify_users().await;
+
+    Negotiated(Protocol::QueueStatusResponse(status))
+}
+
+#[get("/queue")]
+pub async fn get_queue_status(user: &User) -> Negotiated<Protocol> {
+    match MatchmakingQueue::status(user.id) {
+        Some(status) => Negotiated(Protocol::QueueStatusResponse(status)),
+        None => Negotiated(Error::new_protocol(
+            Status::NotFound.code,
+            "Not in queue".to_string(),
+        )),
+    }
+}
+
+#[delete("/queue")]
+pub async fn leave_queue(user: &User) -> Negotiated<Protocol> {
+    if MatchmakingQueue::leave(user.id) {
+        MatchmakingQueue::notify_users().await;
+    }
+
+    Negotiated(Protocol::QueueLeaveResponse)
+}
@@ -0,0 +1,275 @@
+use std::{env, fmt};
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::{delete, insert_into, prelude::*, update};
+use protocol::protocol::{ErrorCode, Protocol, SessionKeys};
+use rocket::{
+    request::{self, FromRequest, Outcome},
+    serde::json::Json,
+    Request, State,
+};
+use uuid::Uuid;
+
+use crate::{schema::sessions, Database};
+
+use super::negotiated::Negotiated;
+use super::polling::ActivePolls;
+use super::users::{api_key_failure, authenticate, ApiKeyError, User};
+
+/// Lifetimes of the session keys. Configured with `SESSION_TIMEOUT` and
+/// `SESSION_REFRESH_TIMEOUT` in minutes.
+#[derive(Debug, Clone, Copy)]
+pub struct SessionConfig {
+    /// Time without requests after which a session key expires
+    pub timeout: Duration,
+    /// Time after which the refresh key expires
+    pub refresh_timeout: Duration,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::minutes(60),
+            refresh_timeout: Duration::days(30),
+        }
+    }
+}
+
+impl SessionConfig {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+
+        Self {
+            timeout: minutes_from_env("SESSION_TIMEOUT", default.timeout),
+            refresh_timeout: minutes_from_env("SESSION_REFRESH_TIMEOUT", default.refresh_timeout),
+        }
+    }
+
+    /// Expiry is only moved once a quarter of the timeout passed, so not every
+    /// request writes to the database
+    pub fn needs_bump(&self, session: &Session) -> bool {
+        session.expires_at < Utc::now().naive_utc() + self.timeout - self.timeout / 4
+    }
+}
+
+#[derive(Identifiable, Queryable, Associations, Clone)]
+#[diesel(belongs_to(User))]
+pub struct Session {
+    pub id: i32,
+    pub user_id: i32,
+    pub token: Uuid,
+    pub refresh_token: Uuid,
+    pub expires_at: NaiveDateTime,
+    pub refresh_expires_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("id", &self.id)
+            .field("user_id", &self.user_id)
+            .field("token", &"[REDACTED]".to_string())
+            .field("refresh_token", &"[REDACTED]".to_string())
+            .field("expires_at", &self.expires_at)
+            .field("refresh_expires_at", &self.refresh_expires_at)
+            .finish()
+    }
+}
+
+impl From<&Session> for SessionKeys {
+    fn from(session: &Session) -> Self {
+        Self {
+            key: session.token.to_string(),
+            refresh_key: session.refresh_token.to_string(),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'r Session {
+    type Error = ApiKeyError;
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        match authenticate(req).await {
+            Ok((_, session)) => Outcome::Success(session),
+            Err(e) => api_key_failure(req, e),
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = sessions)]
+struct NewSession {
+    user_id: i32,
+    token: Uuid,
+    refresh_token: Uuid,
+    expires_at: NaiveDateTime,
+    refresh_expires_at: NaiveDateTime,
+}
+
+/// Starts a new session for the user and removes its expired ones
+pub fn create_session(
+    con: &mut PgConnection,
+    user_id: i32,
+    config: &SessionConfig,
+) -> QueryResult<Session> {
+    let now = Utc::now().naive_utc();
+
+    delete(sessions::table)
+        .filter(sessions::user_id.eq(user_id))
+        .filter(sessions::refresh_expires_at.lt(now))
+        .execute(con)?;
+
+    insert_into(sessions::table)
+        .values(NewSession {
+            user_id,
+            token: Uuid::new_v4(),
+            refresh_token: Uuid::new_v4(),
+            expires_at: now + config.timeout,
+            refresh_expires_at: now + config.refresh_timeout,
+        })
+        .get_result(con)
+}
+
+pub fn bump_session(
+    con: &mut PgConnection,
+    session_id: i32,
+    config: &SessionConfig,
+) -> QueryResult<usize> {
+    update(sessions::table)
+        .filter(sessions::id.eq(session_id))
+        .set(sessions::expires_at.eq(Utc::now().naive_utc() + config.timeout))
+        .execute(con)
+}
+
+#[post("/sessions/refresh", data = "<refresh_key>")]
+pub async fn refresh_session(
+    refresh_key: Json<String>,
+    db: Database,
+    config: &State<SessionConfig>,
+) -> Negotiated<Protocol> {
+    let Ok(refresh_token) = Uuid::parse_str(&refresh_key) else {
+        return Negotiated(ErrorCode::Unauthorized.into());
+    };
+    let config = *config.inner();
+
+    // Refresh keys are rotated, so each one can only be used once
+    let session = db
+        .run(move |con| {
+            let now = Utc::now().naive_utc();
+            update(sessions::table)
+                .filter(sessions::refresh_token.eq(refresh_token))
+                .filter(sessions::refresh_expires_at.gt(now))
+                .set((
+                    sessions::token.eq(Uuid::new_v4()),
+                    sessions::refresh_token.eq(Uuid::new_v4()),
+                    sessions::expires_at.eq(now + config.timeout),
+                    sessions::refresh_expires_at.eq(now + config.refresh_timeout),
+                ))
+                .get_result::<Session>(con)
+                .optional()
+        })
+        .await;
+
+    match session {
+        Ok(Some(session)) => Negotiated(Protocol::SessionResponse((&session).into())),
+        Ok(None) => Negotiated(ErrorCode::Unauthorized.into()),
+        Err(e) => {
+            warn!("Failed to refresh session: {:?}", e);
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
+
+#[delete("/sessions")]
+pub async fn logout(session: &Session, db: Database) -> Negotiated<Protocol> {
+    let session_id = session.id;
+    match db
+        .run(move |con| {
+            delete(sessions::table)
+                .filter(sessions::id.eq(session_id))
+                .execute(con)
+        })
+        .await
+    {
+        Ok(_) => {
+            ActivePolls::close_session(session_id);
+            Negotiated(Protocol::LogoutResponse)
+        }
+        Err(e) => {
+            warn!("Failed to delete session {}: {:?}", session_id, e);
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
+
+#[delete("/sessions/all")]
+pub async fn logout_all(user: &User, db: Database) -> Negotiated<Protocol> {
+    let user_id = user.id;
+    match db
+        .run(move |con| {
+            delete(sessions::table)
+                .filter(sessions::user_id.eq(user_id))
+                .returning(sessions::id)
+                .get_results::<i32>(con)
+        })
+        .await
+    {
+        Ok(session_ids) => {
+            for session_id in session_ids {
+                ActivePolls::close_session(session_id);
+            }
+            Negotiated(Protocol::LogoutResponse)
+        }
+        Err(e) => {
+            warn!("Failed to delete sessions of user {}: {:?}", user_id, e);
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
+
+/// Reads a positive number of minutes. Invalid values fall back to `default`.
+fn minutes_from_env(name: &str, default: Duration) -> Duration {
+    let Ok(value) = env::var(name) else {
+        return default;
+    };
+
+    match value.trim().parse::<i64>() {
+        // Larger values overflow `Duration`
+        Ok(minutes) if (1..=i64::MAX / 60_000).contains(&minutes) => Duration::minutes(minutes),
+        _ => {
+            warn!(
+                "{} {} is not a valid number of minutes, using {}",
+                name,
+                value,
+                default.num_minutes()
+            );
+            default
+        }
+    }
+}
+
+#[test]
+fn test_needs_bump() {
+    let config = SessionConfig {
+        timeout: Duration::minutes(60),
+        refresh_timeout: Duration::days(1),
+    };
+    let now = Utc::now().naive_utc();
+    let session = |expires_at| Session {
+        id: 1,
+        user_id: 1,
+        token: Uuid::new_v4(),
+        refresh_token: Uuid::new_v4(),
+        expires_at,
+        refresh_expires_at: now + config.refresh_timeout,
+        created_at: now,
+        updated_at: now,
+    };
+
+    assert!(!config.needs_bump(&session(now + Duration::minutes(60))));
+    assert!(!config.needs_bump(&session(now + Duration::minutes(50))));
+    assert!(config.needs_bump(&session(now + Duration::minutes(40))));
+    assert!(config.needs_bump(&session(now - Duration::minutes(1))));
+}
@@ -6,11 +6,12 @@ use crate::{
 };
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use protocol::protocol::{BuyRequest, Error, GameUserInfo, Protocol};
-use rocket::{http::Status, serde::json::Json};
+use protocol::protocol::{BuyRequest, Error, ErrorCode, Protocol};
+use rocket::serde::json::Json;
 use serde::{Deserialize, Serialize};
 
 use super::game::GameGuard;
+use super::negotiated::Negotiated;
 
 #[derive(Queryable, Identifiable, Associations, Serialize, Deserialize, Clone, Debug)]
 #[diesel(belongs_to(GameUser))]
@@ -79,83 +80,53 @@ pub enum ShopError {
 }
 
 #[get("/games/shops")]
-pub async fn get_shop(game: GameGuard, user: &User) -> Json<Protocol> {
+pub async fn get_shop(game: GameGuard, user: &User) -> Negotiated<Protocol> {
     let game = game.0.lock().await;
     if let Some(game_user) = game.get_user(user.id) {
-        Json(Protocol::GameShopResponse(
-            GameUserInfo {
-                experience: game_user.experience,
-                health: game_user.health,
-                money: game_user.money,
-                name: game_user.display_name.to_string(),
-                avatar: game_user.god.clone().map(|g| g.id),
-            },
+        Negotiated(Protocol::GameShopResponse(
+            game_user.user_info(),
             game_user.shop.locked,
             game_user.shop.characters.clone(),
         ))
     } else {
-        Json(Error::new_protocol(
-            Status::InternalServerError.code,
-            "Internal server error".to_string(),
-        ))
+        Negotiated(ErrorCode::NotInGame.into())
     }
 }
 
 #[post("/games/shops")]
-pub async fn reroll_shop(game: GameGuard, user: &User) -> Json<Protocol> {
+pub async fn reroll_shop(game: GameGuard, user: &User) -> Negotiated<Protocol> {
     let mut game = game.0.lock().await;
-    if let Some(game_user) = game.get_user_mut(user.id) {
-        // TODO: Handle error
-        if game_user.reroll().is_ok() {
-            Json(Protocol::GameShopResponse(
-                GameUserInfo {
-                    experience: game_user.experience,
-                    health: game_user.health,
-                    money: game_user.money,
-                    name: game_user.display_name.to_string(),
-                    avatar: game_user.god.clone().map(|g| g.id),
-                },
-                false,
-                game_user.shop.characters.clone(),
-            ))
-        } else {
-            Json(Error::new_protocol_response(
-                Status::InternalServerError.code,
-                "Internal server error".to_string(),
-                Protocol::RerollShopRequest,
-            ))
-        }
-    } else {
-        Json(Error::new_protocol_response(
-            Status::InternalServerError.code,
-            "Internal server error".to_string(),
-            Protocol::RerollShopRequest,
-        ))
+    let game_user = game.get_shop_user_mut(user.id).and_then(|game_user| {
+        game_user.reroll()?;
+        Ok(game_user)
+    });
+
+    match game_user {
+        Ok(game_user) => Negotiated(Protocol::GameShopResponse(
+            game_user.user_info(),
+            false,
+            game_user.shop.characters.clone(),
+        )),
+        Err(e) => Negotiated(Protocol::NetworkingError(
+            Error::from(e).with_reference(Protocol::RerollShopRequest),
+        )),
     }
 }
 
 #[patch("/games/shops")]
-pub async fn toggle_lock_shop(game: GameGuard, user: &User) -> Json<Protocol> {
+pub async fn toggle_lock_shop(game: GameGuard, user: &User) -> Negotiated<Protocol> {
     let mut game = game.0.lock().await;
-    if let Some(user) = game.get_user_mut(user.id) {
-        user.shop.locked = !user.shop.locked;
-
-        Json(Protocol::GameShopResponse(
-            GameUserInfo {
-                experience: user.experience,
-                health: user.health,
-                money: user.money,
-                name: user.display_name.to_string(),
-                avatar: user.god.clone().map(|g| g.id),
-            },
-            user.shop.locked,
-            user.shop.characters.clone(),
-        ))
-    } else {
-        Json(Error::new_protocol(
-            Status::InternalServerError.code,
-            "Internal server error".to_string(),
-        ))
+    match game.get_shop_user_mut(user.id) {
+        Ok(user) => {
+            user.shop.locked = !user.shop.locked;
+
+            Negotiated(Protocol::GameShopResponse(
+                user.user_info(),
+                user.shop.locked,
+                user.shop.characters.clone(),
+            ))
+        }
+        Err(e) => Negotiated(e.into()),
     }
 }
 
@@ -164,39 +135,24 @@ pub async fn buy_character(
     user: &User,
     game: GameGuard,
     buy_request: Json<BuyRequest>,
-) -> Json<Protocol> {
+) -> Negotiated<Protocol> {
     let mut game = game.0.lock().await;
-    if let Some(game_user) = game.get_user_mut(user.id) {
-        if game_user
-            .buy(
-                buy_request.character_idx as usize,
-                buy_request.target_idx as usize,
-            )
-            .is_ok()
-        {
-            Json(Protocol::BuyResponse(
-                GameUserInfo {
-                    experience: game_user.experience,
-                    health: game_user.health,
-                    money: game_user.money,
-                    name: game_user.display_name.to_string(),
-                    avatar: game_user.god.clone().map(|g| g.id),
-                },
-                game_user.shop.characters.clone(),
-                game_user.board.to_vec(),
-            ))
-        } else {
-            Json(Error::new_protocol_response(
-                Status::InternalServerError.code,
-                "Internal server error".to_string(),
-                Protocol::BuyRequest(buy_request.into_inner()),
-            ))
-        }
-    } else {
-        Json(Error::new_protocol_response(
-            Status::InternalServerError.code,
-            "Internal server error".to_string(),
-            Protocol::BuyRequest(buy_request.into_inner()),
-        ))
+    let game_user = game.get_shop_user_mut(user.id).and_then(|game_user| {
+        game_user.buy(
+            buy_request.character_idx as usize,
+            buy_request.target_idx as usize,
+        )?;
+        Ok(game_user)
+    });
+
+    match game_user {
+        Ok(game_user) => Negotiated(Protocol::BuyResponse(
+            game_user.user_info(),
+            game_user.shop.characters.clone(),
+            game_user.board.to_vec(),
+        )),
+        Err(e) => Negotiated(Protocol::NetworkingError(
+            Error::from(e).with_reference(Protocol::BuyRequest(buy_request.into_inner())),
+        )),
     }
 }
@@ -0,0 +1,196 @@
+use crate::{
+    schema::{sessions, user_bans, users},
+    service::game_service,
+    Database, RunningGames,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::{delete, dsl::now, insert_into, prelude::*, update};
+use protocol::protocol::{BanInfo, BanRequest, ErrorCode, Protocol, UserBan};
+use rocket::{serde::json::Json, State};
+
+use super::{admin::Admin, negotiated::Negotiated, polling::ActivePolls};
+
+/// Ban records returned to admins
+const BAN_HISTORY_SIZE: i64 = 100;
+const MAX_REASON_LENGTH: usize = 256;
+
+#[derive(Insertable)]
+#[diesel(table_name = user_bans)]
+pub struct NewUserBan {
+    user_id: i32,
+    issued_by: Option<i32>,
+    reason: String,
+    expires_at: Option<NaiveDateTime>,
+}
+
+/// Returns the ban that ends last if the user is banned right now
+pub fn get_active_ban(con: &mut PgConnection, user_id: i32) -> QueryResult<Option<BanInfo>> {
+    user_bans::table
+        .filter(user_bans::user_id.eq(user_id))
+        .filter(
+            user_bans::expires_at
+                .is_null()
+                .or(user_bans::expires_at.gt(now)),
+        )
+        // Postgres sorts permanent bans first when descending
+        .order(user_bans::expires_at.desc())
+        .select((user_bans::reason, user_bans::expires_at))
+        .first::<(String, Option<NaiveDateTime>)>(con)
+        .optional()
+        .map(|ban| {
+            ban.map(|(reason, expires_at)| BanInfo {
+                reason,
+                expires_at: expires_at.map(|expires_at| DateTime::from_utc(expires_at, Utc)),
+            })
+        })
+}
+
+fn load_bans(con: &mut PgConnection) -> QueryResult<Vec<UserBan>> {
+    user_bans::table
+        .inner_join(users::table.on(users::id.eq(user_bans::user_id)))
+        .order(user_bans::created_at.desc())
+        .limit(BAN_HISTORY_SIZE)
+        .select((
+            user_bans::id,
+            user_bans::user_id,
+            users::username,
+            user_bans::issued_by,
+            user_bans::reason,
+            user_bans::expires_at,
+            user_bans::created_at,
+        ))
+        .load::<(
+            i32,
+            i32,
+            String,
+            Option<i32>,
+            String,
+            Option<NaiveDateTime>,
+            NaiveDateTime,
+        )>(con)
+        .map(|bans| {
+            bans.into_iter()
+                .map(
+                    |(id, user_id, username, issued_by, reason, expires_at, created_at)| UserBan {
+                        id,
+                        user_id,
+                        username,
+                        issued_by,
+                        reason,
+                        expires_at: expires_at
+                            .map(|expires_at| DateTime::from_utc(expires_at, Utc)),
+                        created_at: DateTime::from_utc(created_at, Utc),
+                    },
+                )
+                .collect()
+        })
+}
+
+fn bans_response(bans: QueryResult<Vec<UserBan>>) -> Negotiated<Protocol> {
+    match bans {
+        Ok(bans) => Negotiated(Protocol::UserBansResponse(bans)),
+        Err(e) => {
+            warn!("Failed to update bans: {:?}", e);
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
+
+/// Hands the game slot of the user to a bot, so the game goes on without them
+async fn remove_from_game(games: &RunningGames, user_id: i32) {
+    let Some(game_id) = game_service::get_user_game(games, user_id).await else {
+        return;
+    };
+    let Some(game) = games.games.lock().await.get(&game_id).cloned() else {
+        return;
+    };
+
+    let mut game = game.lock().await;
+    if let Some(player_id) = game.get_user(user_id).map(|player| player.id) {
+        game_service::kick_player(&mut game, player_id).await;
+    }
+}
+
+#[get("/admin/bans")]
+pub async fn get_bans(_admin: Admin<'_>, db: Database) -> Negotiated<Protocol> {
+    bans_response(db.run(load_bans).await)
+}
+
+#[put("/admin/bans/<user_id>", data = "<ban>")]
+pub async fn ban_user(
+    admin: Admin<'_>,
+    db: Database,
+    games: &State<RunningGames>,
+    user_id: i32,
+    ban: Json<BanRequest>,
+) -> Negotiated<Protocol> {
+    let reason = ban.reason.trim().to_string();
+    if user_id == admin.0.id
+        || reason.is_empty()
+        || reason.chars().count() > MAX_REASON_LENGTH
+        || ban
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Negotiated(ErrorCode::InvalidRequest.into());
+    }
+
+    let new_ban = NewUserBan {
+        user_id,
+        issued_by: Some(admin.0.id),
+        reason,
+        expires_at: ban.expires_at.map(|expires_at| expires_at.naive_utc()),
+    };
+    let bans = db
+        .run(move |con| {
+            con.transaction(|con| {
+                insert_into(user_bans::table).values(new_ban).execute(con)?;
+                delete(sessions::table)
+                    .filter(sessions::user_id.eq(user_id))
+                    .execute(con)?;
+
+                load_bans(con)
+            })
+        })
+        .await;
+
+    match bans {
+        Err(diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::ForeignKeyViolation,
+            _,
+        )) => Negotiated(ErrorCode::NotFound.into()),
+        Err(e) => bans_response(Err(e)),
+        bans => {
+            info!("Admin {} banned user {}", admin.0.id, user_id);
+            // Clearing discards pending events, so the game leave has to come after it
+            ActivePolls::clear_user(user_id);
+            remove_from_game(games, user_id).await;
+            bans_response(bans)
+        }
+    }
+}
+
+/// Ends the active bans early. The records are kept for the history.
+#[delete("/admin/bans/<user_id>")]
+pub async fn unban_user(admin: Admin<'_>, db: Database, user_id: i32) -> Negotiated<Protocol> {
+    let bans = db
+        .run(move |con| {
+            update(user_bans::table)
+                .filter(user_bans::user_id.eq(user_id))
+                .filter(
+                    user_bans::expires_at
+                        .is_null()
+                        .or(user_bans::expires_at.gt(now)),
+                )
+                .set(user_bans::expires_at.eq(now.nullable()))
+                .execute(con)?;
+
+            load_bans(con)
+        })
+        .await;
+
+    if bans.is_ok() {
+        info!("Admin {} unbanned user {}", admin.0.id, user_id);
+    }
+    bans_response(bans)
+}
@@ -0,0 +1,97 @@
+use crate::{
+    schema::{user_mutes, users},
+    Database,
+};
+use diesel::{delete, insert_into, prelude::*};
+use protocol::protocol::{ErrorCode, MutedUser, Protocol};
+
+use super::negotiated::Negotiated;
+use super::users::User;
+
+#[derive(Insertable)]
+#[diesel(table_name = user_mutes)]
+pub struct NewUserMute {
+    user_id: i32,
+    muted_user_id: i32,
+}
+
+fn load_mutes(con: &mut PgConnection, user_id: i32) -> QueryResult<Vec<MutedUser>> {
+    user_mutes::table
+        .inner_join(users::table.on(users::id.eq(user_mutes::muted_user_id)))
+        .filter(user_mutes::user_id.eq(user_id))
+        .order(users::display_name.asc())
+        .select((users::id, users::display_name))
+        .load::<(i32, Option<String>)>(con)
+        .map(|mutes| {
+            mutes
+                .into_iter()
+                .map(|(id, name)| MutedUser {
+                    id,
+                    name: name.unwrap_or_default(),
+                })
+                .collect()
+        })
+}
+
+fn mutes_response(user_id: i32, mutes: QueryResult<Vec<MutedUser>>) -> Negotiated<Protocol> {
+    match mutes {
+        Ok(mutes) => Negotiated(Protocol::MutesResponse(mutes)),
+        Err(e) => {
+            warn!("Failed to update mutes of user {}: {:?}", user_id, e);
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
+
+#[get("/users/@me/mutes")]
+pub async fn get_mutes(user: &User, db: Database) -> Negotiated<Protocol> {
+    let user_id = user.id;
+    mutes_response(user_id, db.run(move |con| load_mutes(con, user_id)).await)
+}
+
+#[put("/users/@me/mutes/<muted_user_id>")]
+pub async fn mute_user(user: &User, db: Database, muted_user_id: i32) -> Negotiated<Protocol> {
+    if muted_user_id == user.id {
+        return Negotiated(ErrorCode::InvalidRequest.into());
+    }
+
+    let user_id = user.id;
+    let mutes = db
+        .run(move |con| {
+            insert_into(user_mutes::table)
+                .values(NewUserMute {
+                    user_id,
+                    muted_user_id,
+                })
+                .on_conflict_do_nothing()
+                .execute(con)?;
+
+            load_mutes(con, user_id)
+        })
+        .await;
+
+    match mutes {
+        Err(diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::ForeignKeyViolation,
+            _,
+        )) => Negotiated(ErrorCode::NotFound.into()),
+        mutes => mutes_response(user_id, mutes),
+    }
+}
+
+#[delete("/users/@me/mutes/<muted_user_id>")]
+pub async fn unmute_user(user: &User, db: Database, muted_user_id: i32) -> Negotiated<Protocol> {
+    let user_id = user.id;
+    let mutes = db
+        .run(move |con| {
+            delete(user_mutes::table)
+                .filter(user_mutes::user_id.eq(user_id))
+                .filter(user_mutes::muted_user_id.eq(muted_user_id))
+                .execute(con)?;
+
+            load_mutes(con, user_id)
+        })
+        .await;
+
+    mutes_response(user_id, mutes)
+}
@@ -1,9 +1,37 @@
-use super::{lobbies::LobbyWithUsers, polling::ActivePolls};
-use crate::{model::polling::Channel, schema::lobby_users, schema::users, Database, RunningGames};
-use argon2::{password_hash::SaltString, Argon2, PasswordHasher};
+use super::{
+    guard_failure, guard_failure_with,
+    lobbies::LobbyWithUsers,
+    negotiated::Negotiated,
+    polling::ActivePolls,
+    sessions::{bump_session, create_session, Session, SessionConfig},
+    user_bans::get_active_ban,
+    websocket,
+};
+use crate::{
+    game::DEFAULT_RATING,
+    model::polling::Channel,
+    schema::{lobby_users, sessions, users},
+    service::{
+        display_name_service::{self, DisplayNameRules},
+        game_service, websocket_service,
+    },
+    Database, RunningGames,
+};
+use argon2::{password_hash::SaltString, Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
 use chrono::NaiveDateTime;
-use diesel::{dsl::now, insert_into, prelude::*, update, ExpressionMethods, QueryDsl, RunQueryDsl};
-use protocol::protocol::{Credentials, Error, LoginResponse, Protocol, UserData};
+use diesel::{
+    delete,
+    dsl::now,
+    insert_into,
+    prelude::*,
+    result::{DatabaseErrorKind, Error as DieselError},
+    update, ExpressionMethods, QueryDsl, RunQueryDsl,
+};
+use protocol::protocol::{
+    BanInfo, Credentials, Error, ErrorCode, LeaderboardEntry, LoginResponse, Protocol, Role,
+    UserData,
+};
+use rand::Rng;
 use rand_core::OsRng;
 use rocket::{
     http::Status,
@@ -12,10 +40,21 @@ use rocket::{
     serde::json::Json,
     Request, State,
 };
-use serde::Deserialize;
+use static_init::dynamic;
 use std::fmt;
 use uuid::Uuid;
-use validator::Validate;
+
+const LEADERBOARD_SIZE: i64 = 100;
+/// Failed logins after which the account is locked
+const MAX_FAILED_LOGINS: i32 = 5;
+const LOCKOUT_MINUTES: i64 = 15;
+/// Attempts to find a free generated name before giving up on a guest login
+const GUEST_NAME_ATTEMPTS: usize = 5;
+
+/// Verified against for unknown usernames, so they take as long as wrong passwords
+#[dynamic]
+static DUMMY_HASH: String = hash_password("dummy password", &SaltString::generate(&mut OsRng))
+    .expect("Failed to hash the dummy password");
 
 #[derive(Identifiable, Queryable, Clone)]
 pub struct User {
@@ -26,10 +65,21 @@ pub struct User {
     pub display_name: Option<String>,
     pub currency: i32,
     pub tutorial: bool,
-    pub session_token: Option<Uuid>,
-    pub session_expires: Option<NaiveDateTime>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub rating: i32,
+    pub rated_games: i32,
+    pub failed_logins: i32,
+    pub locked_until: Option<NaiveDateTime>,
+    pub guest: bool,
+    pub display_name_key: Option<String>,
+    pub role: String,
+}
+
+impl User {
+    pub fn role(&self) -> Role {
+        self.role.parse().unwrap_or_default()
+    }
 }
 
 impl fmt::Debug for User {
@@ -43,10 +93,15 @@ impl fmt::Debug for User {
             .field("display_name", &self.display_name)
             .field("currency", &self.currency)
             .field("tutorial", &self.tutorial)
-            .field("session_token", &"[REDACTED]".to_string())
-            .field("session_expires", &self.session_expires)
             .field("created_at", &self.created_at)
             .field("updated_at", &self.updated_at)
+            .field("rating", &self.rating)
+            .field("rated_games", &self.rated_games)
+            .field("failed_logins", &self.failed_logins)
+            .field("locked_until", &self.locked_until)
+            .field("guest", &self.guest)
+            .field("display_name_key", &self.display_name_key)
+            .field("role", &self.role)
             .finish()
     }
 }
@@ -55,121 +110,282 @@ impl fmt::Debug for User {
 pub enum ApiKeyError {
     Missing,
     Invalid,
+    Banned(BanInfo),
     Other,
 }
 
+/// Identifies the session of a request
+enum SessionKey {
+    Token(Uuid),
+    /// Calls dispatched from a websocket of the session
+    Id(i32),
+}
+
+/// Resolves the session of the request once and caches it for the other guards
+pub(crate) async fn authenticate<'r>(
+    req: &'r Request<'_>,
+) -> &'r Result<(User, Session), ApiKeyError> {
+    req.local_cache_async(async {
+        // Browsers can not set headers on websocket connections
+        let key = req.headers().get_one("x-api-key").or_else(|| {
+            websocket::is_upgrade_request(req)
+                .then(|| websocket::protocol_key(req))
+                .flatten()
+        });
+        let key = match (websocket_service::dispatched_session(req), key) {
+            (Some(session_id), _) => SessionKey::Id(session_id),
+            (None, Some(key)) => match Uuid::parse_str(key) {
+                Ok(key) => SessionKey::Token(key),
+                Err(_) => return Err(ApiKeyError::Invalid),
+            },
+            (None, None) => return Err(ApiKeyError::Missing),
+        };
+        let Some(db) = req.guard::<Database>().await.succeeded() else {
+            return Err(ApiKeyError::Other);
+        };
+        let config = req
+            .rocket()
+            .state::<SessionConfig>()
+            .copied()
+            .unwrap_or_default();
+
+        db.run(move |con| {
+            let query = sessions::table
+                .inner_join(users::table)
+                .filter(sessions::expires_at.gt(now))
+                .into_boxed();
+            let query = match key {
+                SessionKey::Token(token) => query.filter(sessions::token.eq(token)),
+                SessionKey::Id(session_id) => query.filter(sessions::id.eq(session_id)),
+            };
+            let (session, user) = query
+                .first::<(Session, User)>(con)
+                .optional()
+                .map_err(|_| ApiKeyError::Other)?
+                .ok_or(ApiKeyError::Invalid)?;
+
+            if let Some(ban) = get_active_ban(con, user.id).map_err(|_| ApiKeyError::Other)? {
+                return Err(ApiKeyError::Banned(ban));
+            }
+
+            if config.needs_bump(&session) {
+                bump_session(con, session.id, &config).map_err(|_| ApiKeyError::Other)?;
+            }
+
+            trace!("Setting user {:?}", user);
+            Ok((user, session))
+        })
+        .await
+    })
+    .await
+}
+
+pub(crate) fn api_key_failure<S>(
+    req: &Request<'_>,
+    error: &ApiKeyError,
+) -> request::Outcome<S, ApiKeyError> {
+    let code = match error {
+        ApiKeyError::Missing => ErrorCode::Unauthorized,
+        ApiKeyError::Invalid => ErrorCode::InvalidSession,
+        ApiKeyError::Banned(ban) => {
+            return guard_failure_with(req, ban.clone().into(), error.clone());
+        }
+        ApiKeyError::Other => ErrorCode::Internal,
+    };
+    guard_failure(req, code, error.clone())
+}
+
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for &'r User {
     type Error = ApiKeyError;
     async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
-        let user = req
-            .local_cache_async(async {
-                match req.headers().get_one("x-api-key").map(|s| s.to_string()) {
-                    None => Err(ApiKeyError::Missing),
-                    Some(key) => {
-                        if let Some(db) = req.guard::<Database>().await.succeeded() {
-                            db.run(move |con| {
-                                let user = users::table
-                                    .filter(
-                                        users::session_token
-                                            .eq(Some(Uuid::parse_str(&key).unwrap()))
-                                            .and(users::session_expires.gt(now)),
-                                    )
-                                    .first::<User>(con)
-                                    .unwrap();
-
-                                update(users::table)
-                                    .filter(users::id.eq(user.id))
-                                    .set((users::session_expires.eq(Some(
-                                        chrono::Utc::now().naive_utc() + chrono::Duration::hours(1),
-                                    )),))
-                                    .execute(con)
-                                    .unwrap();
-
-                                trace!("Setting user {:?}", user);
-                                Ok(user)
-                            })
-                            .await
-                        } else {
-                            Err(ApiKeyError::Other)
-                        }
-                    }
-                }
-            })
-            .await;
-        match user {
-            Ok(user) => Outcome::Success(user),
-            Err(e) => Outcome::Failure((Status::Unauthorized, e.clone())),
+        match authenticate(req).await {
+            Ok((user, _)) => Outcome::Success(user),
+            Err(e) => api_key_failure(req, e),
         }
     }
 }
 
-#[derive(Insertable)]
+#[derive(Insertable, AsChangeset)]
 #[diesel(table_name = users)]
 pub struct NewUser {
     pub username: String,
     pub password: String,
     pub salt: String,
-    pub session_token: Option<Uuid>,
-    pub session_expires: Option<NaiveDateTime>,
 }
 
 impl NewUser {
-    fn from_credentials(cred: &Credentials, session_token: Uuid) -> Result<NewUser, ()> {
+    fn from_credentials(cred: &Credentials) -> Result<NewUser, ()> {
         let salt = SaltString::generate(&mut OsRng);
         let username: String = cred.username.clone();
 
         // Hash password to PHC string ($argon2id$v=19$...)
-        if let Ok(password_hash) = hash_password(cred, &salt) {
+        if let Ok(password_hash) = hash_password(&cred.password, &salt) {
             return Ok(NewUser {
                 username,
                 password: password_hash,
                 salt: salt.to_string(),
-                session_token: Some(session_token),
-                session_expires: Some(chrono::Utc::now().naive_utc() + chrono::Duration::hours(1)),
             });
         }
         Err(())
     }
 }
 
-fn hash_password(cred: &Credentials, salt: &SaltString) -> Result<String, ()> {
+#[derive(Insertable)]
+#[diesel(table_name = users)]
+struct NewGuest {
+    username: String,
+    password: String,
+    salt: String,
+    display_name: String,
+    display_name_key: String,
+    guest: bool,
+}
+
+impl NewGuest {
+    /// Guests get a random password nobody knows, so they can only use their session
+    fn generate() -> Result<NewGuest, ()> {
+        let salt = SaltString::generate(&mut OsRng);
+        let id = Uuid::new_v4().simple().to_string();
+        let display_name = guest_display_name(&mut rand::thread_rng());
+
+        Ok(NewGuest {
+            username: format!("guest_{}", &id[..16]),
+            password: hash_password(&Uuid::new_v4().to_string(), &salt)?,
+            salt: salt.to_string(),
+            display_name_key: display_name_service::normalize(&display_name),
+            display_name,
+            guest: true,
+        })
+    }
+}
+
+fn guest_display_name(rng: &mut impl Rng) -> String {
+    format!("Guest{:010}", rng.gen_range(0..10_000_000_000u64))
+}
+
+/// Deletes guests that can not log in anymore, because every refresh token
+/// expired, and are not seated in one of the `playing` games.
+pub fn delete_abandoned_guests(con: &mut PgConnection, playing: Vec<i32>) -> QueryResult<usize> {
+    let with_session = sessions::table
+        .filter(sessions::refresh_expires_at.gt(now))
+        .select(sessions::user_id);
+    delete(users::table)
+        .filter(users::guest.eq(true))
+        .filter(users::id.ne_all(with_session))
+        .filter(users::id.ne_all(playing))
+        .execute(con)
+}
+
+fn hash_password(password: &str, salt: &SaltString) -> Result<String, ()> {
     // Argon2 with default params (Argon2id v19)
     // Hash password to PHC string ($argon2id$v=19$...)
-    if let Ok(hash) = Argon2::default().hash_password(cred.password.as_bytes(), salt) {
+    if let Ok(hash) = Argon2::default().hash_password(password.as_bytes(), salt) {
         return Ok(hash.to_string());
     }
 
     Err(())
 }
 
-#[put("/users", data = "<creds>")]
-pub async fn register(creds: Json<Credentials>, db: Database) -> Json<Protocol> {
-    let session_token = Uuid::new_v4();
+/// Verifies `password` against a PHC string. The salt and params are read from the hash.
+fn verify_password(password: &str, hash: &str) -> bool {
+    let Ok(hash) = PasswordHash::new(hash) else {
+        warn!("Stored password hash is corrupted");
+        return false;
+    };
 
-    let new_user =
-        NewUser::from_credentials(&creds, session_token).expect("Failed to hash password");
+    Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .is_ok()
+}
+
+/// Counts a failed login and locks the account after `MAX_FAILED_LOGINS`
+async fn record_failed_login(db: &Database, user_id: i32) {
+    let result = db
+        .run(move |con| {
+            let failed_logins = update(users::table)
+                .filter(users::id.eq(user_id))
+                .set(users::failed_logins.eq(users::failed_logins + 1))
+                .returning(users::failed_logins)
+                .get_result::<i32>(con)?;
+
+            if failed_logins >= MAX_FAILED_LOGINS {
+                debug!(
+                    "Locking user {} after {} failed logins",
+                    user_id, failed_logins
+                );
+                update(users::table)
+                    .filter(users::id.eq(user_id))
+                    .set((
+                        users::failed_logins.eq(0),
+                        users::locked_until.eq(Some(
+                            chrono::Utc::now().naive_utc()
+                                + chrono::Duration::minutes(LOCKOUT_MINUTES),
+                        )),
+                    ))
+                    .execute(con)?;
+            }
+
+            Ok::<_, DieselError>(())
+        })
+        .await;
+
+    if let Err(e) = result {
+        warn!("Failed to record failed login of user {}: {:?}", user_id, e);
+    }
+}
+
+#[put("/users", data = "<creds>")]
+pub async fn register(
+    creds: Json<Credentials>,
+    db: Database,
+    config: &State<SessionConfig>,
+) -> Negotiated<Protocol> {
+    let Ok(new_user) = NewUser::from_credentials(&creds) else {
+        warn!("Failed to hash password");
+        return Negotiated(ErrorCode::Internal.into());
+    };
+    let config = *config.inner();
 
     // TODO: return new user
-    let user_id = db
-        .run(|con| {
-            insert_into(users::table)
-                .values(new_user)
-                .returning(users::id)
-                .get_result(con)
+    let (user_id, session) = match db
+        .run(move |con| {
+            con.transaction(|con| {
+                let user_id = insert_into(users::table)
+                    .values(new_user)
+                    .returning(users::id)
+                    .get_result(con)?;
+                let session = create_session(con, user_id, &config)?;
+
+                Ok::<_, DieselError>((user_id, session))
+            })
         })
         .await
-        .expect("Failed to create user");
+    {
+        Ok(result) => result,
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            return Negotiated(ErrorCode::UsernameTaken.into());
+        }
+        Err(e) => {
+            warn!("Failed to create user: {:?}", e);
+            return Negotiated(ErrorCode::Internal.into());
+        }
+    };
 
-    Json(Protocol::LoginResponse(LoginResponse {
-        key: session_token.to_string(),
+    Negotiated(Protocol::LoginResponse(LoginResponse {
+        key: session.token.to_string(),
+        refresh_key: session.refresh_token.to_string(),
         user: UserData {
             id: user_id,
             username: creds.username.clone(),
             display_name: None,
             currency: 0,
+            rating: DEFAULT_RATING,
             lobby: None,
+            guest: false,
+            role: Role::Player,
         },
+        game: None,
     }))
 }
 
@@ -178,49 +394,81 @@ pub async fn login(
     creds: Json<Credentials>,
     db: Database,
     games: &State<RunningGames>,
-) -> Json<Protocol> {
+    config: &State<SessionConfig>,
+) -> Negotiated<Protocol> {
     let username = creds.username.clone();
-    let user = db
+    let user = match db
         .run(move |con| {
             users::table
                 .filter(users::username.eq(username))
                 .first::<User>(con)
+                .optional()
         })
         .await
-        .expect("Failed to retrieve user from db");
-
-    assert_eq!(
-        hash_password(
-            &creds,
-            &SaltString::from_b64(user.salt.as_str()).expect("User salt currupted")
-        )
-        .expect("Failed to verify login"),
-        user.password
-    );
+    {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            verify_password(&creds.password, &DUMMY_HASH);
+            return Negotiated(ErrorCode::InvalidCredentials.into());
+        }
+        Err(e) => {
+            warn!("Failed to retrieve user from db: {:?}", e);
+            return Negotiated(ErrorCode::Internal.into());
+        }
+    };
 
-    let session_token = Uuid::new_v4();
-
-    db.run(move |con| {
-        update(users::table)
-            .filter(users::id.eq(user.id))
-            .set((
-                users::session_token.eq(Some(session_token)),
-                users::session_expires
-                    .eq(chrono::Utc::now().naive_utc() + chrono::Duration::hours(1)),
-            ))
-            .execute(con)
-    })
-    .await
-    .expect("Failed to update session token");
+    // The lock is only revealed to callers that know the password
+    let locked = user
+        .locked_until
+        .is_some_and(|until| until > chrono::Utc::now().naive_utc());
+    if !verify_password(&creds.password, &user.password) {
+        if !locked {
+            record_failed_login(&db, user.id).await;
+        }
+        return Negotiated(ErrorCode::InvalidCredentials.into());
+    }
+    if locked {
+        return Negotiated(ErrorCode::AccountLocked.into());
+    }
+
+    let user_id = user.id;
+    match db.run(move |con| get_active_ban(con, user_id)).await {
+        Ok(None) => {}
+        Ok(Some(ban)) => return Negotiated(Protocol::NetworkingError(ban.into())),
+        Err(e) => {
+            warn!("Failed to load bans of user {}: {:?}", user_id, e);
+            return Negotiated(ErrorCode::Internal.into());
+        }
+    }
 
-    let mut game = None;
+    let config = *config.inner();
+    let session = match db
+        .run(move |con| {
+            con.transaction(|con| {
+                update(users::table)
+                    .filter(users::id.eq(user_id))
+                    .set((
+                        users::failed_logins.eq(0),
+                        users::locked_until.eq(None::<NaiveDateTime>),
+                    ))
+                    .execute(con)?;
 
-    for (id, g) in games.games.lock().await.iter() {
-        if g.lock().await.has_user(user.id) {
-            debug!("User {:?} is in game {:?}", user.id, id);
-            game = Some(Channel::Game(*id));
-            break;
+                create_session(con, user_id, &config)
+            })
+        })
+        .await
+    {
+        Ok(session) => session,
+        Err(e) => {
+            warn!("Failed to create session: {:?}", e);
+            return Negotiated(ErrorCode::Internal.into());
         }
+    };
+
+    let game = game_service::get_user_game(games, user.id).await;
+    if let Some(game) = game {
+        debug!("User {:?} is in game {:?}", user.id, game);
+        game_service::resume_player(games, user.id).await;
     }
 
     let channels = vec![
@@ -233,71 +481,280 @@ pub async fn login(
                 .ok()
         })
         .await,
-        game,
+        game.map(Channel::Game),
     ];
 
-    ActivePolls::clear_user(user.id);
-
+    // Other devices of the user keep their polls and websockets
     for channel in channels.into_iter().flatten() {
         ActivePolls::join_user(channel, user.id);
     }
 
-    Json(Protocol::LoginResponse(LoginResponse {
-        key: session_token.to_string(),
+    let role = user.role();
+    Negotiated(Protocol::LoginResponse(LoginResponse {
+        key: session.token.to_string(),
+        refresh_key: session.refresh_token.to_string(),
         user: UserData {
             id: user.id,
             username: user.username,
             display_name: user.display_name,
             currency: user.currency,
+            rating: user.rating,
             lobby: None,
+            guest: user.guest,
+            role,
         },
+        game,
     }))
 }
 
+#[post("/users/guest")]
+pub async fn guest_login(db: Database, config: &State<SessionConfig>) -> Negotiated<Protocol> {
+    let config = *config.inner();
+
+    for _ in 0..GUEST_NAME_ATTEMPTS {
+        let Ok(guest) = NewGuest::generate() else {
+            warn!("Failed to hash password");
+            return Negotiated(ErrorCode::Internal.into());
+        };
+
+        match db
+            .run(move |con| {
+                con.transaction(|con| {
+                    let user = insert_into(users::table)
+                        .values(guest)
+                        .get_result::<User>(con)?;
+                    let session = create_session(con, user.id, &config)?;
+
+                    Ok::<_, DieselError>((user, session))
+                })
+            })
+            .await
+        {
+            Ok((user, session)) => {
+                debug!("Created guest {:?}", user);
+                return Negotiated(Protocol::LoginResponse(LoginResponse {
+                    key: session.token.to_string(),
+                    refresh_key: session.refresh_token.to_string(),
+                    user: UserData {
+                        id: user.id,
+                        username: user.username,
+                        display_name: user.display_name,
+                        currency: user.currency,
+                        rating: user.rating,
+                        lobby: None,
+                        guest: true,
+                        role: Role::Player,
+                    },
+                    game: None,
+                }));
+            }
+            Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => continue,
+            Err(e) => {
+                warn!("Failed to create guest: {:?}", e);
+                return Negotiated(ErrorCode::Internal.into());
+            }
+        }
+    }
+
+    warn!(
+        "No free guest name found after {} attempts",
+        GUEST_NAME_ATTEMPTS
+    );
+    Negotiated(ErrorCode::Internal.into())
+}
+
 #[get("/users/@me")]
-pub fn me(user: &User, lobby: Option<LobbyWithUsers>) -> Json<Protocol> {
-    Json(Protocol::UserResponse(UserData {
+pub fn me(user: &User, lobby: Option<LobbyWithUsers>) -> Negotiated<Protocol> {
+    Negotiated(Protocol::UserResponse(UserData {
         id: user.id,
         username: user.username.to_string(),
         display_name: user.display_name.clone(),
         currency: user.currency,
+        rating: user.rating,
         lobby: lobby.map(|l| l.into()),
+        guest: user.guest,
+        role: user.role(),
     }))
 }
 
-#[derive(Validate, Deserialize)]
-pub struct SetDisplayNameForm {
-    #[validate(length(min = 4, max = 32))]
-    display_name: String,
+/// Turns a guest into a regular account. Currency, history and rating stay
+/// with the user id, so only the credentials change.
+#[put("/users/@me/credentials", data = "<creds>")]
+pub async fn claim_account(
+    user: &User,
+    db: Database,
+    lobby: Option<LobbyWithUsers>,
+    creds: Json<Credentials>,
+) -> Negotiated<Protocol> {
+    let credentials = match claim_credentials(user, &creds) {
+        Ok(credentials) => credentials,
+        Err(code) => return Negotiated(code.into()),
+    };
+
+    let user_id = user.id;
+    match db
+        .run(move |con| {
+            update(users::table)
+                .filter(users::id.eq(user_id))
+                .filter(users::guest.eq(true))
+                .set((credentials, users::guest.eq(false)))
+                .get_result::<User>(con)
+                .optional()
+        })
+        .await
+    {
+        Ok(Some(user)) => Negotiated(Protocol::UserResponse(UserData {
+            id: user.id,
+            role: user.role(),
+            username: user.username,
+            display_name: user.display_name,
+            currency: user.currency,
+            rating: user.rating,
+            lobby: lobby.map(|l| l.into()),
+            guest: user.guest,
+        })),
+        Ok(None) => Negotiated(ErrorCode::AlreadyClaimed.into()),
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            Negotiated(ErrorCode::UsernameTaken.into())
+        }
+        Err(e) => {
+            warn!("Failed to claim guest {}: {:?}", user_id, e);
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
+
+/// The credentials replacing the generated ones of a guest
+fn claim_credentials(user: &User, creds: &Credentials) -> Result<NewUser, ErrorCode> {
+    if !user.guest {
+        return Err(ErrorCode::AlreadyClaimed);
+    }
+    NewUser::from_credentials(creds).map_err(|_| {
+        warn!("Failed to hash password");
+        ErrorCode::Internal
+    })
 }
 
 #[put("/users/display_name", data = "<display_name>")]
 pub async fn set_display_name(
     db: Database,
     user: &User,
+    rules: &State<DisplayNameRules>,
     display_name: Json<String>,
-) -> Json<Protocol> {
-    let display_name = SetDisplayNameForm {
-        display_name: display_name.0.trim().to_string(),
+) -> Negotiated<Protocol> {
+    let display_name = match rules.validate(&display_name) {
+        Ok(display_name) => display_name,
+        Err(code) => return Negotiated(code.into()),
     };
-    display_name.validate().unwrap();
 
     let user_id = user.id;
-    if let Ok(display_name) = db
+    match db
         .run(move |con| {
             diesel::update(users::table.filter(users::id.eq(user_id)))
-                .set(users::display_name.eq(&display_name.display_name))
+                .set((
+                    users::display_name.eq(&display_name.name),
+                    users::display_name_key.eq(&display_name.key),
+                ))
                 .execute(con)?;
 
             QueryResult::Ok(display_name)
         })
         .await
     {
-        Json(Protocol::DisplaynameResponse(display_name.display_name))
-    } else {
-        Json(Error::new_protocol(
+        Ok(display_name) => Negotiated(Protocol::DisplaynameResponse(display_name.name)),
+        Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+            Negotiated(ErrorCode::DisplayNameTaken.into())
+        }
+        Err(e) => {
+            warn!("Failed to update display name of user {}: {:?}", user_id, e);
+            Negotiated(ErrorCode::Internal.into())
+        }
+    }
+}
+
+#[get("/users/leaderboard")]
+pub async fn leaderboard(db: Database) -> Negotiated<Protocol> {
+    let entries = db
+        .run(|con| {
+            users::table
+                .filter(users::rated_games.gt(0))
+                .filter(users::display_name.is_not_null())
+                .order((users::rating.desc(), users::rated_games.desc()))
+                .select((users::display_name, users::rating, users::rated_games))
+                .limit(LEADERBOARD_SIZE)
+                .load::<(Option<String>, i32, i32)>(con)
+        })
+        .await;
+
+    match entries {
+        Ok(entries) => Negotiated(Protocol::LeaderboardResponse(
+            entries
+                .into_iter()
+                .enumerate()
+                .map(|(idx, (name, rating, games))| LeaderboardEntry {
+                    rank: idx as u32 + 1,
+                    name: name.unwrap_or_default(),
+                    rating,
+                    games,
+                })
+                .collect(),
+        )),
+        Err(_) => Negotiated(Error::new_protocol(
             Status::InternalServerError.code,
-            "Failed to update display name".to_string(),
-        ))
+            "Failed to load leaderboard".to_string(),
+        )),
     }
 }
+
+#[test]
+fn test_verify_password() {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = hash_password("correct horse", &salt).unwrap();
+
+    assert!(verify_password("correct horse", &hash));
+    assert!(!verify_password("wrong horse", &hash));
+    assert!(!verify_password("correct horse", "not a phc string"));
+    assert!(!verify_password("correct horse", &DUMMY_HASH));
+}
+
+#[test]
+fn test_claim_guest() {
+    let guest = NewGuest::generate().unwrap();
+    assert!(guest.display_name.starts_with("Guest"));
+    let created = chrono::Utc::now().naive_utc();
+    let mut user = User {
+        id: 1,
+        username: guest.username,
+        password: guest.password,
+        salt: guest.salt,
+        display_name: Some(guest.display_name),
+        currency: 0,
+        tutorial: false,
+        created_at: created,
+        updated_at: created,
+        rating: DEFAULT_RATING,
+        rated_games: 0,
+        failed_logins: 0,
+        locked_until: None,
+        guest: guest.guest,
+        display_name_key: Some(guest.display_name_key),
+        role: Role::Player.as_str().to_string(),
+    };
+    let creds = Credentials {
+        username: "claimed".to_string(),
+        password: "correct horse".to_string(),
+    };
+
+    let claimed = claim_credentials(&user, &creds).unwrap();
+    assert_eq!(claimed.username, "claimed");
+    assert_ne!(claimed.salt, user.salt);
+    assert!(verify_password("correct horse", &claimed.password));
+    assert!(!verify_password("correct horse", &user.password));
+
+    // Only guests can be claimed, and only once
+    user.guest = false;
+    assert!(matches!(
+        claim_credentials(&user, &creds),
+        Err(ErrorCode::AlreadyClaimed)
+    ));
+}
@@ -0,0 +1,274 @@
+use std::{io, pin::Pin};
+
+use futures::{SinkExt, StreamExt};
+use protocol::{
+    codec::{CodecError, Encoding},
+    protocol::{Error, EventBatch, Protocol},
+};
+use rocket::{
+    data::{IoHandler, IoStream},
+    http::Status,
+    request::{FromRequest, Outcome},
+    response::{self, Responder},
+    tokio::sync::mpsc,
+    Request, Response, State,
+};
+use tokio_tungstenite::{
+    tungstenite::{handshake::derive_accept_key, protocol::Role, Message},
+    WebSocketStream,
+};
+
+use super::{
+    polling::{get_event_batch, ActivePolls, LazyDatabase, Subscription},
+    sessions::Session,
+    users::User,
+};
+use crate::{
+    service::{game_service, websocket_service::ApiDispatcher},
+    RunningGames,
+};
+
+/// Subprotocol selected if the client offers it
+const PROTOCOL: &str = "rog.v1";
+/// Browsers can not set headers on websockets, so they offer the session key
+/// as an additional subprotocol `key.<session key>`
+const KEY_PROTOCOL_PREFIX: &str = "key.";
+/// Api calls answered but not yet sent
+const PENDING_REPLIES: usize = 16;
+
+/// Handshake information of a websocket upgrade request
+pub struct WebSocketUpgrade {
+    key: String,
+    /// Whether the client offered `PROTOCOL`
+    protocol: bool,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WebSocketUpgrade {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if !is_upgrade_request(req) {
+            return Outcome::Error((Status::UpgradeRequired, ()));
+        }
+
+        match req.headers().get_one("Sec-WebSocket-Key") {
+            Some(key) => Outcome::Success(WebSocketUpgrade {
+                key: key.to_string(),
+                protocol: protocols(req).any(|protocol| protocol == PROTOCOL),
+            }),
+            None => Outcome::Error((Status::BadRequest, ())),
+        }
+    }
+}
+
+pub fn is_upgrade_request(req: &Request<'_>) -> bool {
+    req.headers()
+        .get_one("Upgrade")
+        .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+}
+
+fn protocols<'r>(req: &'r Request<'_>) -> impl Iterator<Item = &'r str> {
+    req.headers()
+        .get("Sec-WebSocket-Protocol")
+        .flat_map(|protocols| protocols.split(','))
+        .map(str::trim)
+}
+
+/// Session key offered in the `Sec-WebSocket-Protocol` header
+pub fn protocol_key<'r>(req: &'r Request<'_>) -> Option<&'r str> {
+    protocols(req).find_map(|protocol| protocol.strip_prefix(KEY_PROTOCOL_PREFIX))
+}
+
+/// Streams the notifications of a user over a websocket connection
+pub struct EventStream {
+    accept: String,
+    protocol: bool,
+    user_id: i32,
+    session_id: i32,
+    signal: Subscription,
+    batch: EventBatch,
+    encoding: Encoding,
+    dispatcher: ApiDispatcher,
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for EventStream {
+    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = Response::build();
+        response
+            .status(Status::SwitchingProtocols)
+            .raw_header("Connection", "Upgrade")
+            .raw_header("Sec-WebSocket-Accept", self.accept.clone());
+        // Browsers close the connection if the selected protocol was not offered
+        if self.protocol {
+            response.raw_header("Sec-WebSocket-Protocol", PROTOCOL);
+        }
+        response.upgrade("websocket", self).ok()
+    }
+}
+
+#[rocket::async_trait]
+impl IoHandler for EventStream {
+    async fn io(self: Pin<Box<Self>>, io: IoStream) -> io::Result<()> {
+        let EventStream {
+            user_id,
+            session_id,
+            mut signal,
+            batch,
+            encoding,
+            dispatcher,
+            ..
+        } = *Pin::into_inner(self);
+        let (replies, mut pending_replies) = mpsc::channel(PENDING_REPLIES);
+        let (mut sink, mut stream) = WebSocketStream::from_raw_socket(io, Role::Server, None)
+            .await
+            .split();
+
+        debug!("Websocket opened for user {}", user_id);
+
+        let mut sent_seq = batch.last_seq;
+        let mut batch = Some(batch);
+        loop {
+            if let Some(batch) = batch.take() {
+                let message = encode_message(encoding, &Protocol::EventBatchResponse(batch))?;
+                if sink.send(message).await.is_err() {
+                    break;
+                }
+            }
+
+            rocket::tokio::select! {
+                changed = signal.changed() => {
+                    if changed.is_err() {
+                        // Session logged out or the events were discarded
+                        break;
+                    }
+
+                    match ActivePolls::events_since(user_id, Some(sent_seq)) {
+                        Ok((events, last_seq)) => {
+                            if !events.is_empty() {
+                                sent_seq = last_seq;
+                                batch = Some(EventBatch {
+                                    snapshot: None,
+                                    events,
+                                    last_seq,
+                                });
+                            }
+                        }
+                        // Client resumes with a snapshot after reconnecting
+                        Err(_) => break,
+                    }
+                }
+                message = stream.next() => match message {
+                    Some(Ok(message @ (Message::Text(_) | Message::Binary(_)))) => {
+                        // Text frames are always json, binary frames msgpack
+                        let message = match message {
+                            Message::Text(text) => Encoding::Json.decode(text.as_bytes()),
+                            message => Encoding::MessagePack.decode(&message.into_data()),
+                        };
+                        let response = handle_message(user_id, session_id, message, &dispatcher, &replies);
+                        if let Some(response) = response {
+                            if sink.send(encode_message(encoding, &response)?).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    // Ping / Pong is handled by tungstenite
+                    Some(Ok(_)) => {}
+                },
+                Some(reply) = pending_replies.recv() => {
+                    if sink.send(encode_message(encoding, &reply)?).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        debug!("Websocket closed for user {}", user_id);
+        let _ = sink.close().await;
+
+        Ok(())
+    }
+}
+
+fn encode_message(encoding: Encoding, message: &Protocol) -> io::Result<Message> {
+    let data = encoding
+        .encode(message)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    Ok(match encoding {
+        Encoding::Json => Message::Text(String::from_utf8_lossy(&data).into_owned()),
+        Encoding::MessagePack => Message::Binary(data),
+    })
+}
+
+fn handle_message(
+    user_id: i32,
+    session_id: i32,
+    message: Result<Protocol, CodecError>,
+    dispatcher: &ApiDispatcher,
+    replies: &mpsc::Sender<Protocol>,
+) -> Option<Protocol> {
+    match message {
+        Ok(Protocol::EventAck(seq)) => {
+            ActivePolls::ack(user_id, seq);
+            None
+        }
+        Ok(Protocol::ApiCall(call)) => {
+            // Slow calls must not hold back the events
+            let dispatcher = dispatcher.clone();
+            let replies = replies.clone();
+            rocket::tokio::spawn(async move {
+                let _ = replies
+                    .send(dispatcher.dispatch(session_id, call).await)
+                    .await;
+            });
+            None
+        }
+        Ok(message) => {
+            trace!(
+                "Unsupported websocket message from {}: {:?}",
+                user_id,
+                message
+            );
+            Some(Error::new_protocol(
+                Status::BadRequest.code,
+                "Unsupported websocket message".to_string(),
+            ))
+        }
+        Err(e) => Some(Error::new_protocol(Status::BadRequest.code, e.to_string())),
+    }
+}
+
+/// Browsers can not set an `Accept` header on websockets, so the encoding can
+/// also be selected with the `encoding` query parameter
+#[allow(clippy::too_many_arguments)]
+#[get("/ws?<since>&<encoding>")]
+pub async fn connect(
+    user: &User,
+    session: &Session,
+    upgrade: WebSocketUpgrade,
+    dispatcher: &State<ApiDispatcher>,
+    since: Option<u64>,
+    encoding: Option<&str>,
+    db: LazyDatabase<'_>,
+    games: &State<RunningGames>,
+) -> EventStream {
+    let signal = ActivePolls::register(user.id, session.id);
+    if let Some(since) = since {
+        ActivePolls::ack(user.id, since);
+    }
+    game_service::resume_player(games, user.id).await;
+
+    EventStream {
+        accept: derive_accept_key(upgrade.key.as_bytes()),
+        protocol: upgrade.protocol,
+        user_id: user.id,
+        session_id: session.id,
+        signal,
+        batch: get_event_batch(&db, games, user.id, since).await,
+        encoding: encoding
+            .and_then(|encoding| encoding.parse().ok())
+            .unwrap_or(Encoding::Json),
+        dispatcher: dispatcher.inner().clone(),
+    }
+}
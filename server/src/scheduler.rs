@@ -1,8 +1,11 @@
 use crate::{
-    game::game_instance::GameInstance,
-    model::lobbies::Lobby,
+    game::game_instance::{GameInstance, GameSettings},
+    model::{lobbies::Lobby, polling::Channel, users},
     schema::lobbies,
-    service::{game_service, lobby_service},
+    service::{
+        chat_service, friend_service, game_service, lobby_service,
+        matchmaking_service::MatchmakingQueue,
+    },
     Database,
 };
 use diesel::{dsl::now, prelude::*, ExpressionMethods, QueryDsl};
@@ -15,8 +18,13 @@ use uuid::Uuid;
 
 type GameMap = HashMap<Uuid, Arc<Mutex<GameInstance>>>;
 
+/// Presence is derived every few ticks, as it has to look at all games
+const PRESENCE_UPDATE_TICKS: u64 = 5;
+/// Abandoned guests are deleted once an hour
+const GUEST_CLEANUP_TICKS: u64 = 60 * 60;
+
 pub async fn long_running_task(db: Database, games: &Arc<Mutex<GameMap>>) {
-    loop {
+    for tick in 0.. {
         // trace!("Long running task");
         if let Ok(lobbies) = db
             .run(move |con| {
@@ -28,11 +36,14 @@ pub async fn long_running_task(db: Database, games: &Arc<Mutex<GameMap>>) {
         {
             for lobby in lobbies {
                 debug!("Starting lobby {:?}", lobby);
-                let game = game_service::start_game(&db, &lobby).await;
+                let Some(game) = game_service::start_game(&db, &lobby).await else {
+                    continue;
+                };
                 games
                     .lock()
                     .await
                     .insert(game.game_id, Arc::new(Mutex::new(game)));
+                chat_service::clear_history(&Channel::Lobby(lobby.id));
                 db.run(move |con| {
                     lobby_service::close_lobby(con, &lobby);
                 })
@@ -42,6 +53,26 @@ pub async fn long_running_task(db: Database, games: &Arc<Mutex<GameMap>>) {
             warn!("Failed to load lobbies to start")
         }
 
+        let matches = MatchmakingQueue::take_matches();
+        if !matches.is_empty() {
+            for players in matches {
+                debug!("Starting matchmaking game for {:?}", players);
+                let game = game_service::create_game(
+                    players
+                        .into_iter()
+                        .map(|entry| (entry.user_id, entry.display_name, entry.rating))
+                        .collect(),
+                    GameSettings::default(),
+                )
+                .await;
+                games
+                    .lock()
+                    .await
+                    .insert(game.game_id, Arc::new(Mutex::new(game)));
+            }
+            MatchmakingQueue::notify_users().await;
+        }
+
         // This is needed to avoid locking the games mutex for too long
         {
             let mut games = games.lock().await;
@@ -54,7 +85,7 @@ pub async fn long_running_task(db: Database, games: &Arc<Mutex<GameMap>>) {
                 let game_id = game.lock().await.game_id;
                 debug!("Next turn for game {:?}", game_id);
 
-                let game_ended = game_service::next_turn(game.lock().await.borrow_mut()).await;
+                let game_ended = game_service::next_turn(&db, game.lock().await.borrow_mut()).await;
                 if game_ended {
                     ended_games.push(game_id);
                 }
@@ -70,6 +101,25 @@ pub async fn long_running_task(db: Database, games: &Arc<Mutex<GameMap>>) {
             }
         }
 
+        if tick % PRESENCE_UPDATE_TICKS == 0 {
+            friend_service::update_presences(&db, games).await;
+        }
+
+        if tick % GUEST_CLEANUP_TICKS == 0 {
+            let mut playing = vec![];
+            for game in games.lock().await.values() {
+                playing.extend(game.lock().await.players.iter().filter_map(|p| p.user_id));
+            }
+            match db
+                .run(move |con| users::delete_abandoned_guests(con, playing))
+                .await
+            {
+                Ok(0) => {}
+                Ok(deleted) => debug!("Deleted {} abandoned guests", deleted),
+                Err(e) => warn!("Failed to delete abandoned guests: {:?}", e),
+            }
+        }
+
         tokio::time::sleep(Duration::from_secs(1)).await;
     }
 }
@@ -1,5 +1,27 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    currency_transactions (id) {
+        id -> Int4,
+        user_id -> Int4,
+        amount -> Int4,
+        source -> Varchar,
+        game_id -> Nullable<Uuid>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    friendships (id) {
+        id -> Int4,
+        user_id -> Int4,
+        friend_id -> Int4,
+        accepted -> Bool,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     game_user_avatar_choices (id) {
         id -> Int4,
@@ -60,6 +82,32 @@ diesel::table! {
         start_at -> Nullable<Timestamp>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        max_players -> Int4,
+        bot_fill -> Bool,
+        rules -> Varchar,
+        rated -> Bool,
+    }
+}
+
+diesel::table! {
+    lobby_bans (id) {
+        id -> Int4,
+        lobby_id -> Int4,
+        user_id -> Int4,
+        expires_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    lobby_invites (id) {
+        id -> Int4,
+        lobby_id -> Int4,
+        user_id -> Int4,
+        inviter_id -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -75,6 +123,19 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    sessions (id) {
+        id -> Int4,
+        user_id -> Int4,
+        token -> Uuid,
+        refresh_token -> Uuid,
+        expires_at -> Timestamp,
+        refresh_expires_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     shops (id) {
         id -> Int4,
@@ -87,6 +148,28 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    user_bans (id) {
+        id -> Int4,
+        user_id -> Int4,
+        issued_by -> Nullable<Int4>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        reason -> Varchar,
+        expires_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    user_mutes (id) {
+        id -> Int4,
+        user_id -> Int4,
+        muted_user_id -> Int4,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Int4,
@@ -96,23 +179,41 @@ diesel::table! {
         display_name -> Nullable<Varchar>,
         currency -> Int4,
         tutorial -> Bool,
-        session_token -> Nullable<Uuid>,
-        session_expires -> Nullable<Timestamp>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        rating -> Int4,
+        rated_games -> Int4,
+        failed_logins -> Int4,
+        locked_until -> Nullable<Timestamp>,
+        guest -> Bool,
+        display_name_key -> Nullable<Varchar>,
+        role -> Varchar,
     }
 }
 
+diesel::joinable!(currency_transactions -> users (user_id));
 diesel::joinable!(lobbies -> users (master_id));
+diesel::joinable!(lobby_bans -> lobbies (lobby_id));
+diesel::joinable!(lobby_bans -> users (user_id));
+diesel::joinable!(lobby_invites -> lobbies (lobby_id));
 diesel::joinable!(lobby_users -> lobbies (lobby_id));
+diesel::joinable!(lobby_users -> users (user_id));
+diesel::joinable!(sessions -> users (user_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    currency_transactions,
+    friendships,
     game_user_avatar_choices,
     game_user_characters,
     game_users,
     games,
     lobbies,
+    lobby_bans,
+    lobby_invites,
     lobby_users,
+    sessions,
     shops,
+    user_bans,
+    user_mutes,
     users,
 );
@@ -0,0 +1,138 @@
+use crate::{
+    model::polling::{ActivePolls, Channel},
+    schema::user_mutes,
+    Database,
+};
+use diesel::prelude::*;
+use protocol::protocol::{ChatMessage, ErrorCode, Protocol, MAX_CHAT_MESSAGE_LENGTH};
+use static_init::dynamic;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Mutex,
+};
+
+/// Messages kept per channel for players joining later
+const HISTORY_SIZE: usize = 50;
+
+#[dynamic]
+static HISTORY: Mutex<HashMap<Channel, VecDeque<ChatMessage>>> = Mutex::new(HashMap::new());
+
+/// Trims the message and checks its length. Control characters are not
+/// allowed, so messages stay on a single line.
+pub fn validate_message(text: &str) -> Result<String, ErrorCode> {
+    let text = text.trim();
+    let length = text.chars().count();
+    if length == 0 || length > MAX_CHAT_MESSAGE_LENGTH || text.chars().any(char::is_control) {
+        return Err(ErrorCode::InvalidChatMessage);
+    }
+
+    Ok(text.to_string())
+}
+
+/// Recent messages of the channel without the ones from muted users
+pub fn get_history(channel: &Channel, muted: &HashSet<i32>) -> Vec<ChatMessage> {
+    HISTORY
+        .lock()
+        .unwrap()
+        .get(channel)
+        .map(|history| {
+            history
+                .iter()
+                .filter(|message| !muted.contains(&message.from_id))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn push_history(channel: Channel, message: ChatMessage) {
+    let mut history = HISTORY.lock().unwrap();
+    let messages = history.entry(channel).or_default();
+    messages.push_back(message);
+    while messages.len() > HISTORY_SIZE {
+        messages.pop_front();
+    }
+}
+
+pub fn clear_history(channel: &Channel) {
+    HISTORY.lock().unwrap().remove(channel);
+}
+
+pub fn get_muted_users(con: &mut PgConnection, user_id: i32) -> QueryResult<HashSet<i32>> {
+    user_mutes::table
+        .filter(user_mutes::user_id.eq(user_id))
+        .select(user_mutes::muted_user_id)
+        .load::<i32>(con)
+        .map(|muted| muted.into_iter().collect())
+}
+
+/// Stores the message and delivers it to the recipients that did not mute the sender
+pub async fn send_message(
+    db: &Database,
+    channel: Channel,
+    recipients: Vec<i32>,
+    message: ChatMessage,
+) -> QueryResult<()> {
+    let sender = message.from_id;
+    let muted_by = db
+        .run(move |con| {
+            user_mutes::table
+                .filter(user_mutes::muted_user_id.eq(sender))
+                .select(user_mutes::user_id)
+                .load::<i32>(con)
+        })
+        .await?;
+
+    push_history(channel, message.clone());
+
+    for recipient in recipients
+        .into_iter()
+        .filter(|recipient| *recipient != sender && !muted_by.contains(recipient))
+    {
+        ActivePolls::notify(recipient, Protocol::ChatMessageResponse(message.clone())).await;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_message() {
+    assert_eq!(validate_message("  hello  "), Ok("hello".to_string()));
+    assert_eq!(validate_message("   "), Err(ErrorCode::InvalidChatMessage));
+    assert_eq!(validate_message("a\nb"), Err(ErrorCode::InvalidChatMessage));
+    assert_eq!(
+        validate_message("a\u{7}b"),
+        Err(ErrorCode::InvalidChatMessage)
+    );
+    assert!(validate_message(&"ä".repeat(MAX_CHAT_MESSAGE_LENGTH)).is_ok());
+    assert_eq!(
+        validate_message(&"a".repeat(MAX_CHAT_MESSAGE_LENGTH + 1)),
+        Err(ErrorCode::InvalidChatMessage)
+    );
+}
+
+#[test]
+fn test_history() {
+    let channel = Channel::Lobby(-1);
+    let message = |from_id: i32, text: &str| ChatMessage {
+        channel: protocol::protocol::ChatChannel::Lobby,
+        from_id,
+        from: format!("Player{}", from_id),
+        text: text.to_string(),
+        sent_at: chrono::Utc::now(),
+    };
+
+    for i in 0..HISTORY_SIZE + 5 {
+        push_history(channel, message(1 + i as i32 % 2, &i.to_string()));
+    }
+
+    let history = get_history(&channel, &HashSet::new());
+    assert_eq!(history.len(), HISTORY_SIZE);
+    assert_eq!(history[0].text, "5");
+    assert!(get_history(&channel, &HashSet::from([1]))
+        .iter()
+        .all(|message| message.from_id == 2));
+
+    clear_history(&channel);
+    assert!(get_history(&channel, &HashSet::new()).is_empty());
+}
@@ -0,0 +1,38 @@
+use crate::{
+    game::PLACEMENT_REWARDS,
+    model::currency_transactions::{NewCurrencyTransaction, TransactionSource},
+    schema::{currency_transactions, users},
+};
+use diesel::{insert_into, prelude::*, update};
+use uuid::Uuid;
+
+pub fn get_reward(placement: u8) -> i32 {
+    PLACEMENT_REWARDS
+        .get((placement as usize).saturating_sub(1))
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Records the transaction in the ledger and updates the users balance.
+/// Returns the new balance.
+pub fn credit(
+    con: &mut PgConnection,
+    user_id: i32,
+    amount: i32,
+    source: TransactionSource,
+    game_id: Option<Uuid>,
+) -> QueryResult<i32> {
+    con.transaction(|con| {
+        insert_into(currency_transactions::table)
+            .values(NewCurrencyTransaction::new(
+                user_id, amount, source, game_id,
+            ))
+            .execute(con)?;
+
+        update(users::table)
+            .filter(users::id.eq(user_id))
+            .set(users::currency.eq(users::currency + amount))
+            .returning(users::currency)
+            .get_result(con)
+    })
+}
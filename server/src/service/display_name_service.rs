@@ -0,0 +1,333 @@
+use std::{env, fs};
+
+use crate::schema::users;
+use diesel::{
+    prelude::*,
+    result::{DatabaseErrorKind, Error as DieselError},
+    update,
+};
+use protocol::protocol::ErrorCode;
+use rocket::log::private::warn;
+use validator::{Validate, ValidationError};
+
+pub const MIN_LENGTH: u64 = 4;
+pub const MAX_LENGTH: u64 = 32;
+
+/// Words nobody may use, matched against the normalized name. Extended with
+/// the file in `DISPLAY_NAME_DENY_LIST`.
+const DEFAULT_DENY_LIST: &str = include_str!("../../display_name_deny_list.txt");
+
+/// Decides whether a normalized display name may be used
+pub trait NameFilter: Send + Sync {
+    fn allows(&self, normalized: &str) -> bool;
+}
+
+/// Rejects names containing any of the words, unless every occurrence is part
+/// of an allowed word, e.g. `admin` in `badminton`
+pub struct DenyList {
+    words: Vec<String>,
+    allowed: Vec<String>,
+}
+
+impl DenyList {
+    /// Parses one word per line. Lines starting with `!` are allowed words.
+    /// Empty lines and lines starting with `#` are ignored.
+    pub fn parse(list: &str) -> Self {
+        let (allowed, words): (Vec<_>, Vec<_>) = list
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.strip_prefix('!') {
+                Some(word) => (true, normalize(word)),
+                None => (false, normalize(line)),
+            })
+            .filter(|(_, word)| !word.is_empty())
+            .partition(|(allowed, _)| *allowed);
+        Self {
+            words: words.into_iter().map(|(_, word)| word).collect(),
+            allowed: allowed.into_iter().map(|(_, word)| word).collect(),
+        }
+    }
+
+    fn is_allowed_at(&self, normalized: &str, start: usize, len: usize) -> bool {
+        self.allowed.iter().any(|allowed| {
+            occurrences(normalized, allowed)
+                .any(|idx| idx <= start && start + len <= idx + allowed.len())
+        })
+    }
+}
+
+impl NameFilter for DenyList {
+    fn allows(&self, normalized: &str) -> bool {
+        self.words.iter().all(|word| {
+            occurrences(normalized, word).all(|idx| self.is_allowed_at(normalized, idx, word.len()))
+        })
+    }
+}
+
+/// Start of every occurrence of `needle`, including overlapping ones
+fn occurrences<'a>(haystack: &'a str, needle: &'a str) -> impl Iterator<Item = usize> + 'a {
+    haystack
+        .char_indices()
+        .map(|(idx, _)| idx)
+        .filter(move |idx| haystack[*idx..].starts_with(needle))
+}
+
+/// A display name that passed all rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayName {
+    pub name: String,
+    /// Normalized form used for uniqueness, so lookalike names collide
+    pub key: String,
+}
+
+pub struct DisplayNameRules {
+    filters: Vec<Box<dyn NameFilter>>,
+}
+
+impl Default for DisplayNameRules {
+    fn default() -> Self {
+        Self::new().with_filter(DenyList::parse(DEFAULT_DENY_LIST))
+    }
+}
+
+impl DisplayNameRules {
+    /// Rules without any filters
+    pub fn new() -> Self {
+        Self { filters: vec![] }
+    }
+
+    pub fn from_env() -> Self {
+        let rules = Self::default();
+        match env::var("DISPLAY_NAME_DENY_LIST") {
+            Ok(path) => {
+                let list = fs::read_to_string(&path)
+                    .unwrap_or_else(|e| panic!("Failed to read deny list {}: {}", path, e));
+                rules.with_filter(DenyList::parse(&list))
+            }
+            Err(_) => rules,
+        }
+    }
+
+    pub fn with_filter(mut self, filter: impl NameFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub fn validate(&self, name: &str) -> Result<DisplayName, ErrorCode> {
+        let form = DisplayNameForm {
+            display_name: name.trim(),
+        };
+        if let Err(errors) = form.validate() {
+            let length_error = errors
+                .field_errors()
+                .get("display_name")
+                .is_some_and(|errors| errors.iter().any(|e| e.code == "length"));
+            return Err(if !length_error {
+                ErrorCode::InvalidDisplayName
+            } else if (form.display_name.chars().count() as u64) < MIN_LENGTH {
+                ErrorCode::DisplayNameTooShort
+            } else {
+                ErrorCode::DisplayNameTooLong
+            });
+        }
+
+        let key = normalize(form.display_name);
+        if !self.filters.iter().all(|filter| filter.allows(&key)) {
+            return Err(ErrorCode::DisplayNameNotAllowed);
+        }
+
+        Ok(DisplayName {
+            name: form.display_name.to_string(),
+            key,
+        })
+    }
+}
+
+#[derive(Validate)]
+struct DisplayNameForm<'a> {
+    #[validate(
+        length(min = "MIN_LENGTH", max = "MAX_LENGTH"),
+        custom = "validate_characters"
+    )]
+    display_name: &'a str,
+}
+
+/// Letters and digits separated by single spaces, `-` or `_`, starting with a letter
+fn validate_characters(name: &str) -> Result<(), ValidationError> {
+    let starts_with_letter = name.chars().next().is_some_and(char::is_alphabetic);
+    let allowed = name.chars().all(|c| c.is_alphanumeric() || is_separator(c));
+    let single_separators = !name
+        .chars()
+        .zip(name.chars().skip(1))
+        .any(|(a, b)| is_separator(a) && is_separator(b));
+    let ends_with_separator = name.chars().last().is_some_and(is_separator);
+
+    if starts_with_letter && allowed && single_separators && !ends_with_separator {
+        Ok(())
+    } else {
+        Err(ValidationError::new("characters"))
+    }
+}
+
+fn is_separator(c: char) -> bool {
+    matches!(c, ' ' | '-' | '_')
+}
+
+/// Maps a name to a skeleton where lookalike characters are the same, e.g.
+/// `Pl4yer_0ne`, `player one` and `plаyerone` with a cyrillic `а` all become `playerone`.
+pub fn normalize(name: &str) -> String {
+    name.chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !is_separator(*c) && *c != '.')
+        .map(|c| match c {
+            '0' | 'ο' | 'о' | 'ò' | 'ó' | 'ô' | 'õ' | 'ö' => 'o',
+            '1' | 'i' | 'ι' | 'і' | 'ì' | 'í' | 'î' | 'ï' | '|' => 'l',
+            '3' | 'е' | 'è' | 'é' | 'ê' | 'ë' => 'e',
+            '4' | '@' | 'а' | 'α' | 'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+            '5' | '$' | 'ѕ' => 's',
+            '7' | 'τ' => 't',
+            '8' | 'β' => 'b',
+            'р' | 'ρ' => 'p',
+            'с' | 'ç' => 'c',
+            'у' | 'ý' | 'ÿ' => 'y',
+            'х' | 'χ' => 'x',
+            'к' | 'κ' => 'k',
+            'ν' => 'v',
+            'ј' => 'j',
+            'ԁ' => 'd',
+            'ɡ' => 'g',
+            'ñ' => 'n',
+            'ù' | 'ú' | 'û' | 'ü' => 'u',
+            c => c,
+        })
+        .collect::<String>()
+        .replace("rn", "m")
+        .replace("vv", "w")
+}
+
+/// Normalizes keys that still hold the plain display name, like the ones
+/// copied when the column was added. Keys that would collide are kept.
+pub fn normalize_keys(con: &mut PgConnection) -> QueryResult<usize> {
+    let names = users::table
+        .filter(users::display_name_key.eq(users::display_name))
+        .select((users::id, users::display_name))
+        .load::<(i32, Option<String>)>(con)?;
+
+    let mut updated = 0;
+    for (user_id, name) in names {
+        let Some(name) = name else { continue };
+        let key = normalize(&name);
+        if key == name {
+            continue;
+        }
+        match update(users::table.find(user_id))
+            .set(users::display_name_key.eq(&key))
+            .execute(con)
+        {
+            Ok(_) => updated += 1,
+            Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => warn!(
+                "Display name key {} of user {} is taken, keeping {}",
+                key, user_id, name
+            ),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(updated)
+}
+
+#[test]
+fn test_normalize() {
+    assert_eq!(normalize("Player One"), "playerone");
+    assert_eq!(normalize("Pl4yer_0ne"), "playerone");
+    assert_eq!(normalize("plаyer-one"), "playerone");
+    assert_eq!(normalize("PLAYER1"), normalize("playerl"));
+    assert_eq!(normalize("Ilse"), normalize("1lse"));
+    assert_eq!(normalize("modern"), normalize("modem"));
+    assert_ne!(normalize("Player2"), normalize("Player3"));
+}
+
+#[test]
+fn test_validate() {
+    let rules = DisplayNameRules::new().with_filter(DenyList::parse("# comment\n\nbadword\n"));
+
+    assert_eq!(
+        rules.validate("  Player One "),
+        Ok(DisplayName {
+            name: "Player One".to_string(),
+            key: "playerone".to_string(),
+        })
+    );
+    assert!(rules.validate("Jörg_der-Große").is_ok());
+    assert_eq!(rules.validate("abc"), Err(ErrorCode::DisplayNameTooShort));
+    assert_eq!(rules.validate("äöü"), Err(ErrorCode::DisplayNameTooShort));
+    assert_eq!(
+        rules.validate(&"a".repeat(33)),
+        Err(ErrorCode::DisplayNameTooLong)
+    );
+    assert_eq!(
+        rules.validate("1Player"),
+        Err(ErrorCode::InvalidDisplayName)
+    );
+    assert_eq!(
+        rules.validate("Player  One"),
+        Err(ErrorCode::InvalidDisplayName)
+    );
+    assert_eq!(
+        rules.validate("Player_"),
+        Err(ErrorCode::InvalidDisplayName)
+    );
+    assert_eq!(
+        rules.validate("Player!"),
+        Err(ErrorCode::InvalidDisplayName)
+    );
+    assert_eq!(
+        rules.validate("Play\u{200b}er"),
+        Err(ErrorCode::InvalidDisplayName)
+    );
+    assert_eq!(
+        rules.validate("xXBadWordXx"),
+        Err(ErrorCode::DisplayNameNotAllowed)
+    );
+    assert_eq!(
+        rules.validate("the b4d_w0rd"),
+        Err(ErrorCode::DisplayNameNotAllowed)
+    );
+}
+
+#[test]
+fn test_deny_list_allowed_words() {
+    let list = DenyList::parse("badword\n!superbadwords\n");
+
+    assert!(list.allows(&normalize("SuperBadWords")));
+    assert!(list.allows(&normalize("my superbadwords")));
+    assert!(!list.allows(&normalize("SuperBadWord")));
+    assert!(!list.allows(&normalize("BadWordSuperBadWords")));
+
+    // Overlapping occurrences are checked on their own
+    let list = DenyList::parse("lol\n!xlol\n");
+    assert!(list.allows(&normalize("xlol")));
+    assert!(!list.allows(&normalize("xlolol")));
+}
+
+#[test]
+fn test_default_deny_list() {
+    let rules = DisplayNameRules::default();
+
+    assert_eq!(
+        rules.validate("Admin"),
+        Err(ErrorCode::DisplayNameNotAllowed)
+    );
+    assert_eq!(
+        rules.validate("M0derator"),
+        Err(ErrorCode::DisplayNameNotAllowed)
+    );
+    assert!(rules.validate("Player One").is_ok());
+    assert!(rules.validate("Badminton").is_ok());
+    assert!(rules.validate("Ecosystem").is_ok());
+    assert!(rules.validate("Yoshitaka").is_ok());
+    assert_eq!(
+        rules.validate("BadmintonAdmin"),
+        Err(ErrorCode::DisplayNameNotAllowed)
+    );
+}
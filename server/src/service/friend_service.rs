@@ -0,0 +1,237 @@
+use crate::{
+    game::game_instance::GameInstance,
+    model::polling::ActivePolls,
+    schema::{friendships, users},
+    Database,
+};
+use diesel::prelude::*;
+use protocol::protocol::{Friend, FriendPresence, FriendStatus, Presence, Protocol};
+use rocket::tokio::sync::Mutex as AsyncMutex;
+use static_init::dynamic;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+use uuid::Uuid;
+
+/// Presence of the online users as last pushed to their friends
+#[dynamic]
+static PRESENCES: Mutex<HashMap<i32, Presence>> = Mutex::new(HashMap::new());
+
+pub fn get_presence(user_id: i32) -> Presence {
+    PRESENCES
+        .lock()
+        .unwrap()
+        .get(&user_id)
+        .copied()
+        .unwrap_or_default()
+}
+
+fn derive_presence(
+    user_id: i32,
+    lobby_users: &HashSet<i32>,
+    game_users: &HashSet<i32>,
+) -> Presence {
+    if game_users.contains(&user_id) {
+        Presence::Game
+    } else if lobby_users.contains(&user_id) {
+        Presence::Lobby
+    } else {
+        Presence::Menu
+    }
+}
+
+/// Users whose presence differs between `previous` and `current`. Users
+/// missing from a map are offline.
+fn changed_presences(
+    previous: &HashMap<i32, Presence>,
+    current: &HashMap<i32, Presence>,
+) -> Vec<(i32, Presence)> {
+    let went_offline = previous
+        .keys()
+        .filter(|user| !current.contains_key(user))
+        .map(|user| (*user, Presence::Offline));
+    let changed = current
+        .iter()
+        .filter(|(user, presence)| previous.get(user) != Some(presence))
+        .map(|(user, presence)| (*user, *presence));
+
+    went_offline.chain(changed).collect()
+}
+
+/// Derives the presence of all users from their connections, lobbies and
+/// games and pushes changes to their online friends
+pub async fn update_presences(
+    db: &Database,
+    games: &AsyncMutex<HashMap<Uuid, Arc<AsyncMutex<GameInstance>>>>,
+) {
+    let mut game_users = HashSet::new();
+    for game in games.lock().await.values() {
+        let game = game.lock().await;
+        game_users.extend(
+            game.players
+                .iter()
+                .filter(|player| player.is_active())
+                .filter_map(|player| player.user_id),
+        );
+    }
+
+    let online = ActivePolls::online_users();
+    let lobby_users = ActivePolls::lobby_users();
+    let current = online
+        .iter()
+        .map(|user| (*user, derive_presence(*user, &lobby_users, &game_users)))
+        .collect::<HashMap<_, _>>();
+
+    let changed = {
+        let mut presences = PRESENCES.lock().unwrap();
+        let changed = changed_presences(&presences, &current);
+        *presences = current;
+        changed
+    };
+    if changed.is_empty() {
+        return;
+    }
+
+    let user_ids = changed.iter().map(|(user, _)| *user).collect::<Vec<_>>();
+    let friendships = match db
+        .run(move |con| {
+            friendships::table
+                .filter(friendships::accepted.eq(true))
+                .filter(
+                    friendships::user_id
+                        .eq_any(&user_ids)
+                        .or(friendships::friend_id.eq_any(&user_ids)),
+                )
+                .select((friendships::user_id, friendships::friend_id))
+                .load::<(i32, i32)>(con)
+        })
+        .await
+    {
+        Ok(friendships) => friendships,
+        Err(e) => {
+            warn!("Failed to load friends for presence updates: {:?}", e);
+            return;
+        }
+    };
+
+    for (user, presence) in changed {
+        trace!("Presence of user {} changed to {:?}", user, presence);
+        let friends = friendships.iter().filter_map(|&(a, b)| {
+            if a == user {
+                Some(b)
+            } else if b == user {
+                Some(a)
+            } else {
+                None
+            }
+        });
+        for friend in friends.filter(|friend| online.contains(friend)) {
+            ActivePolls::notify(
+                friend,
+                Protocol::FriendPresenceResponse(FriendPresence { id: user, presence }),
+            )
+            .await;
+        }
+    }
+}
+
+/// Friends and open requests of the user, friends first
+pub fn load_friends(con: &mut PgConnection, user_id: i32) -> QueryResult<Vec<Friend>> {
+    let outgoing = friendships::table
+        .inner_join(users::table.on(users::id.eq(friendships::friend_id)))
+        .filter(friendships::user_id.eq(user_id))
+        .select((users::id, users::display_name, friendships::accepted))
+        .load::<(i32, Option<String>, bool)>(con)?;
+    let incoming = friendships::table
+        .inner_join(users::table.on(users::id.eq(friendships::user_id)))
+        .filter(friendships::friend_id.eq(user_id))
+        .select((users::id, users::display_name, friendships::accepted))
+        .load::<(i32, Option<String>, bool)>(con)?;
+
+    let mut friends = outgoing
+        .into_iter()
+        .map(|(id, name, accepted)| (id, name, accepted, FriendStatus::Outgoing))
+        .chain(
+            incoming
+                .into_iter()
+                .map(|(id, name, accepted)| (id, name, accepted, FriendStatus::Incoming)),
+        )
+        .map(|(id, name, accepted, pending)| Friend {
+            id,
+            name: name.unwrap_or_default(),
+            status: if accepted {
+                FriendStatus::Friend
+            } else {
+                pending
+            },
+            presence: if accepted {
+                get_presence(id)
+            } else {
+                Presence::Offline
+            },
+        })
+        .collect::<Vec<_>>();
+
+    friends.sort_by_key(|friend| (friend.status != FriendStatus::Friend, friend.name.clone()));
+    Ok(friends)
+}
+
+/// Sends the current friends list to the user
+pub async fn notify_friends_changed(db: &Database, user_id: i32) {
+    match db.run(move |con| load_friends(con, user_id)).await {
+        Ok(friends) => {
+            ActivePolls::notify(user_id, Protocol::FriendsResponse(friends)).await;
+        }
+        Err(e) => warn!("Failed to load friends of user {}: {:?}", user_id, e),
+    }
+}
+
+#[test]
+fn test_derive_presence() {
+    let lobby_users = HashSet::from([1, 2]);
+    let game_users = HashSet::from([2, 3]);
+
+    assert_eq!(
+        derive_presence(1, &lobby_users, &game_users),
+        Presence::Lobby
+    );
+    assert_eq!(
+        derive_presence(2, &lobby_users, &game_users),
+        Presence::Game
+    );
+    assert_eq!(
+        derive_presence(3, &lobby_users, &game_users),
+        Presence::Game
+    );
+    assert_eq!(
+        derive_presence(4, &lobby_users, &game_users),
+        Presence::Menu
+    );
+}
+
+#[test]
+fn test_changed_presences() {
+    let previous = HashMap::from([
+        (1, Presence::Menu),
+        (2, Presence::Lobby),
+        (3, Presence::Game),
+    ]);
+    let current = HashMap::from([
+        (1, Presence::Menu),
+        (2, Presence::Game),
+        (4, Presence::Menu),
+    ]);
+
+    let mut changed = changed_presences(&previous, &current);
+    changed.sort_by_key(|(user, _)| *user);
+    assert_eq!(
+        changed,
+        vec![
+            (2, Presence::Game),
+            (3, Presence::Offline),
+            (4, Presence::Menu)
+        ]
+    );
+    assert!(changed_presences(&current, &current).is_empty());
+}
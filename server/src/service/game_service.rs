@@ -1,85 +1,125 @@
 use crate::{
-    game::{game_instance::GameInstance, game_instance_player::GameInstancePlayer},
+    game::{
+        game_instance::{GameInstance, GameSettings},
+        game_instance_player::GameInstancePlayer,
+        DEFAULT_RATING,
+    },
     model::{
+        currency_transactions::TransactionSource,
         lobbies::Lobby,
         lobby_users::LobbyUser,
         polling::{ActivePolls, Channel},
     },
-    schema::{lobbies, lobby_users},
-    Database,
+    schema::{lobbies, lobby_users, users},
+    service::{chat_service, currency_service, rating_service},
+    Database, RunningGames,
 };
 use diesel::{delete, prelude::*};
-use futures::stream::{futures_unordered::FuturesUnordered, StreamExt};
 use protocol::{
     gods::get_gods,
     protocol::{GameResult, Protocol},
 };
 use rand::seq::SliceRandom;
-use rocket::log::private::debug;
+use rocket::log::private::{debug, warn};
+use static_init::dynamic;
+use std::{collections::HashMap, sync::Mutex};
+use uuid::Uuid;
+
+/// Games of the users a bot took over from while they were offline
+#[dynamic]
+static OFFLINE_PLAYERS: Mutex<HashMap<i32, Uuid>> = Mutex::new(HashMap::new());
 
-pub async fn start_game(db: &Database, lobby: &Lobby) -> GameInstance {
+pub async fn start_game(db: &Database, lobby: &Lobby) -> Option<GameInstance> {
     let lobby_id = lobby.id;
+    let settings = lobby.game_settings();
     let lobby = lobby.clone();
-    let mut heros = get_gods().to_vec();
-    heros.shuffle(&mut rand::thread_rng());
 
     // Get players from db
     let players = db
         .run(move |con| {
-            let mut users: Vec<(Option<i32>, Option<String>)> = LobbyUser::belonging_to(&lobby)
-                .select((lobby_users::user_id, lobby_users::display_name))
-                .load::<(i32, String)>(con)
-                .unwrap()
-                .into_iter()
-                .map(|(user, display_name)| (Some(user), Some(display_name)))
-                .collect::<Vec<_>>();
-
-            while users.len() < 8 {
-                users.push((None, None));
-            }
-
-            users
+            LobbyUser::belonging_to(&lobby)
+                .inner_join(users::table)
+                .select((
+                    lobby_users::user_id,
+                    lobby_users::display_name,
+                    users::rating,
+                ))
+                .load::<(i32, String, i32)>(con)
         })
         .await;
+    let players = match players {
+        Ok(players) => players,
+        Err(e) => {
+            warn!("Failed to load players of lobby {}: {}", lobby_id, e);
+            return None;
+        }
+    };
+
+    let game = create_game(players, settings).await;
+
+    if let Err(e) = db
+        .run(move |con| delete(lobbies::table.filter(lobbies::id.eq(lobby_id))).execute(con))
+        .await
+    {
+        warn!("Failed to delete started lobby {}: {}", lobby_id, e);
+    }
+
+    Some(game)
+}
+
+/// Creates a new game for the given `(user_id, display_name, rating)` tuples.
+/// Missing players are filled up with bots or empty slots depending on the settings.
+pub async fn create_game(players: Vec<(i32, String, i32)>, settings: GameSettings) -> GameInstance {
+    let mut heros = get_gods().to_vec();
+    heros.shuffle(&mut rand::thread_rng());
+
+    let mut players = players
+        .into_iter()
+        .map(|(user, display_name, rating)| (Some(user), Some(display_name), rating))
+        .collect::<Vec<_>>();
+
+    if settings.bot_fill {
+        while players.len() < 8 {
+            players.push((None, None, DEFAULT_RATING));
+        }
+    }
 
     let players = players
         .into_iter()
-        .map(|(user, display_name)| {
-            let hero_choices = Vec::drain(&mut heros, 0..4).collect::<Vec<_>>();
+        .map(|(user, display_name, rating)| {
+            let hero_choices = heros.split_off(heros.len().saturating_sub(4));
+            let god_choices = std::array::from_fn(|i| hero_choices.get(i).map_or(0, |god| god.id));
 
             if let Some(display_name) = display_name {
-                GameInstancePlayer::new(
-                    user,
-                    display_name,
-                    hero_choices
-                        .iter()
-                        .map(|god| god.id)
-                        .collect::<Vec<_>>()
-                        .try_into()
-                        .unwrap(),
-                )
+                GameInstancePlayer::new(user, display_name, god_choices).with_rating(rating)
             } else {
-                let god = hero_choices.choose(&mut rand::thread_rng()).unwrap();
-                GameInstancePlayer::new(
-                    None,
-                    format!("[BOT] {}", god.name),
-                    hero_choices
-                        .iter()
-                        .map(|god| god.id)
-                        .collect::<Vec<_>>()
-                        .try_into()
-                        .unwrap(),
-                )
-                .with_god(god.clone())
+                let god = hero_choices
+                    .choose(&mut rand::thread_rng())
+                    .cloned()
+                    .unwrap_or_default();
+                GameInstancePlayer::new(None, format!("[BOT] {}", god.name), god_choices)
+                    .with_god(god)
+                    .with_rating(rating)
             }
         })
         .collect::<Vec<_>>();
 
-    let game = GameInstance::new(players.try_into().unwrap());
+    // Unused slots are filled with eliminated players, last slot first
+    let player_count = players.len();
+    let mut players = players.into_iter();
+    let players = std::array::from_fn(|i| {
+        players.next().unwrap_or_else(|| {
+            GameInstancePlayer::empty_slot(
+                get_gods()
+                    .choose(&mut rand::thread_rng())
+                    .cloned()
+                    .unwrap_or_default(),
+                (8 + player_count - i) as u8,
+            )
+        })
+    });
 
-    db.run(move |con| delete(lobbies::table.filter(lobbies::id.eq(lobby_id))).execute(con))
-        .await
-        .unwrap();
+    let game = GameInstance::new(players, settings);
 
     ActivePolls::join_users(
         Channel::Game(game.game_id),
@@ -90,28 +130,54 @@ pub async fn start_game(db: &Database, lobby: &Lobby) -> GameInstance {
     );
     notify_users(&game).await;
 
-    for user in game.players.iter().filter(|p| p.user_id.is_some()) {
-        ActivePolls::notify(
-            user.user_id.unwrap(),
-            Protocol::GameStartResponse(user.god_choices),
-        )
-        .await;
+    for user in game.players.iter() {
+        if let Some(user_id) = user.user_id {
+            ActivePolls::notify(user_id, Protocol::GameStartResponse(user.god_choices)).await;
+        }
     }
 
     game
 }
 
-pub async fn next_turn(game: &mut GameInstance) -> bool {
+/// Returns the id of the game the user is still playing in
+pub async fn get_user_game(games: &RunningGames, user_id: i32) -> Option<Uuid> {
+    for (id, game) in games.games.lock().await.iter() {
+        if game.lock().await.has_user(user_id) {
+            return Some(*id);
+        }
+    }
+    None
+}
+
+pub fn track_offline_player(user_id: i32, game_id: Uuid) {
+    OFFLINE_PLAYERS.lock().unwrap().insert(user_id, game_id);
+}
+
+/// Gives the slot back to a returning user, in case a bot took over while
+/// they were offline
+pub async fn resume_player(games: &RunningGames, user_id: i32) {
+    let Some(game_id) = OFFLINE_PLAYERS.lock().unwrap().remove(&user_id) else {
+        return;
+    };
+    let game = games.games.lock().await.get(&game_id).cloned();
+    if let Some(game) = game {
+        if let Some(player) = game.lock().await.get_user_mut(user_id) {
+            player.record_action();
+        }
+    }
+}
+
+pub async fn next_turn(db: &Database, game: &mut GameInstance) -> bool {
     debug!("Next turn for game {:?}", game.game_id);
 
-    let ended = game.next_turn().await;
+    let ended = game.next_turn(db).await;
 
     notify_users(game).await;
 
     ended
 }
 
-pub async fn update_player_placements(game: &mut GameInstance) -> QueryResult<()> {
+pub async fn update_player_placements(db: &Database, game: &mut GameInstance) -> QueryResult<()> {
     debug!("Updating player placements for game {:?}", game.game_id);
     let game_id = game.game_id;
 
@@ -144,45 +210,119 @@ pub async fn update_player_placements(game: &mut GameInstance) -> QueryResult<()
         next_placement -= 1;
     }
 
-    users
+    let mut finished = users.iter().map(|user| user.id).collect::<Vec<_>>();
+
+    let game_over = game.is_game_over();
+    if game_over {
+        if let Some(winner) = game
+            .players
+            .iter_mut()
+            .find(|user| user.health > 0 && user.placement.is_none())
+        {
+            winner.placement = Some(1);
+            finished.push(winner.id);
+        }
+    }
+
+    for player in game
+        .players
         .iter()
-        .filter(|user| user.user_id.is_some())
-        .map(|user| {
-            let user_id = user.user_id.unwrap();
-            ActivePolls::leave_channel(&Channel::Game(game_id), &user_id);
-            ActivePolls::notify(
-                user_id,
-                Protocol::GameEndResponse(GameResult {
-                    place: user.placement.unwrap(),
-                    reward: 100,
-                    ranking: 1,
-                }),
-            )
-        })
-        .collect::<FuturesUnordered<_>>()
-        .collect::<Vec<_>>()
-        .await;
+        .filter(|player| finished.contains(&player.id))
+    {
+        let Some(user_id) = player.user_id else {
+            continue;
+        };
 
-    if game.is_game_over() {
-        let winner = game.players.iter().find(|user| user.health > 0).unwrap();
-
-        if winner.user_id.is_some() {
-            ActivePolls::notify(
-                winner.user_id.unwrap(),
-                Protocol::GameEndResponse(GameResult {
-                    place: 1,
-                    reward: 100,
-                    ranking: 1,
-                }),
-            )
+        let rated = game.settings.rated;
+        let ranking_change = if rated {
+            rating_service::calculate_rating_change(player, &game.players)
+        } else {
+            0
+        };
+        let reward = currency_service::get_reward(player.placement.unwrap_or_default());
+
+        let result = db
+            .run(move |con| {
+                con.transaction(|con| {
+                    let ranking = if rated {
+                        Some(rating_service::update_rating(con, user_id, ranking_change)?)
+                    } else {
+                        None
+                    };
+                    currency_service::credit(
+                        con,
+                        user_id,
+                        reward,
+                        TransactionSource::GameReward,
+                        Some(game_id),
+                    )?;
+
+                    QueryResult::Ok(ranking)
+                })
+            })
             .await;
-        }
+
+        let (reward, ranking, ranking_change) = match result {
+            Ok(ranking) => (reward, ranking.unwrap_or(player.rating), ranking_change),
+            Err(e) => {
+                warn!("Failed to reward user {}: {:?}", user_id, e);
+                (0, player.rating, 0)
+            }
+        };
+
+        ActivePolls::leave_channel(&Channel::Game(game_id), &user_id);
+        ActivePolls::notify(
+            user_id,
+            Protocol::GameEndResponse(GameResult {
+                place: player.placement.unwrap_or_default(),
+                reward,
+                ranking,
+                ranking_change,
+            }),
+        )
+        .await;
+    }
+
+    if game_over {
         ActivePolls::close_channel(&Channel::Game(game_id));
+        chat_service::clear_history(&Channel::Game(game_id));
     }
 
     QueryResult::Ok(())
 }
 
+/// Replaces the player with a bot and sends them back to the menu.
+/// Returns the user id of the removed player.
+pub async fn kick_player(game: &mut GameInstance, player_id: Uuid) -> Option<i32> {
+    let game_id = game.game_id;
+    let player = game.get_game_user_mut(player_id)?;
+    let user_id = player.user_id?;
+    player.replace_with_bot();
+
+    ActivePolls::leave_channel(&Channel::Game(game_id), &user_id);
+    ActivePolls::notify(user_id, Protocol::GameLeaveResponse).await;
+    notify_users(game).await;
+
+    Some(user_id)
+}
+
+/// Sends all remaining players back to the menu without rewards. The game
+/// has to be removed from the running games by the caller.
+pub async fn end_game(game: &GameInstance) {
+    let channel = Channel::Game(game.game_id);
+    for user_id in game
+        .players
+        .iter()
+        .filter(|player| player.is_active())
+        .filter_map(|player| player.user_id)
+    {
+        ActivePolls::notify(user_id, Protocol::GameLeaveResponse).await;
+    }
+
+    ActivePolls::close_channel(&channel);
+    chat_service::clear_history(&channel);
+}
+
 pub async fn notify_users(game: &GameInstance) {
     ActivePolls::notify_channel(
         &Channel::Game(game.game_id),
@@ -190,3 +330,40 @@ pub async fn notify_users(game: &GameInstance) {
     )
     .await;
 }
+
+#[test]
+fn test_resume_offline_player() {
+    use rocket::tokio::sync::Mutex as AsyncMutex;
+    use std::sync::Arc;
+
+    // Not used by other tests
+    const USER: i32 = -49;
+
+    rocket::async_test(async {
+        let mut players: [GameInstancePlayer; 8] = Default::default();
+        players[0] = GameInstancePlayer::new(Some(USER), "Player".to_string(), [0; 4]);
+        players[0].autoplay = true;
+        let game = GameInstance::new(players, GameSettings::default());
+        let game_id = game.game_id;
+        let games = RunningGames {
+            games: Arc::new(AsyncMutex::new(HashMap::from([(
+                game_id,
+                Arc::new(AsyncMutex::new(game)),
+            )]))),
+        };
+        let is_bot = || async {
+            let game = games.games.lock().await[&game_id].clone();
+            let is_bot = game.lock().await.get_user(USER).unwrap().is_bot();
+            is_bot
+        };
+
+        // Bots that took over from connected users keep playing
+        resume_player(&games, USER).await;
+        assert!(is_bot().await);
+
+        track_offline_player(USER, game_id);
+        resume_player(&games, USER).await;
+        assert!(!is_bot().await);
+        assert!(!OFFLINE_PLAYERS.lock().unwrap().contains_key(&USER));
+    });
+}
@@ -1,72 +1,117 @@
 use crate::{
     model::{
         lobbies::{Lobby, LobbyError, LobbyWithUsers, NewLobby},
+        lobby_bans::NewLobbyBan,
+        lobby_invites::{LobbyInvite, NewLobbyInvite},
         lobby_users::{LobbyUser, NewLobbyUser},
         polling::{ActivePolls, Channel},
         users::User,
     },
-    schema::{lobbies, lobby_users},
+    schema::{lobbies, lobby_bans, lobby_invites, lobby_users, users},
+    service::chat_service,
     Database,
 };
 
 use chrono::NaiveDateTime;
 use diesel::{
     delete,
-    dsl::{count, exists, not},
+    dsl::{count, exists, not, now},
     insert_into,
     prelude::*,
-    update,
+    select, update,
+};
+use protocol::protocol::{
+    LobbyInvite as LobbyInviteInfo, LobbyJoinRequest, LobbySettings, Protocol,
 };
-use protocol::protocol::{LobbyJoinRequest, Protocol};
 use rocket::log::private::{debug, trace, warn};
 
+const LOBBY_LIST_SIZE: i64 = 100;
+/// Minutes a kicked user is not allowed to rejoin the lobby
+const KICK_BAN_DURATION: i64 = 5;
+
 pub async fn join_lobby(
     db: &Database,
     lobby: LobbyJoinRequest,
     user: &User,
 ) -> Result<(), LobbyError> {
     let user = user.clone();
-    let user_id = user.id;
     let lobby_name = lobby.name.clone();
-    if let Ok(lobby) = db
+    match db
         .run(move |con| {
-            let lobby = get_or_create_lobby(con, &lobby, user_id);
-            if let Ok(lobby) = lobby {
-                debug!("Target lobby {:?} for user {:?}", lobby, user);
-                debug!("Deleting old lobby user entries for user {:?}", user);
-                if let Err(err) = delete(lobby_users::table)
-                    .filter(lobby_users::user_id.eq(user.id))
-                    .execute(con)
-                {
-                    error!("Failed to delete lobby user entries {:?}", err);
-                    return Err(LobbyError::Internal);
-                }
-
-                debug!("Inserting new lobby user entry for user {:?}", user);
-                if let Err(err) = insert_into(lobby_users::table)
-                    .values::<NewLobbyUser>(NewLobbyUser::from_parents(&lobby, &user))
-                    .execute(con)
-                {
-                    error!("Failed to create lobby user entry {:?}", err);
-                    return Err(LobbyError::Internal);
-                }
-
-                ActivePolls::join_user(Channel::Lobby(lobby.id), user.id);
-                Ok(lobby)
-            } else {
-                warn!("Failed to get or create lobby {:?}", lobby);
-                Err(LobbyError::Internal)
-            }
+            let lobby = get_or_create_lobby(con, &lobby, user.id)?;
+            debug!("Target lobby {:?} for user {:?}", lobby, user);
+            add_user_to_lobby(con, &lobby, &user)?;
+            Ok(lobby)
         })
         .await
     {
-        debug!("Joined lobby {:?}. Sending update.", lobby);
-        notify_lobby_users(db, lobby.id).await;
-        Ok(())
-    } else {
-        warn!("Failed to join lobby {:?}", lobby_name);
-        Err(LobbyError::Internal)
+        Ok(lobby) => {
+            debug!("Joined lobby {:?}. Sending update.", lobby);
+            let _ = notify_lobby_users(db, lobby.id).await;
+            Ok(())
+        }
+        Err(err) => {
+            warn!("Failed to join lobby {:?}: {:?}", lobby_name, err);
+            Err(err)
+        }
+    }
+}
+
+fn add_user_to_lobby(con: &mut PgConnection, lobby: &Lobby, user: &User) -> Result<(), LobbyError> {
+    match is_banned(con, lobby.id, user.id) {
+        Ok(true) => return Err(LobbyError::Banned),
+        Err(err) => {
+            error!("Failed to check lobby bans {:?}", err);
+            return Err(LobbyError::Internal);
+        }
+        _ => {}
+    }
+
+    match LobbyUser::belonging_to(lobby)
+        .filter(lobby_users::user_id.ne(user.id))
+        .select(count(lobby_users::id))
+        .first::<i64>(con)
+    {
+        Ok(player_count) if player_count >= lobby.max_players as i64 => {
+            return Err(LobbyError::Full)
+        }
+        Err(err) => {
+            error!("Failed to count lobby users {:?}", err);
+            return Err(LobbyError::Internal);
+        }
+        _ => {}
+    }
+
+    debug!("Deleting old lobby user entries for user {:?}", user);
+    if let Err(err) = delete(lobby_users::table)
+        .filter(lobby_users::user_id.eq(user.id))
+        .execute(con)
+    {
+        error!("Failed to delete lobby user entries {:?}", err);
+        return Err(LobbyError::Internal);
     }
+
+    debug!("Inserting new lobby user entry for user {:?}", user);
+    if let Err(err) = insert_into(lobby_users::table)
+        .values::<NewLobbyUser>(NewLobbyUser::from_parents(lobby, user))
+        .execute(con)
+    {
+        error!("Failed to create lobby user entry {:?}", err);
+        return Err(LobbyError::Internal);
+    }
+
+    ActivePolls::join_user(Channel::Lobby(lobby.id), user.id);
+    Ok(())
+}
+
+fn is_banned(con: &mut PgConnection, lobby_id: i32, user_id: i32) -> QueryResult<bool> {
+    select(exists(
+        lobby_bans::table
+            .filter(lobby_bans::lobby_id.eq(lobby_id))
+            .filter(lobby_bans::user_id.eq(user_id))
+            .filter(lobby_bans::expires_at.gt(now)),
+    ))
+    .get_result(con)
 }
 
 fn get_or_create_lobby(
@@ -81,13 +126,7 @@ fn get_or_create_lobby(
         .first::<Lobby>(con)
     {
         trace!("Found existing lobby {:?}", existing_lobby);
-        match LobbyUser::belonging_to(&existing_lobby)
-            .select(count(lobby_users::id))
-            .first::<i64>(con)
-        {
-            Ok(player_count) if player_count >= 8 => Err(LobbyError::Full),
-            _ => Ok(existing_lobby),
-        }
+        Ok(existing_lobby)
     } else {
         debug!("Creating new lobby {:?}", lobby);
         match insert_into(lobbies::table)
@@ -95,7 +134,7 @@ fn get_or_create_lobby(
             .get_results::<Lobby>(con)
         {
             Ok(results) => {
-                debug!("Created new lobby {:?}", results.first().unwrap());
+                debug!("Created new lobby {:?}", results.first());
                 results.first().cloned().ok_or(LobbyError::Internal)
             }
             Err(e) => {
@@ -107,6 +146,139 @@ fn get_or_create_lobby(
     }
 }
 
+pub async fn kick_user(db: &Database, lobby: &Lobby, target: &LobbyUser) -> QueryResult<()> {
+    let lobby_id = lobby.id;
+    let lobby_user_id = target.id;
+    let user_id = target.user_id;
+    db.run(move |con| {
+        con.transaction(|con| {
+            delete(lobby_users::table)
+                .filter(lobby_users::id.eq(lobby_user_id))
+                .execute(con)?;
+
+            let expires_at =
+                chrono::Utc::now().naive_utc() + chrono::Duration::minutes(KICK_BAN_DURATION);
+            insert_into(lobby_bans::table)
+                .values(NewLobbyBan::new(lobby_id, user_id, expires_at))
+                .on_conflict((lobby_bans::lobby_id, lobby_bans::user_id))
+                .do_update()
+                .set(lobby_bans::expires_at.eq(expires_at))
+                .execute(con)
+        })
+    })
+    .await?;
+
+    ActivePolls::leave_channel(&Channel::Lobby(lobby_id), &user_id);
+    let _ = ActivePolls::notify(user_id, Protocol::LobbyKickResponse).await;
+    let _ = notify_lobby_users(db, lobby_id).await;
+
+    Ok(())
+}
+
+pub async fn set_master(db: &Database, lobby: i32, user_id: i32) -> QueryResult<()> {
+    db.run(move |con| {
+        update(lobbies::table)
+            .filter(lobbies::id.eq(lobby))
+            .set(lobbies::master_id.eq(user_id))
+            .execute(con)
+    })
+    .await?;
+
+    let _ = notify_lobby_users(db, lobby).await;
+
+    Ok(())
+}
+
+pub async fn invite_user(
+    db: &Database,
+    lobby: &LobbyWithUsers,
+    inviter: &User,
+    display_name: String,
+) -> Result<(), LobbyError> {
+    let Ok(invitee) = db
+        .run(move |con| {
+            users::table
+                .filter(users::display_name.eq(display_name))
+                .first::<User>(con)
+        })
+        .await
+    else {
+        return Err(LobbyError::NotFound);
+    };
+
+    if lobby.users.iter().any(|u| u.user_id == invitee.id) {
+        return Err(LobbyError::Conflict);
+    }
+
+    let lobby_id = lobby.lobby.id;
+    let invitee_id = invitee.id;
+    let inviter_id = inviter.id;
+    let invite_id = db
+        .run(move |con| {
+            con.transaction(|con| {
+                // An explicit invite lifts a previous kick
+                delete(lobby_bans::table)
+                    .filter(lobby_bans::lobby_id.eq(lobby_id))
+                    .filter(lobby_bans::user_id.eq(invitee_id))
+                    .execute(con)?;
+
+                insert_into(lobby_invites::table)
+                    .values(NewLobbyInvite::new(lobby_id, invitee_id, inviter_id))
+                    .on_conflict((lobby_invites::lobby_id, lobby_invites::user_id))
+                    .do_update()
+                    .set(lobby_invites::inviter_id.eq(inviter_id))
+                    .returning(lobby_invites::id)
+                    .get_result::<i32>(con)
+            })
+        })
+        .await
+        .map_err(|err| {
+            error!("Failed to create lobby invite {:?}", err);
+            LobbyError::Internal
+        })?;
+
+    let _ = ActivePolls::notify(
+        invitee_id,
+        Protocol::LobbyInviteResponse(LobbyInviteInfo {
+            id: invite_id,
+            lobby: lobby.lobby.name.clone(),
+            from: inviter
+                .display_name
+                .clone()
+                .unwrap_or_else(|| inviter.username.clone()),
+        }),
+    )
+    .await;
+
+    Ok(())
+}
+
+pub async fn accept_invite(db: &Database, invite_id: i32, user: &User) -> Result<(), LobbyError> {
+    let user = user.clone();
+    let lobby = db
+        .run(move |con| {
+            let (invite, lobby) = lobby_invites::table
+                .inner_join(lobbies::table)
+                .filter(lobby_invites::id.eq(invite_id))
+                .filter(lobby_invites::user_id.eq(user.id))
+                .first::<(LobbyInvite, Lobby)>(con)
+                .map_err(|_| LobbyError::NotFound)?;
+
+            add_user_to_lobby(con, &lobby, &user)?;
+
+            if let Err(err) = delete(&invite).execute(con) {
+                warn!("Failed to delete accepted invite {:?}", err);
+            }
+
+            Ok(lobby)
+        })
+        .await?;
+
+    let _ = notify_lobby_users(db, lobby.id).await;
+
+    Ok(())
+}
+
 pub async fn remove_user_from_lobbies(db: &Database, user: &User) {
     debug!("Removing user {:?} from lobbies", user);
     let user_id = user.id;
@@ -122,21 +294,75 @@ pub async fn remove_user_from_lobbies(db: &Database, user: &User) {
         reassign_master(db).await;
 
         for lobby_id in lobby_ids {
+            ActivePolls::leave_channel(&Channel::Lobby(lobby_id), &user_id);
             notify_lobby_users(db, lobby_id).await;
         }
     }
 }
 
-pub async fn set_ready_state(db: &Database, user: &LobbyUser, rdy: bool) {
-    let user_id = user.id;
+/// Returns all lobbies together with their player count
+pub async fn list_lobbies(db: &Database) -> QueryResult<Vec<(Lobby, i64)>> {
+    db.run(move |con| {
+        let lobbies = lobbies::table
+            .order(lobbies::created_at.desc())
+            .limit(LOBBY_LIST_SIZE)
+            .load::<Lobby>(con)?;
+
+        let player_counts = LobbyUser::belonging_to(&lobbies)
+            .group_by(lobby_users::lobby_id)
+            .select((lobby_users::lobby_id, count(lobby_users::id)))
+            .load::<(i32, i64)>(con)?;
+
+        Ok(lobbies
+            .into_iter()
+            .map(|lobby| {
+                let players = player_counts
+                    .iter()
+                    .find(|(lobby_id, _)| *lobby_id == lobby.id)
+                    .map_or(0, |(_, players)| *players);
+                (lobby, players)
+            })
+            .collect())
+    })
+    .await
+}
+
+pub async fn update_settings(
+    db: &Database,
+    lobby: i32,
+    settings: LobbySettings,
+) -> QueryResult<()> {
     db.run(move |con| {
-        diesel::update(lobby_users::table)
-            .filter(lobby_users::id.eq(user_id))
-            .set(lobby_users::ready.eq(rdy))
+        update(lobbies::table)
+            .filter(lobbies::id.eq(lobby))
+            .set((
+                lobbies::max_players.eq(settings.max_players as i32),
+                lobbies::bot_fill.eq(settings.bot_fill),
+                lobbies::rules.eq(settings.rules.as_str()),
+                lobbies::rated.eq(settings.rated),
+            ))
             .execute(con)
-            .unwrap();
     })
-    .await;
+    .await?;
+
+    let _ = notify_lobby_users(db, lobby).await;
+
+    Ok(())
+}
+
+pub async fn set_ready_state(db: &Database, user: &LobbyUser, rdy: bool) {
+    let user_id = user.id;
+    if let Err(e) = db
+        .run(move |con| {
+            diesel::update(lobby_users::table)
+                .filter(lobby_users::id.eq(user_id))
+                .set(lobby_users::ready.eq(rdy))
+                .execute(con)
+        })
+        .await
+    {
+        warn!("Failed to set ready state of lobby user {}: {}", user_id, e);
+    }
 
     notify_lobby_users(db, user.lobby_id).await;
 }
@@ -147,25 +373,34 @@ pub async fn start_lobby_timer(db: &Database, lobby: &LobbyWithUsers) {
     let start_time =
         chrono::Utc::now().naive_utc() + chrono::Duration::seconds(if users_rdy { 5 } else { 20 });
 
-    db.run(move |con| {
-        update(lobbies::table)
-            .set(lobbies::start_at.eq(Some(start_time)))
-            .execute(con)
-            .unwrap()
-    })
-    .await;
+    let lobby_id = lobby.lobby.id;
+    if let Err(e) = db
+        .run(move |con| {
+            update(lobbies::table)
+                .filter(lobbies::id.eq(lobby_id))
+                .set(lobbies::start_at.eq(Some(start_time)))
+                .execute(con)
+        })
+        .await
+    {
+        warn!("Failed to start timer of lobby {}: {}", lobby_id, e);
+    }
 
-    notify_lobby_users(db, lobby.lobby.id).await;
+    notify_lobby_users(db, lobby_id).await;
 }
 
 pub async fn stop_lobby_timer(db: &Database, lobby: i32) {
-    db.run(move |con| {
-        update(lobbies::table)
-            .set(lobbies::start_at.eq(None::<NaiveDateTime>))
-            .execute(con)
-            .unwrap()
-    })
-    .await;
+    if let Err(e) = db
+        .run(move |con| {
+            update(lobbies::table)
+                .filter(lobbies::id.eq(lobby))
+                .set(lobbies::start_at.eq(None::<NaiveDateTime>))
+                .execute(con)
+        })
+        .await
+    {
+        warn!("Failed to stop timer of lobby {}: {}", lobby, e);
+    }
 
     notify_lobby_users(db, lobby).await;
 }
@@ -182,7 +417,10 @@ async fn reassign_master(db: &Database) {
                 .filter(lobby_users::id.is_null())
                 .select(lobbies::all_columns)
                 .load::<Lobby>(con)
-                .unwrap()
+                .unwrap_or_else(|err| {
+                    error!("Failed to load lobbies without master {:?}", err);
+                    vec![]
+                })
                 .iter()
                 .filter_map(|lobby| {
                     if let Ok(user) = LobbyUser::belonging_to(lobby)
@@ -215,13 +453,19 @@ async fn reassign_master(db: &Database) {
 
 fn remove_empty_lobbies(con: &mut PgConnection) {
     debug!("Removing empty lobbies");
-    if let Err(err) = delete(lobbies::table)
+    match delete(lobbies::table)
         .filter(not(exists(
             lobby_users::table.select(lobby_users::id).distinct(),
         )))
-        .execute(con)
+        .returning(lobbies::id)
+        .get_results::<i32>(con)
     {
-        error!("Failed to delete empty lobbies {}", err)
+        Ok(removed) => {
+            for lobby_id in removed {
+                chat_service::clear_history(&Channel::Lobby(lobby_id));
+            }
+        }
+        Err(err) => error!("Failed to delete empty lobbies {}", err),
     }
 }
 
@@ -232,6 +476,7 @@ pub fn close_lobby(con: &mut PgConnection, lobby: &Lobby) -> Result<(), String>
         .execute(con);
 
     if result.is_ok() {
+        chat_service::clear_history(&Channel::Lobby(lobby.id));
         Ok(())
     } else {
         Err("Failed to delete lobby".to_string())
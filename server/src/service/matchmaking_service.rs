@@ -0,0 +1,204 @@
+use chrono::{DateTime, Utc};
+use protocol::protocol::{Protocol, QueueStatus};
+use rocket::futures::future::join_all;
+use static_init::dynamic;
+use std::sync::Mutex;
+
+use crate::model::polling::ActivePolls;
+
+const MATCH_SIZE: usize = 8;
+const BASE_RATING_WINDOW: i32 = 100;
+const RATING_WINDOW_PER_SECOND: i32 = 10;
+const MAX_RATING_WINDOW: i32 = 1000;
+/// Seconds after which the longest waiting player gets a game filled up with bots
+const BOT_FILL_TIMEOUT: i64 = 60;
+
+#[derive(Clone, Debug)]
+pub struct QueueEntry {
+    pub user_id: i32,
+    pub display_name: String,
+    pub rating: i32,
+    pub joined_at: DateTime<Utc>,
+}
+
+impl QueueEntry {
+    pub fn new(user_id: i32, display_name: String, rating: i32) -> Self {
+        Self {
+            user_id,
+            display_name,
+            rating,
+            joined_at: Utc::now(),
+        }
+    }
+
+    fn waited(&self, now: DateTime<Utc>) -> i64 {
+        (now - self.joined_at).num_seconds().max(0)
+    }
+
+    fn rating_window(&self, now: DateTime<Utc>) -> i32 {
+        (BASE_RATING_WINDOW + self.waited(now) as i32 * RATING_WINDOW_PER_SECOND)
+            .min(MAX_RATING_WINDOW)
+    }
+}
+
+#[derive(Default)]
+pub struct MatchmakingQueue {
+    entries: Vec<QueueEntry>,
+}
+
+#[dynamic]
+static MATCHMAKING_QUEUE: Mutex<MatchmakingQueue> = Mutex::new(MatchmakingQueue::default());
+
+impl MatchmakingQueue {
+    pub fn get() -> &'static Mutex<Self> {
+        &MATCHMAKING_QUEUE
+    }
+
+    pub fn join(entry: QueueEntry) -> QueueStatus {
+        let mut queue = Self::get().lock().unwrap();
+        let user_id = entry.user_id;
+        if !queue.entries.iter().any(|e| e.user_id == user_id) {
+            queue.entries.push(entry);
+        }
+
+        queue.status_of(user_id, Utc::now()).unwrap()
+    }
+
+    pub fn leave(user_id: i32) -> bool {
+        let mut queue = Self::get().lock().unwrap();
+        let len = queue.entries.len();
+        queue.entries.retain(|e| e.user_id != user_id);
+        len != queue.entries.len()
+    }
+
+    pub fn status(user_id: i32) -> Option<QueueStatus> {
+        Self::get().lock().unwrap().status_of(user_id, Utc::now())
+    }
+
+    /// Removes all players that can be matched from the queue and returns them grouped by game
+    pub fn take_matches() -> Vec<Vec<QueueEntry>> {
+        let mut queue = Self::get().lock().unwrap();
+        let groups = find_matches(&queue.entries, Utc::now());
+
+        let entries = std::mem::take(&mut queue.entries);
+        let matches = groups
+            .iter()
+            .map(|group| {
+                group
+                    .iter()
+                    .map(|idx| entries[*idx].clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        queue.entries = entries
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, _)| !groups.iter().flatten().any(|matched| matched == idx))
+            .map(|(_, entry)| entry)
+            .collect();
+
+        matches
+    }
+
+    pub async fn notify_users() {
+        let statuses = {
+            let queue = Self::get().lock().unwrap();
+            let now = Utc::now();
+            queue
+                .entries
+                .iter()
+                .filter_map(|e| queue.status_of(e.user_id, now).map(|s| (e.user_id, s)))
+                .collect::<Vec<_>>()
+        };
+
+        join_all(statuses.into_iter().map(|(user, status)| {
+            ActivePolls::notify(user, Protocol::QueueStatusResponse(status))
+        }))
+        .await;
+    }
+
+    fn status_of(&self, user_id: i32, now: DateTime<Utc>) -> Option<QueueStatus> {
+        self.entries
+            .iter()
+            .find(|e| e.user_id == user_id)
+            .map(|entry| QueueStatus {
+                players: self.entries.len() as u32,
+                rating_window: entry.rating_window(now),
+                queued_since: entry.joined_at,
+            })
+    }
+}
+
+/// Groups queued players starting with the longest waiting one. Players are
+/// matched if their rating is inside the rating window of that player, which
+/// widens the longer they wait.
+fn find_matches(entries: &[QueueEntry], now: DateTime<Utc>) -> Vec<Vec<usize>> {
+    let mut order = (0..entries.len()).collect::<Vec<_>>();
+    order.sort_by_key(|idx| entries[*idx].joined_at);
+
+    let mut matched = vec![false; entries.len()];
+    let mut groups = vec![];
+
+    for anchor in order.iter() {
+        if matched[*anchor] {
+            continue;
+        }
+
+        let anchor_entry = &entries[*anchor];
+        let window = anchor_entry.rating_window(now);
+        let group = order
+            .iter()
+            .filter(|idx| {
+                !matched[**idx] && (entries[**idx].rating - anchor_entry.rating).abs() <= window
+            })
+            .take(MATCH_SIZE)
+            .copied()
+            .collect::<Vec<_>>();
+
+        if group.len() == MATCH_SIZE || anchor_entry.waited(now) >= BOT_FILL_TIMEOUT {
+            for idx in group.iter() {
+                matched[*idx] = true;
+            }
+            groups.push(group);
+        }
+    }
+
+    groups
+}
+
+#[test]
+fn test_find_matches() {
+    let now = Utc::now();
+    let entry = |rating: i32, waited: i64| QueueEntry {
+        user_id: rating,
+        display_name: String::new(),
+        rating,
+        joined_at: now - chrono::Duration::seconds(waited),
+    };
+
+    // Not enough players
+    let entries = (0..7).map(|i| entry(1000 + i, 0)).collect::<Vec<_>>();
+    assert!(find_matches(&entries, now).is_empty());
+
+    // Full game
+    let entries = (0..9)
+        .map(|i| entry(1000 + i, 9 - i as i64))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        find_matches(&entries, now),
+        vec![vec![0, 1, 2, 3, 4, 5, 6, 7]]
+    );
+
+    // Rating too far apart
+    let entries = (0..8).map(|i| entry(1000 + i * 80, 0)).collect::<Vec<_>>();
+    assert!(find_matches(&entries, now).is_empty());
+
+    // Window widens with wait time
+    let entries = (0..8).map(|i| entry(1000 + i * 80, 50)).collect::<Vec<_>>();
+    assert_eq!(find_matches(&entries, now).len(), 1);
+
+    // Bots fill after timeout
+    let entries = vec![entry(1000, BOT_FILL_TIMEOUT), entry(2000, 0)];
+    assert_eq!(find_matches(&entries, now), vec![vec![0]]);
+}
@@ -1,6 +1,15 @@
 pub(crate) mod character_service;
+pub(crate) mod chat_service;
 pub(crate) mod combat_service;
+pub(crate) mod currency_service;
+pub(crate) mod display_name_service;
+pub(crate) mod friend_service;
 pub(crate) mod game_service;
 pub(crate) mod lobby_service;
+pub(crate) mod matchmaking_service;
+pub(crate) mod openapi_service;
+pub(crate) mod polling_service;
+pub(crate) mod rating_service;
 pub(crate) mod shop_service;
 pub(crate) mod simple_bot_service;
+pub(crate) mod websocket_service;
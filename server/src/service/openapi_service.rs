@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+
+use protocol::{
+    api::{self, Access, Endpoint, ENDPOINTS},
+    protocol::Protocol,
+    schemars::{gen::SchemaSettings, schema::Schema},
+    version::PROTOCOL_VERSION,
+};
+use serde_json::{json, Map, Value};
+use static_init::dynamic;
+
+/// The document only changes with the protocol, so it is built once
+#[dynamic]
+static DOCUMENT: Value = build_document();
+
+/// OpenAPI 3 description of the api built from the protocol endpoint table
+pub fn get_document() -> &'static Value {
+    &DOCUMENT
+}
+
+fn build_document() -> Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    let bodies = api::body_schemas(&mut generator);
+    // Only the root schema applies the OpenAPI adjustments to the definitions
+    let root = generator.into_root_schema_for::<Protocol>();
+
+    let mut paths = Map::new();
+    let mut schemas = match serde_json::to_value(&root.definitions) {
+        Ok(Value::Object(schemas)) => schemas,
+        _ => Map::new(),
+    };
+    let protocol = schemas
+        .entry("Protocol")
+        .or_insert_with(|| serde_json::to_value(&root.schema).unwrap_or_default())
+        .clone();
+
+    for endpoint in ENDPOINTS {
+        let path = endpoint.path.replace('<', "{").replace('>', "}");
+        let operation = get_operation(endpoint, &protocol, &bodies, &mut schemas);
+
+        if let Value::Object(methods) = paths.entry(path).or_insert_with(|| json!({})) {
+            methods.insert(endpoint.method.as_str().to_lowercase(), operation);
+        }
+    }
+
+    insert_response_schema(&protocol, &mut schemas, "NetworkingError");
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": env!("CARGO_PKG_NAME"),
+            "version": PROTOCOL_VERSION,
+        },
+        "servers": [{ "url": "/api/v1" }],
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "apiKey": {
+                    "type": "apiKey",
+                    "in": "header",
+                    "name": "x-api-key",
+                },
+            },
+        },
+    })
+}
+
+fn get_operation(
+    endpoint: &Endpoint,
+    protocol: &Value,
+    bodies: &BTreeMap<&str, Schema>,
+    schemas: &mut Map<String, Value>,
+) -> Value {
+    let parameters = endpoint
+        .params
+        .iter()
+        .map(|(name, ty)| parameter(name, ty, "path", true))
+        .chain(
+            endpoint
+                .query
+                .iter()
+                .map(|(name, ty)| parameter(name, ty, "query", false)),
+        )
+        .collect::<Vec<_>>();
+
+    let responses = endpoint
+        .responses
+        .iter()
+        .map(|response| insert_response_schema(protocol, schemas, response))
+        .collect::<Vec<_>>();
+    let response_schema = if responses.is_empty() {
+        // Not a `Protocol` response
+        json!({ "type": "object" })
+    } else {
+        json!({ "oneOf": responses })
+    };
+
+    let mut operation = json!({
+        "operationId": endpoint.name,
+        "summary": endpoint.summary.trim(),
+        "parameters": parameters,
+        "responses": {
+            "200": {
+                "description": "Success",
+                "content": { "application/json": { "schema": response_schema } },
+            },
+            "default": {
+                "description": "Error",
+                "content": {
+                    "application/json": { "schema": schema_ref("Protocol.NetworkingError") },
+                },
+            },
+        },
+    });
+
+    if let Some(body) = endpoint.body.and_then(|body| bodies.get(body)) {
+        operation["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": body } },
+        });
+    }
+
+    if endpoint.access != Access::Public {
+        operation["security"] = json!([{ "apiKey": [] }]);
+    }
+    if endpoint.access == Access::Admin {
+        operation["tags"] = json!(["admin"]);
+        operation["description"] = json!("Requires the admin role");
+    }
+
+    operation
+}
+
+fn parameter(name: &str, ty: &str, location: &str, required: bool) -> Value {
+    let schema = match ty {
+        "i32" | "u64" | "usize" => json!({ "type": "integer" }),
+        "Uuid" => json!({ "type": "string", "format": "uuid" }),
+        _ => json!({ "type": "string" }),
+    };
+
+    json!({
+        "name": name,
+        "in": location,
+        "required": required,
+        "schema": schema,
+    })
+}
+
+/// Adds the schema of a single `Protocol` variant, e.g. `{"BoardResponse": [...]}`
+fn insert_response_schema(
+    protocol: &Value,
+    schemas: &mut Map<String, Value>,
+    variant: &str,
+) -> Value {
+    let name = format!("Protocol.{}", variant);
+    if let Some(schema) = variant_schema(protocol, variant) {
+        schemas.insert(name.clone(), schema);
+    }
+    schema_ref(&name)
+}
+
+/// Picks the variant out of the externally tagged enum schema. Unit variants
+/// are grouped into a single string enum.
+fn variant_schema(schema: &Value, variant: &str) -> Option<Value> {
+    schema["oneOf"].as_array()?.iter().find_map(|schema| {
+        let units = schema["enum"].as_array();
+        if units.is_some_and(|units| units.iter().any(|unit| unit == variant)) {
+            Some(json!({ "type": "string", "enum": [variant] }))
+        } else if schema["properties"].get(variant).is_some() {
+            Some(schema.clone())
+        } else {
+            None
+        }
+    })
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+#[test]
+fn test_document_schemas() {
+    let document = get_document();
+    let schemas = &document["components"]["schemas"];
+
+    let buy = &document["paths"]["/games/shops/buy"]["post"]["requestBody"];
+    assert_eq!(
+        buy["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/BuyRequest"
+    );
+    assert!(schemas["BuyRequest"]["properties"].as_object().is_some());
+
+    // Types are traced through nested structs and enums
+    let lobby = &schemas["LobbyInfo"]["properties"];
+    assert_eq!(lobby["start_at"]["format"], "date-time");
+    assert_eq!(lobby["start_at"]["nullable"], true);
+    assert_eq!(
+        schemas["Protocol.PollingTimeout"],
+        serde_json::json!({ "type": "string", "enum": ["PollingTimeout"] })
+    );
+    assert_eq!(
+        schemas["Protocol.BoardResponse"]["required"],
+        serde_json::json!(["BoardResponse"])
+    );
+    let error = &schemas["Error"]["properties"];
+    assert_eq!(error["status"]["type"], "integer");
+    assert!(error["reference"]["allOf"][0]["$ref"]
+        .as_str()
+        .is_some_and(|reference| reference.ends_with("/Protocol")));
+}
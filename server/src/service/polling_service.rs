@@ -0,0 +1,59 @@
+use crate::{
+    model::{
+        lobbies::{Lobby, LobbyWithUsers},
+        lobby_users::LobbyUser,
+    },
+    schema::{lobbies, lobby_users},
+    service::matchmaking_service::MatchmakingQueue,
+    Database, RunningGames,
+};
+use diesel::prelude::*;
+use protocol::protocol::Protocol;
+
+/// Collects the current state of the user. Sent to clients that can no longer
+/// resume from their last event.
+pub async fn get_snapshot(db: &Database, games: &RunningGames, user_id: i32) -> Vec<Protocol> {
+    let mut snapshot = Vec::new();
+
+    for game in games.games.lock().await.values() {
+        if let Some(state) = game.lock().await.get_state(user_id) {
+            snapshot.push(Protocol::GameStateResponse(state));
+            return snapshot;
+        }
+    }
+
+    let lobby = db
+        .run(move |con| {
+            let lobby = lobbies::table
+                .filter(
+                    lobbies::id.eq_any(
+                        lobby_users::table
+                            .filter(lobby_users::user_id.eq(user_id))
+                            .select(lobby_users::lobby_id),
+                    ),
+                )
+                .first::<Lobby>(con)
+                .optional()?;
+
+            match lobby {
+                Some(lobby) => {
+                    let users = LobbyUser::belonging_to(&lobby).load(con)?;
+                    QueryResult::Ok(Some(LobbyWithUsers { lobby, users }))
+                }
+                None => Ok(None),
+            }
+        })
+        .await;
+
+    match lobby {
+        Ok(Some(lobby)) => snapshot.push(Protocol::LobbyStatusResponse(lobby.into())),
+        Ok(None) => {}
+        Err(e) => warn!("Failed to load lobby snapshot for {}: {:?}", user_id, e),
+    }
+
+    if let Some(status) = MatchmakingQueue::status(user_id) {
+        snapshot.push(Protocol::QueueStatusResponse(status));
+    }
+
+    snapshot
+}
@@ -0,0 +1,78 @@
+use crate::{game::game_instance_player::GameInstancePlayer, schema::users};
+use diesel::{prelude::*, update};
+
+const K_FACTOR: f64 = 32.0;
+/// Bots count less than real players so farming them does not inflate the rating
+const BOT_WEIGHT: f64 = 0.5;
+
+/// Pairwise Elo over placements. Every opponent that placed worse counts as a
+/// win, every opponent still alive or placed better counts as a loss.
+pub fn calculate_rating_change(player: &GameInstancePlayer, players: &[GameInstancePlayer]) -> i32 {
+    let Some(placement) = player.placement else {
+        return 0;
+    };
+
+    let (change, total_weight) = players
+        .iter()
+        .filter(|opponent| opponent.id != player.id && !opponent.empty)
+        .fold((0.0, 0.0), |(change, total_weight), opponent| {
+            let weight = if opponent.user_id.is_some() {
+                1.0
+            } else {
+                BOT_WEIGHT
+            };
+            let score = match opponent.placement {
+                Some(opponent_placement) if opponent_placement > placement => 1.0,
+                _ => 0.0,
+            };
+            let expected =
+                1.0 / (1.0 + 10f64.powf((opponent.rating - player.rating) as f64 / 400.0));
+
+            (change + weight * (score - expected), total_weight + weight)
+        });
+
+    if total_weight == 0.0 {
+        return 0;
+    }
+
+    (K_FACTOR * change / total_weight).round() as i32
+}
+
+pub fn update_rating(con: &mut PgConnection, user_id: i32, change: i32) -> QueryResult<i32> {
+    update(users::table)
+        .filter(users::id.eq(user_id))
+        .set((
+            users::rating.eq(users::rating + change),
+            users::rated_games.eq(users::rated_games + 1),
+        ))
+        .returning(users::rating)
+        .get_result(con)
+}
+
+#[test]
+fn test_rating_change() {
+    let mut players = (1..=8)
+        .map(|placement| GameInstancePlayer {
+            user_id: Some(placement),
+            placement: Some(placement as u8),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(calculate_rating_change(&players[0], &players), 16);
+    assert_eq!(calculate_rating_change(&players[7], &players), -16);
+    assert_eq!(
+        calculate_rating_change(&players[3], &players)
+            + calculate_rating_change(&players[4], &players),
+        0
+    );
+
+    // Beating bots is worth less than beating players
+    players[7].user_id = None;
+    players[6].user_id = None;
+    assert_eq!(calculate_rating_change(&players[5], &players), -11);
+
+    // Underdogs gain more
+    players[0].rating = 800;
+    assert!(calculate_rating_change(&players[0], &players) > 16);
+}
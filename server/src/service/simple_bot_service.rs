@@ -6,7 +6,7 @@ use rand_core::{OsRng, SeedableRng};
 pub async fn perform_bot_turns(game: &mut GameInstance) -> Result<(), diesel::result::Error> {
     let mut rng = rand::rngs::StdRng::from_seed(OsRng.gen());
 
-    for bot in game.players.iter_mut().filter(|p| p.user_id.is_none()) {
+    for bot in game.players.iter_mut().filter(|p| p.is_bot() && !p.empty) {
         perform_bot_turn(bot, &mut rng).await;
     }
 
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use protocol::{
+    api::{find_endpoint, Access, Endpoint, Method},
+    codec::Encoding,
+    protocol::{ApiCall, Error, ErrorCode, Protocol},
+};
+use rocket::{
+    http::{ContentType, Header, Method as HttpMethod, Status},
+    local::asynchronous::Client,
+    Build, Request, Rocket,
+};
+
+const API_BASE: &str = "/api/v1";
+/// Streaming endpoints that can not be answered with a single reply
+const STREAMING_ENDPOINTS: &[&str] = &["poll", "connect"];
+/// Session of a dispatched call. Only trusted by the dispatching instance.
+const SESSION_HEADER: &str = "x-session-id";
+
+/// Marks the api instance that answers websocket calls
+struct LocalDispatch;
+
+/// Executes api calls received over a websocket. The calls are dispatched to
+/// a local instance of the api, so they pass the same guards, fairings and
+/// handlers as regular requests.
+#[derive(Clone)]
+pub struct ApiDispatcher {
+    client: Arc<Client>,
+}
+
+impl ApiDispatcher {
+    /// Launches `rocket` locally without binding a port
+    pub async fn new(rocket: Rocket<Build>) -> Result<Self, rocket::Error> {
+        let client = Client::untracked(rocket.manage(LocalDispatch)).await?;
+        Ok(Self {
+            client: Arc::new(client),
+        })
+    }
+
+    /// Answers the call with the response of the endpoint. The call is
+    /// authenticated by the session id, so it keeps working when the session
+    /// key is refreshed.
+    pub async fn dispatch(&self, session_id: i32, call: ApiCall) -> Protocol {
+        let reply = match self.execute(session_id, &call).await {
+            Ok(response) => response,
+            Err(error) => Protocol::NetworkingError(error),
+        };
+
+        Protocol::ApiReply(call.id, Box::new(reply))
+    }
+
+    async fn execute(&self, session_id: i32, call: &ApiCall) -> Result<Protocol, Error> {
+        let path = call.path.split('?').next().unwrap_or_default();
+        let Some(endpoint) = dispatched_endpoint(call.method, path) else {
+            return Err(ErrorCode::NotFound.into());
+        };
+
+        let mut request = self
+            .client
+            .req(
+                http_method(call.method),
+                format!("{}/{}", API_BASE, call.path),
+            )
+            .header(Header::new(SESSION_HEADER, session_id.to_string()))
+            .header(Header::new("Accept", Encoding::Json.content_type()))
+            .header(ContentType::JSON);
+        if let Some(body) = &call.body {
+            request.set_body(serde_json::to_vec(body).map_err(|_| ErrorCode::InvalidRequest)?);
+        }
+
+        let response = request.dispatch().await;
+        if response.status() == Status::NoContent {
+            return Ok(Protocol::EMPTY(format!("{}/{}", API_BASE, path)));
+        }
+
+        let body = response.into_bytes().await.ok_or(ErrorCode::Internal)?;
+        Encoding::Json.decode(&body).map_err(|err| {
+            warn!("Invalid response of {}: {:?}", endpoint.name, err);
+            ErrorCode::Internal.into()
+        })
+    }
+}
+
+/// Session of a call dispatched from a websocket. Requests reaching the
+/// server over the network can not set it.
+pub(crate) fn dispatched_session(req: &Request<'_>) -> Option<i32> {
+    req.rocket().state::<LocalDispatch>()?;
+    req.headers().get_one(SESSION_HEADER)?.parse().ok()
+}
+
+/// Endpoint of the call if it can be answered over the websocket. Public
+/// endpoints do not need the authenticated socket.
+fn dispatched_endpoint(method: Method, path: &str) -> Option<&'static Endpoint> {
+    find_endpoint(method, &format!("/{}", path))
+        .filter(|endpoint| endpoint.access != Access::Public)
+        .filter(|endpoint| !STREAMING_ENDPOINTS.contains(&endpoint.name))
+}
+
+fn http_method(method: Method) -> HttpMethod {
+    match method {
+        Method::Get => HttpMethod::Get,
+        Method::Post => HttpMethod::Post,
+        Method::Put => HttpMethod::Put,
+        Method::Patch => HttpMethod::Patch,
+        Method::Delete => HttpMethod::Delete,
+    }
+}
+
+#[test]
+fn test_dispatched_session() {
+    rocket::async_test(async {
+        let header = Header::new(SESSION_HEADER, "7");
+
+        let local = Client::untracked(rocket::build().manage(LocalDispatch))
+            .await
+            .expect("valid rocket instance");
+        assert_eq!(
+            dispatched_session(local.get("/").header(header.clone()).inner()),
+            Some(7)
+        );
+        assert_eq!(dispatched_session(local.get("/").inner()), None);
+
+        // The header of requests from the network is ignored
+        let server = Client::untracked(rocket::build())
+            .await
+            .expect("valid rocket instance");
+        assert_eq!(
+            dispatched_session(server.get("/").header(header).inner()),
+            None
+        );
+    })
+}
+
+#[test]
+fn test_dispatched_endpoint() {
+    assert_eq!(
+        dispatched_endpoint(Method::Put, "games/characters/1/2").map(|e| e.name),
+        Some("move_character")
+    );
+    assert_eq!(
+        dispatched_endpoint(Method::Get, "admin/games").map(|e| e.name),
+        Some("get_running_games")
+    );
+    assert!(dispatched_endpoint(Method::Post, "users").is_none());
+    assert!(dispatched_endpoint(Method::Get, "poll").is_none());
+    assert!(dispatched_endpoint(Method::Get, "games/unknown").is_none());
+}